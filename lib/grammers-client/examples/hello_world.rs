@@ -47,7 +47,7 @@ fn main() -> Result<()> {
     }
 
     println!("Sending message...");
-    client.send_message(&username[..], &message)?;
+    client.send_message(&username[..], message)?;
     println!("Message sent!");
 
     Ok(())
@@ -7,9 +7,7 @@
 use std::env;
 use std::io::{self, Write};
 
-use fallible_iterator::FallibleIterator;
-use grammers_client::Client;
-use grammers_session::TextSession;
+use grammers_client::prelude::*;
 
 fn ask_input(message: &str) -> io::Result<String> {
     let mut input = String::new();
@@ -50,7 +48,18 @@ fn main() -> io::Result<()> {
 
     let mut iter = client.iter_dialogs();
     while let Some(dialog) = iter.next()? {
-        println!("[{:>10}] {}", dialog.entity.id(), dialog.entity.display());
+        let preview = match dialog.last_message() {
+            Some(tl::enums::Message::Message(message)) => {
+                format!(" @ {}: {}", message.date, message.message)
+            }
+            _ => String::new(),
+        };
+        println!(
+            "[{:>10}] {}{}",
+            dialog.entity.id(),
+            dialog.entity.display(),
+            preview
+        );
     }
 
     Ok(())
@@ -0,0 +1,100 @@
+use fallible_iterator::FallibleIterator;
+use grammers_tl_types as tl;
+
+use crate::types;
+use crate::{Client, Error};
+
+const MAX_EVENTS_PER_REQUEST: i32 = 100;
+
+/// An iterator over the administrator log of a channel or megagroup, which
+/// returns the newest entries first.
+pub struct AdminLog<'a> {
+    client: &'a mut Client,
+    batch_stack: Vec<types::AdminLogEntry>,
+    done: bool,
+    request: tl::functions::channels::GetAdminLog,
+}
+
+impl<'a> AdminLog<'a> {
+    pub fn new(client: &'a mut Client, channel: tl::enums::InputChannel) -> Self {
+        Self {
+            client,
+            batch_stack: Vec::with_capacity(MAX_EVENTS_PER_REQUEST as usize),
+            done: false,
+            request: tl::functions::channels::GetAdminLog {
+                channel,
+                q: String::new(),
+                events_filter: None,
+                admins: None,
+                max_id: 0,
+                min_id: 0,
+                limit: MAX_EVENTS_PER_REQUEST,
+            },
+        }
+    }
+
+    /// Only returns the kinds of events enabled in the filter. By default,
+    /// all events are returned.
+    ///
+    /// This should be set before the iterator is advanced.
+    pub fn events(mut self, filter: tl::types::ChannelAdminLogEventsFilter) -> Self {
+        self.request.events_filter = Some(filter.into());
+        self
+    }
+
+    /// Only returns the events caused by the given administrators. By
+    /// default, events caused by any administrator are returned.
+    ///
+    /// This should be set before the iterator is advanced.
+    pub fn admins(mut self, admins: Vec<tl::enums::InputUser>) -> Self {
+        self.request.admins = Some(admins);
+        self
+    }
+
+    /// Only returns the events related to the given query, such as the
+    /// text of deleted messages.
+    ///
+    /// This should be set before the iterator is advanced.
+    pub fn query(mut self, query: &str) -> Self {
+        self.request.q = query.to_string();
+        self
+    }
+
+    /// If the batch index is beyond the buffer length, it fills the buffer.
+    fn ensure_buffer(&mut self) -> Result<(), Error> {
+        if self.batch_stack.is_empty() && !self.done {
+            self.fill_buffer()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn fill_buffer(&mut self) -> Result<(), Error> {
+        let results =
+            tl::types::channels::AdminLogResults::from(self.client.invoke(&self.request)??);
+
+        // Events always arrive sorted by ID in descending order.
+        let events = results.events;
+        if events.len() < self.request.limit as usize {
+            self.done = true;
+        }
+        if let Some(tl::enums::ChannelAdminLogEvent::ChannelAdminLogEvent(event)) = events.last() {
+            self.request.max_id = event.id;
+        }
+
+        // The stack is popped from the end, so the newest event goes last.
+        self.batch_stack
+            .extend(events.into_iter().rev().map(types::AdminLogEntry::from));
+        Ok(())
+    }
+}
+
+impl<'a> FallibleIterator for AdminLog<'a> {
+    type Item = types::AdminLogEntry;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        self.ensure_buffer()?;
+        Ok(self.batch_stack.pop())
+    }
+}
@@ -1,12 +1,11 @@
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::io;
 
 use fallible_iterator::FallibleIterator;
 use grammers_tl_types as tl;
 
 use crate::types;
-use crate::Client;
+use crate::{unknown_constructor, Client, Error};
 
 const MAX_DIALOGS_PER_REQUEST: i32 = 100;
 
@@ -18,26 +17,33 @@ pub struct Dialogs<'a> {
     entities: HashMap<i32, types::Entity>,
     messages: HashMap<(i32, i32), tl::enums::Message>,
     request: tl::functions::messages::GetDialogs,
+    skip_empty: bool,
 }
 
-// TODO more reusable methods to get ids from things
-fn peer_id(peer: &tl::enums::Peer) -> i32 {
-    match peer {
-        tl::enums::Peer::PeerUser(user) => user.user_id,
-        tl::enums::Peer::PeerChat(chat) => chat.chat_id,
-        tl::enums::Peer::PeerChannel(channel) => channel.channel_id,
+fn message_id(message: &tl::enums::Message) -> Option<(i32, i32)> {
+    match message {
+        tl::enums::Message::Message(message) => Some((
+            types::chat_id(message.out, message.from_id, &message.to_id),
+            message.id,
+        )),
+        tl::enums::Message::MessageService(message) => Some((
+            types::chat_id(message.out, message.from_id, &message.to_id),
+            message.id,
+        )),
+        // Includes `MessageEmpty`.
+        _ => None,
     }
 }
 
-fn message_id(message: &tl::enums::Message) -> Option<(i32, i32)> {
-    match message {
-        tl::enums::Message::Message(message) => {
-            // TODO this will probably fail in pm
-            Some((peer_id(&message.to_id), message.id))
+/// Returns `true` if the dialog has no messages, or if it belongs to a
+/// deleted user or a chat that was left.
+fn is_empty_dialog(dialog: &types::Dialog) -> bool {
+    dialog.dialog.top_message == 0
+        || match &dialog.entity {
+            types::Entity::User(user) => user.deleted,
+            types::Entity::Chat(chat) => chat.left || chat.deactivated,
+            types::Entity::Channel(channel) => channel.left,
         }
-        tl::enums::Message::MessageService(message) => Some((peer_id(&message.to_id), message.id)),
-        tl::enums::Message::MessageEmpty(_) => None,
-    }
 }
 
 impl<'a> Dialogs<'a> {
@@ -58,11 +64,22 @@ impl<'a> Dialogs<'a> {
                 limit: MAX_DIALOGS_PER_REQUEST,
                 hash: 0,
             },
+            skip_empty: false,
         }
     }
 
+    /// Whether dialogs without messages, with deleted users, or of chats
+    /// that were left should be skipped. By default, all dialogs are
+    /// returned.
+    ///
+    /// This should be set before the iterator is advanced.
+    pub fn skip_empty(mut self, skip_empty: bool) -> Self {
+        self.skip_empty = skip_empty;
+        self
+    }
+
     /// If the batch index is beyond the buffer length, it fills the buffer.
-    fn ensure_buffer(&mut self) -> io::Result<()> {
+    fn ensure_buffer(&mut self) -> Result<(), Error> {
         if self.batch_stack.is_empty() && !self.done {
             self.fill_buffer()
         } else {
@@ -107,23 +124,20 @@ impl<'a> Dialogs<'a> {
     }
 
     fn update_dialogs(&mut self, dialogs: Vec<tl::enums::Dialog>) {
-        dialogs
-            .into_iter()
-            .rev()
-            .for_each(move |dialog| match dialog {
-                tl::enums::Dialog::Dialog(dialog) => {
-                    let peer_id = peer_id(&dialog.peer);
-                    if let Some(entity) = self.entities.remove(&peer_id) {
-                        let last_message = self.messages.remove(&(peer_id, dialog.top_message));
-                        self.batch_stack.push(types::Dialog {
-                            dialog,
-                            entity,
-                            last_message,
-                        });
-                    }
+        dialogs.into_iter().rev().for_each(move |dialog| {
+            // Folders (`DialogFolder`) are skipped.
+            if let tl::enums::Dialog::Dialog(dialog) = dialog {
+                let peer_id = types::peer_id(&dialog.peer);
+                if let Some(entity) = self.entities.remove(&peer_id) {
+                    let last_message = self.messages.remove(&(peer_id, dialog.top_message));
+                    self.batch_stack.push(types::Dialog {
+                        dialog,
+                        entity,
+                        last_message,
+                    });
                 }
-                tl::enums::Dialog::DialogFolder(_) => {}
-            });
+            }
+        });
     }
 
     fn update_request_offsets(&mut self) {
@@ -133,7 +147,7 @@ impl<'a> Dialogs<'a> {
 
         // Find last dialog with a message
         for dialog in self.batch_stack.iter() {
-            if let Some(message) = &dialog.last_message {
+            if let Some(message) = dialog.last_message() {
                 match message {
                     tl::enums::Message::Message(message) => {
                         self.request.offset_id = message.id;
@@ -146,13 +160,15 @@ impl<'a> Dialogs<'a> {
                     tl::enums::Message::MessageEmpty(message) => {
                         self.request.offset_id = message.id;
                     }
+                    #[allow(unreachable_patterns)]
+                    _ => {}
                 }
                 break;
             }
         }
     }
 
-    fn fill_buffer(&mut self) -> io::Result<()> {
+    fn fill_buffer(&mut self) -> Result<(), Error> {
         match self.client.invoke(&self.request)?? {
             tl::enums::messages::Dialogs::Dialogs(tl::types::messages::Dialogs {
                 dialogs,
@@ -186,6 +202,8 @@ impl<'a> Dialogs<'a> {
                 self.total = Some(dialogs.count as usize);
                 self.done = true;
             }
+            #[allow(unreachable_patterns)]
+            _ => return Err(unknown_constructor().into()),
         }
         Ok(())
     }
@@ -193,16 +211,27 @@ impl<'a> Dialogs<'a> {
 
 impl<'a> FallibleIterator for Dialogs<'a> {
     type Item = types::Dialog;
-    type Error = io::Error;
+    type Error = Error;
 
     fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
-        self.ensure_buffer()?;
-        Ok(self.batch_stack.pop())
+        // The offsets of the next request are taken from the whole batch
+        // before any dialog is skipped, so pagination is not affected.
+        loop {
+            self.ensure_buffer()?;
+            match self.batch_stack.pop() {
+                Some(dialog) if self.skip_empty && is_empty_dialog(&dialog) => continue,
+                dialog => break Ok(dialog),
+            }
+        }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         if let Some(total) = self.total {
-            (total, Some(total))
+            if self.skip_empty {
+                (0, Some(total))
+            } else {
+                (total, Some(total))
+            }
         } else {
             (0, None)
         }
@@ -5,17 +5,40 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+mod adminlog;
 mod dialogs;
+mod media;
+mod message_queue;
+mod messages;
+pub mod passport;
+pub mod prelude;
+mod profile_photos;
+mod qr_login;
+mod request_stream;
 pub mod types;
+mod update_stream;
 
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fmt;
 use std::io;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use grammers_mtproto::errors::RPCError;
+pub use grammers_mtproto::errors::RPCError;
+pub use message_queue::QueuedMessage;
+pub use qr_login::QrLogin;
+pub use request_stream::RequestStream;
+pub use update_stream::UpdateStream;
+
+use grammers_crypto::two_factor_auth;
+use grammers_mtproto::errors::{DecryptionError, DeserializeError};
+use grammers_mtproto::AuthKey;
 use grammers_mtsender::{MTSender, RequestResult};
-use grammers_session::{MemorySession, Session};
+use grammers_session::{MemorySession, Session, UpdateState};
 use grammers_tl_types::{self as tl, Deserializable, Serializable, RPC};
+use log::{debug, info, warn};
 
 /// Socket addresses to Telegram datacenters, where the index into this array
 /// represents the data center ID.
@@ -31,12 +54,63 @@ const DC_ADDRESSES: [&str; 6] = [
     "91.108.56.190:443",
 ];
 
+/// Like `DC_ADDRESSES`, but with the IPv6 addresses of the datacenters, used
+/// when the IPv4 ones can't be reached (for example, in IPv6-only networks).
+const DC_ADDRESSES_IPV6: [&str; 6] = [
+    "",
+    "[2001:b28:f23d:f001::a]:443",
+    "[2001:67c:4e8:f002::a]:443",
+    "[2001:b28:f23d:f003::a]:443",
+    "[2001:67c:4e8:f004::a]:443",
+    "[2001:b28:f23f:f005::a]:443",
+];
+
+/// Like `DC_ADDRESSES`, but for Telegram's test servers, which use separate
+/// accounts and are meant for development (see [`Client::new_test`]).
+///
+/// [`Client::new_test`]: struct.Client.html#method.new_test
+const TEST_DC_ADDRESSES: [&str; 4] = [
+    "",
+    "149.154.175.10:443",
+    "149.154.167.40:443",
+    "149.154.175.117:443",
+];
+
 /// The DC ID to originally connect to.
 const DEFAULT_DC_ID: usize = 2;
 
+/// The maximum amount of updates to fetch per channel difference request.
+const CHANNEL_DIFFERENCE_LIMIT: i32 = 100;
+
+/// The maximum amount of chats in common to fetch per request.
+const COMMON_CHATS_LIMIT: i32 = 100;
+
+/// The maximum length, in characters, of the title of a group or channel.
+const MAX_CHAT_TITLE_LENGTH: usize = 255;
+
+/// The API ID used to initialize the connection when none is provided.
+// TODO user-provided api key
+const DEFAULT_API_ID: i32 = 6;
+
 /// When no locale is found, use this one instead.
 const DEFAULT_LOCALE: &str = "en";
 
+/// How many times all the addresses of a datacenter are tried when connecting
+/// before giving up.
+const CONNECT_ATTEMPTS: u32 = 3;
+
+/// How long to wait before trying all the addresses of a datacenter again,
+/// which is doubled after every failed attempt.
+const CONNECT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How many times a request is retried after Telegram fails with an internal
+/// error before giving up.
+const INTERNAL_ERROR_RETRIES: u32 = 2;
+
+/// How long to wait before retrying a request that failed with an internal
+/// error.
+const INTERNAL_ERROR_RETRY_DELAY: Duration = Duration::from_millis(500);
+
 /// A client capable of connecting to Telegram and invoking requests.
 pub struct Client {
     api_id: i32,
@@ -45,16 +119,44 @@ pub struct Client {
 
     /// The stored phone and its hash from the last `request_login_code` call.
     last_phone_hash: Option<(String, String)>,
+
+    /// Messages waiting to be sent by `send_queued`.
+    message_queue: message_queue::MessageQueue,
+
+    /// The datacenter addresses from the last configuration received.
+    dc_options: Vec<tl::types::DcOption>,
+
+    /// Whether IPv6 addresses should be tried before IPv4 ones.
+    prefer_ipv6: bool,
+
+    /// Whether the client is connected to the test servers instead of the
+    /// production ones.
+    test_mode: bool,
+
+    /// The logged-in user, fetched on the first call to `me`.
+    me: Option<tl::types::User>,
+
+    /// The length, in UTF-16 code units, at which `send_long_message`
+    /// splits the text, if it should be less than the limit.
+    max_message_length: Option<usize>,
+
+    /// The limits from the last configuration received.
+    limits: types::Limits,
+
+    /// Senders connected to datacenters other than the user's, authorized
+    /// as the logged-in user. These are used to download the files stored
+    /// in those datacenters.
+    exported_senders: HashMap<i32, MTSender>,
 }
 
 /// Implementors of this trait have a way to turn themselves into the
 /// desired input parameter.
 pub trait IntoInput<T> {
-    fn convert(&self, client: &mut Client) -> io::Result<T>;
+    fn convert(&self, client: &mut Client) -> Result<T, Error>;
 }
 
 impl IntoInput<tl::enums::InputPeer> for tl::types::User {
-    fn convert(&self, _client: &mut Client) -> io::Result<tl::enums::InputPeer> {
+    fn convert(&self, _client: &mut Client) -> Result<tl::enums::InputPeer, Error> {
         if let Some(access_hash) = self.access_hash {
             Ok(tl::enums::InputPeer::InputPeerUser(
                 tl::types::InputPeerUser {
@@ -63,68 +165,855 @@ impl IntoInput<tl::enums::InputPeer> for tl::types::User {
                 },
             ))
         } else {
-            Err(io::Error::new(
+            Err(io::Error::new(io::ErrorKind::NotFound, "user is missing access_hash").into())
+        }
+    }
+}
+
+impl IntoInput<tl::enums::InputPeer> for types::Entity {
+    fn convert(&self, _client: &mut Client) -> Result<tl::enums::InputPeer, Error> {
+        match (self, self.access_hash()) {
+            (types::Entity::Chat(_), _) | (_, Some(_)) => Ok(self.to_input_peer()),
+            (_, None) => {
+                Err(io::Error::new(io::ErrorKind::NotFound, "entity is missing access_hash").into())
+            }
+        }
+    }
+}
+
+impl IntoInput<tl::enums::InputPeer> for types::PackedChat {
+    fn convert(&self, _client: &mut Client) -> Result<tl::enums::InputPeer, Error> {
+        match (self.ty, self.access_hash) {
+            (types::PackedType::Chat, _) | (_, Some(_)) => Ok(self.to_input_peer()),
+            (_, None) => Err(io::Error::new(
                 io::ErrorKind::NotFound,
-                "user is missing access_hash",
-            ))
+                "packed chat is missing access_hash",
+            )
+            .into()),
         }
     }
 }
 
+/// Usernames are resolved to the user they belong to, except for `"me"`
+/// (which is too short to be a valid username) that refers to oneself,
+/// without the need to fetch any information about the logged-in user.
 impl IntoInput<tl::enums::InputPeer> for &str {
-    fn convert(&self, client: &mut Client) -> io::Result<tl::enums::InputPeer> {
-        if let Some(user) = client.resolve_username(self)? {
+    fn convert(&self, client: &mut Client) -> Result<tl::enums::InputPeer, Error> {
+        if self.eq_ignore_ascii_case("me") {
+            Ok(tl::types::InputPeerSelf {}.into())
+        } else if let Some(user) = client.resolve_username(self)? {
             user.convert(client)
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                "no user has that username",
-            ))
+            Err(io::Error::new(io::ErrorKind::NotFound, "no user has that username").into())
+        }
+    }
+}
+
+/// Invokes a request with `invoke`, and should the server report that the
+/// connection was not initialized, retries it a single time with `init_invoke`,
+/// which is expected to initialize the connection while invoking the request.
+fn retry_if_not_inited<S, T>(
+    state: &mut S,
+    invoke: impl FnOnce(&mut S) -> RequestResult<T>,
+    init_invoke: impl FnOnce(&mut S) -> RequestResult<T>,
+) -> RequestResult<T> {
+    match invoke(state)? {
+        Err(RPCError { ref name, .. }) if name == "CONNECTION_NOT_INITED" => init_invoke(state),
+        result => Ok(result),
+    }
+}
+
+/// Returns the ID of the channel the message was sent to, if any.
+fn message_channel_id(message: &tl::enums::Message) -> Option<i32> {
+    let to_id = match message {
+        tl::enums::Message::Message(message) => &message.to_id,
+        tl::enums::Message::MessageService(message) => &message.to_id,
+        // Includes `MessageEmpty`.
+        _ => return None,
+    };
+    match to_id {
+        tl::enums::Peer::PeerChannel(channel) => Some(channel.channel_id),
+        _ => None,
+    }
+}
+
+/// Returns the `pts` and `pts_count` of the update, along with the ID of the
+/// channel it belongs to (if any), when it changes the update state.
+fn update_pts(update: &tl::enums::Update) -> Option<(Option<i32>, i32, i32)> {
+    use tl::enums::Update as U;
+
+    match update {
+        U::UpdateNewMessage(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateEditMessage(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateDeleteMessages(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateReadHistoryInbox(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateReadHistoryOutbox(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateReadMessagesContents(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateWebPage(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateFolderPeers(u) => Some((None, u.pts, u.pts_count)),
+        U::UpdateNewChannelMessage(u) => {
+            message_channel_id(&u.message).map(|id| (Some(id), u.pts, u.pts_count))
+        }
+        U::UpdateEditChannelMessage(u) => {
+            message_channel_id(&u.message).map(|id| (Some(id), u.pts, u.pts_count))
+        }
+        U::UpdateDeleteChannelMessages(u) => Some((Some(u.channel_id), u.pts, u.pts_count)),
+        U::UpdateChannelWebPage(u) => Some((Some(u.channel_id), u.pts, u.pts_count)),
+        _ => None,
+    }
+}
+
+/// Advances the local `pts` if the new one immediately follows it. Updates
+/// that would leave a gap are ignored, so that `catch_up` fetches them.
+fn apply_pts(local: &mut i32, pts: i32, pts_count: i32) {
+    if *local + pts_count == pts {
+        *local = pts;
+    }
+}
+
+fn apply_update_pts(state: &mut UpdateState, update: &tl::enums::Update) {
+    match update_pts(update) {
+        Some((None, pts, pts_count)) => apply_pts(&mut state.pts, pts, pts_count),
+        Some((Some(channel_id), pts, pts_count)) => {
+            // Channels whose state is not tracked yet are left to `catch_up`.
+            if let Some(local) = state.channels.get_mut(&channel_id) {
+                apply_pts(local, pts, pts_count);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Advances the update state with the updates returned by a request, such as
+/// the new message after sending one.
+fn apply_updates_state(state: &mut UpdateState, updates: &tl::enums::Updates) {
+    use tl::enums::Updates as U;
+
+    let date = match updates {
+        U::UpdateShortMessage(u) => {
+            apply_pts(&mut state.pts, u.pts, u.pts_count);
+            u.date
+        }
+        U::UpdateShortChatMessage(u) => {
+            apply_pts(&mut state.pts, u.pts, u.pts_count);
+            u.date
+        }
+        U::UpdateShortSentMessage(u) => {
+            apply_pts(&mut state.pts, u.pts, u.pts_count);
+            u.date
+        }
+        U::UpdateShort(u) => {
+            apply_update_pts(state, &u.update);
+            u.date
+        }
+        U::UpdatesCombined(u) => {
+            u.updates
+                .iter()
+                .for_each(|update| apply_update_pts(state, update));
+            if u.seq != 0 && u.seq_start == state.seq + 1 {
+                state.seq = u.seq;
+            }
+            u.date
+        }
+        U::Updates(u) => {
+            u.updates
+                .iter()
+                .for_each(|update| apply_update_pts(state, update));
+            if u.seq != 0 && u.seq == state.seq + 1 {
+                state.seq = u.seq;
+            }
+            u.date
+        }
+        // Includes `UpdatesTooLong`.
+        _ => return,
+    };
+    state.date = state.date.max(date);
+}
+
+/// The error for data whose constructor is not known at compile time, which
+/// can only happen if the types are generated as non-exhaustive.
+pub(crate) fn unknown_constructor() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "unknown constructor")
+}
+
+/// Returns `true` if Telegram failed to process the request because of a
+/// transient problem on its side, so it's worth trying again.
+fn is_internal_error(error: &RPCError) -> bool {
+    error.code == -503 || error.name == "RPC_CALL_FAIL"
+}
+
+/// Invokes a request with `invoke`, and should Telegram fail with an internal
+/// error, retries it up to `INTERNAL_ERROR_RETRIES` times after `delay`.
+fn retry_if_internal_error<S, T>(
+    state: &mut S,
+    delay: Duration,
+    mut invoke: impl FnMut(&mut S) -> RequestResult<T>,
+) -> RequestResult<T> {
+    let mut retries = 0;
+    loop {
+        match invoke(state)? {
+            Err(ref error) if retries < INTERNAL_ERROR_RETRIES && is_internal_error(error) => {
+                warn!("{}, retrying in {:?}", error, delay);
+                thread::sleep(delay);
+                retries += 1;
+            }
+            result => break Ok(result),
+        }
+    }
+}
+
+/// Returns `true` if the error was caused by a message that failed the
+/// integrity check after being decrypted.
+fn is_integrity_error(error: &io::Error) -> bool {
+    matches!(
+        error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<DecryptionError>()),
+        Some(DecryptionError::MessageKeyMismatch)
+    )
+}
+
+/// Clones an input peer. The generated types don't implement `Clone`, so
+/// this goes through their serialized form, which is always valid.
+fn clone_input_peer(peer: &tl::enums::InputPeer) -> tl::enums::InputPeer {
+    tl::enums::InputPeer::from_bytes(&peer.to_bytes()).unwrap()
+}
+
+/// Converts an input peer known to be a user into an input user.
+fn input_user(peer: &tl::enums::InputPeer) -> tl::enums::InputUser {
+    match peer {
+        tl::enums::InputPeer::InputPeerSelf(_) => tl::types::InputUserSelf {}.into(),
+        tl::enums::InputPeer::InputPeerUser(user) => tl::types::InputUser {
+            user_id: user.user_id,
+            access_hash: user.access_hash,
+        }
+        .into(),
+        tl::enums::InputPeer::InputPeerUserFromMessage(user) => tl::types::InputUserFromMessage {
+            peer: clone_input_peer(&user.peer),
+            msg_id: user.msg_id,
+            user_id: user.user_id,
+        }
+        .into(),
+        _ => tl::types::InputUserEmpty {}.into(),
+    }
+}
+
+/// Converts an input peer known to be a channel into an input channel.
+fn input_channel(peer: &tl::enums::InputPeer) -> tl::enums::InputChannel {
+    match peer {
+        tl::enums::InputPeer::InputPeerChannel(channel) => tl::types::InputChannel {
+            channel_id: channel.channel_id,
+            access_hash: channel.access_hash,
+        }
+        .into(),
+        tl::enums::InputPeer::InputPeerChannelFromMessage(channel) => {
+            tl::types::InputChannelFromMessage {
+                peer: clone_input_peer(&channel.peer),
+                msg_id: channel.msg_id,
+                channel_id: channel.channel_id,
+            }
+            .into()
+        }
+        _ => tl::types::InputChannelEmpty {}.into(),
+    }
+}
+
+/// Whether media with a self-destruct timer can be sent to the peer, which
+/// is not the case for channels (including megagroups).
+fn supports_ttl(peer: &tl::enums::InputPeer) -> bool {
+    !matches!(
+        peer,
+        tl::enums::InputPeer::InputPeerChannel(_)
+            | tl::enums::InputPeer::InputPeerChannelFromMessage(_)
+    )
+}
+
+/// Returns the addresses that may be used to connect to a datacenter, in the
+/// order in which they should be tried.
+///
+/// The primary address goes first, followed by the rest of addresses of the
+/// datacenter found in `dc_options`, with those of the preferred IP version
+/// first. Addresses meant for media are only used as a last resort, and CDN
+/// addresses are never used.
+fn dc_addresses(
+    dc_options: &[tl::types::DcOption],
+    dc_id: i32,
+    primary: SocketAddr,
+    prefer_ipv6: bool,
+) -> Vec<SocketAddr> {
+    let mut options = dc_options
+        .iter()
+        .filter(|option| option.id == dc_id && !option.cdn)
+        .collect::<Vec<_>>();
+    // Sorting is stable, so the order is otherwise kept.
+    options.sort_by_key(|option| (option.media_only, option.ipv6 != prefer_ipv6));
+
+    let mut addresses = vec![primary];
+    for option in options {
+        let ip = match option.ip_address.parse() {
+            Ok(ip) => ip,
+            Err(_) => continue,
+        };
+        let address = SocketAddr::new(ip, option.port as u16);
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    addresses
+}
+
+/// Splits the text into chunks of at most `limit` UTF-16 code units each.
+///
+/// Chunks are preferably split after a newline, or otherwise after any
+/// whitespace, which is trimmed from their end. Words longer than the limit
+/// are split wherever needed. Chunks with only whitespace are skipped.
+fn split_message(text: &str, limit: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        // Find the longest prefix that fits, in bytes.
+        let mut length = 0;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            length += c.len_utf16();
+            if length > limit {
+                end = i;
+                break;
+            }
+        }
+
+        if end < rest.len() {
+            let prefix = &rest[..end];
+            if let Some(i) = prefix.rfind('\n') {
+                end = i + 1;
+            } else if let Some((i, c)) =
+                prefix.char_indices().rev().find(|(_, c)| c.is_whitespace())
+            {
+                end = i + c.len_utf8();
+            } else if end == 0 {
+                // A limit too small to fit even a single character.
+                end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+            }
+        }
+
+        let chunk = rest[..end].trim_end();
+        if !chunk.trim_start().is_empty() {
+            chunks.push(chunk);
+        }
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+/// Returns the built-in addresses of the datacenter, with the IPv6 one
+/// first if `prefer_ipv6` is set. These are used as a fallback when the
+/// addresses from the configuration are not known or can't be reached.
+fn static_dc_addresses(dc_id: i32, prefer_ipv6: bool) -> Vec<SocketAddr> {
+    if dc_id <= 0 {
+        return Vec::new();
+    }
+    let mut addresses = [&DC_ADDRESSES, &DC_ADDRESSES_IPV6]
+        .iter()
+        .filter_map(|table| table.get(dc_id as usize))
+        .map(|address| address.parse().unwrap())
+        .collect::<Vec<SocketAddr>>();
+    if prefer_ipv6 {
+        addresses.reverse();
+    }
+    addresses
+}
+
+/// Returns the built-in address of the datacenter, either in production or
+/// in the test servers, or `None` if the datacenter is unknown.
+fn static_dc_address(dc_id: i32, test_mode: bool) -> Option<SocketAddr> {
+    let table: &[&str] = if test_mode {
+        &TEST_DC_ADDRESSES
+    } else {
+        &DC_ADDRESSES
+    };
+    match table.get(dc_id as usize) {
+        Some(address) if dc_id > 0 => Some(address.parse().unwrap()),
+        _ => None,
+    }
+}
+
+/// Like `static_dc_addresses`, but the test servers only have one address
+/// per datacenter, and production addresses must never be used for them.
+fn fallback_dc_addresses(dc_id: i32, prefer_ipv6: bool, test_mode: bool) -> Vec<SocketAddr> {
+    if test_mode {
+        static_dc_address(dc_id, true).into_iter().collect()
+    } else {
+        static_dc_addresses(dc_id, prefer_ipv6)
+    }
+}
+
+/// Returns the code Telegram's test servers accept to sign in with the given
+/// test phone number, or `None` if it is not one.
+///
+/// Test numbers have the form `99966XYYYY`, where `X` is the ID of the
+/// datacenter the account lives in and `YYYY` are any digits, and their
+/// login code is `X` repeated five times.
+pub fn test_login_code(phone: &str) -> Option<String> {
+    let digits = phone
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    if digits.len() != 10 || !digits.starts_with("99966") {
+        return None;
+    }
+    match &digits[5..6] {
+        "1" | "2" | "3" => Some(digits[5..6].repeat(5)),
+        _ => None,
+    }
+}
+
+/// Connects to the given datacenter before any configuration is known,
+/// trying its built-in addresses after `primary` until one succeeds.
+fn connect_with_fallback(dc_id: i32, primary: SocketAddr, test_mode: bool) -> io::Result<MTSender> {
+    let mut addresses = vec![primary];
+    for address in fallback_dc_addresses(dc_id, primary.is_ipv6(), test_mode) {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    let mut last_error = None;
+    for address in addresses {
+        match MTSender::connect(address) {
+            Ok(sender) => return Ok(sender),
+            Err(error) => {
+                warn!("failed to connect to {}: {}", address, error);
+                last_error = Some(error);
+            }
         }
     }
+    // There's always at least the primary address.
+    Err(last_error.unwrap())
 }
 
-/// Generate a random message ID suitable for `send_message`.
-fn generate_random_message_id() -> i64 {
+/// Generate a random ID suitable for `send_message` or uploaded files.
+fn generate_random_id() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("system time is before epoch")
         .as_nanos() as i64
 }
 
+/// Returns the parameters of a password key derivation algorithm, or an
+/// error if the algorithm is not known.
+fn password_algo(
+    algo: &tl::enums::PasswordKdfAlgo,
+) -> io::Result<two_factor_auth::PasswordAlgo<'_>> {
+    match algo {
+        tl::enums::PasswordKdfAlgo::PasswordKdfAlgoSHA256SHA256PBKDF2HMACSHA512iter100000SHA256ModPow(
+            algo,
+        ) => Ok(two_factor_auth::PasswordAlgo {
+            salt1: &algo.salt1,
+            salt2: &algo.salt2,
+            g: algo.g,
+            p: &algo.p,
+        }),
+        // Includes `PasswordKdfAlgoUnknown`.
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unknown password algorithm, please update the library",
+        )),
+    }
+}
+
+/// Builds the proof that the current password is known, which is required
+/// by the requests that modify or use it. If the account has no password,
+/// the empty proof is returned and `current` is ignored.
+fn check_password_srp(
+    password: &tl::types::account::Password,
+    current: &str,
+) -> io::Result<tl::enums::InputCheckPasswordSRP> {
+    let (algo, srp_b, srp_id) = match (&password.current_algo, &password.srp_b, password.srp_id) {
+        (Some(algo), Some(srp_b), Some(srp_id)) => (algo, srp_b, srp_id),
+        _ => return Ok(tl::types::InputCheckPasswordEmpty {}.into()),
+    };
+
+    let check = two_factor_auth::check_password(&password_algo(algo)?, srp_b, current.as_bytes())?;
+    Ok(tl::types::InputCheckPasswordSRP {
+        srp_id,
+        a: check.a,
+        m1: check.m1,
+    }
+    .into())
+}
+
+/// Returns the documents of the stickers, skipping the empty ones.
+fn sticker_documents(stickers: Vec<tl::enums::Document>) -> Vec<tl::types::Document> {
+    stickers
+        .into_iter()
+        .filter_map(|document| match document {
+            tl::enums::Document::Document(document) => Some(document),
+            // Includes `DocumentEmpty`.
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the messages contained in any of the possible responses to a
+/// request for messages, skipping the deleted ones.
+fn extract_messages(messages: tl::enums::messages::Messages) -> Vec<types::Message> {
+    let messages = match messages {
+        tl::enums::messages::Messages::Messages(messages) => messages.messages,
+        tl::enums::messages::Messages::MessagesSlice(messages) => messages.messages,
+        tl::enums::messages::Messages::ChannelMessages(messages) => messages.messages,
+        // Includes `MessagesNotModified`.
+        _ => Vec::new(),
+    };
+
+    messages
+        .into_iter()
+        .filter_map(types::Message::from_raw)
+        .collect()
+}
+
+/// Returns the first group or channel found in the updates. When these are
+/// the result of creating a chat, that's the one which was created.
+fn extract_created_chat(updates: tl::enums::Updates) -> Option<types::Entity> {
+    let chats = match updates {
+        tl::enums::Updates::Updates(updates) => updates.chats,
+        tl::enums::Updates::UpdatesCombined(updates) => updates.chats,
+        _ => return None,
+    };
+
+    chats.into_iter().find_map(|chat| match chat {
+        tl::enums::Chat::Chat(chat) => Some(types::Entity::Chat(chat)),
+        tl::enums::Chat::Channel(channel) => Some(types::Entity::Channel(channel)),
+        _ => None,
+    })
+}
+
+/// Extracts the ID of the message that was sent by a request with the given
+/// `random_id` from the updates Telegram returned for it, if any.
+///
+/// Sending messages to different kinds of chats produces different updates:
+/// private conversations and small groups produce `UpdateNewMessage`, while
+/// channels produce `UpdateNewChannelMessage`, and sometimes a short update
+/// is returned instead. This handles all of them.
+fn extract_message_from_updates(updates: &tl::enums::Updates, random_id: i64) -> Option<i32> {
+    fn message_id(message: &tl::enums::Message) -> i32 {
+        match message {
+            tl::enums::Message::Message(message) => message.id,
+            tl::enums::Message::MessageService(message) => message.id,
+            tl::enums::Message::MessageEmpty(message) => message.id,
+            #[allow(unreachable_patterns)]
+            _ => 0,
+        }
+    }
+
+    fn from_update(update: &tl::enums::Update, random_id: i64) -> Option<i32> {
+        match update {
+            tl::enums::Update::UpdateMessageID(update) if update.random_id == random_id => {
+                Some(update.id)
+            }
+            tl::enums::Update::UpdateNewMessage(update) => Some(message_id(&update.message)),
+            tl::enums::Update::UpdateNewChannelMessage(update) => Some(message_id(&update.message)),
+            tl::enums::Update::UpdateNewScheduledMessage(update) => {
+                Some(message_id(&update.message))
+            }
+            _ => None,
+        }
+    }
+
+    fn from_updates(updates: &[tl::enums::Update], random_id: i64) -> Option<i32> {
+        // `UpdateMessageID` is the only one which can be correlated with the
+        // `random_id`, so prefer it over any new message updates.
+        updates
+            .iter()
+            .find_map(|update| match update {
+                tl::enums::Update::UpdateMessageID(update) if update.random_id == random_id => {
+                    Some(update.id)
+                }
+                _ => None,
+            })
+            .or_else(|| {
+                updates
+                    .iter()
+                    .find_map(|update| from_update(update, random_id))
+            })
+    }
+
+    match updates {
+        tl::enums::Updates::UpdateShortSentMessage(update) => Some(update.id),
+        tl::enums::Updates::UpdateShortMessage(update) => Some(update.id),
+        tl::enums::Updates::UpdateShortChatMessage(update) => Some(update.id),
+        tl::enums::Updates::UpdateShort(update) => from_update(&update.update, random_id),
+        tl::enums::Updates::Updates(updates) => from_updates(&updates.updates, random_id),
+        tl::enums::Updates::UpdatesCombined(updates) => from_updates(&updates.updates, random_id),
+        // Includes `UpdatesTooLong`.
+        _ => None,
+    }
+}
+
+/// The error type returned by most of the client's methods.
+///
+/// Failures which are specific to a method, such as entering the wrong
+/// login code, are reported with their own error type instead.
+#[derive(Debug)]
+pub enum Error {
+    /// An input/output error occurred, for example, while reading a file.
+    Io(io::Error),
+    /// Telegram responded to the request with an error.
+    Rpc(RPCError),
+    /// Data received from Telegram could not be understood.
+    Deserialize(io::Error),
+    /// The connection to Telegram was lost, with the error which caused it.
+    Disconnected(io::Error),
+}
+
+impl Error {
+    /// Returns the error Telegram responded with, if this is an RPC error.
+    ///
+    /// This can be used to react to specific errors, such as not being
+    /// allowed to write in a chat (`CHAT_WRITE_FORBIDDEN`).
+    pub fn rpc_error(&self) -> Option<&RPCError> {
+        match self {
+            Self::Rpc(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) | Self::Deserialize(error) | Self::Disconnected(error) => Some(error),
+            Self::Rpc(error) => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "i/o error: {}", error),
+            Self::Rpc(error) => write!(f, "{}", error),
+            Self::Deserialize(error) => write!(f, "failed to deserialize data: {}", error),
+            Self::Disconnected(error) => {
+                write!(f, "the connection to telegram was lost: {}", error)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        // Lower layers wrap their errors inside `io::Error`, so recover them.
+        match error.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected => return Self::Disconnected(error),
+            _ => {}
+        }
+
+        let inner = match error.get_ref() {
+            Some(inner) => inner,
+            None => return Self::Io(error),
+        };
+        if inner.is::<RPCError>() {
+            match error.into_inner().unwrap().downcast::<RPCError>() {
+                Ok(error) => Self::Rpc(*error),
+                Err(_) => unreachable!(),
+            }
+        } else if inner.is::<DeserializeError>() || inner.is::<tl::errors::UnexpectedConstructor>()
+        {
+            Self::Deserialize(error)
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+impl From<RPCError> for Error {
+    fn from(error: RPCError) -> Self {
+        Self::Rpc(error)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) | Error::Deserialize(error) | Error::Disconnected(error) => error,
+            Error::Rpc(error) => error.into(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SignInError {
-    IO(io::Error),
     NoCodeSent,
     SignUpRequired {
         terms_of_service: Option<tl::types::help::TermsOfService>,
     },
     InvalidCode,
-    Other(RPCError),
+    /// Any other error, which is not specific to this method.
+    Error(Error),
 }
 
-impl From<io::Error> for SignInError {
-    fn from(error: io::Error) -> Self {
-        Self::IO(error)
-    }
+/// Implements the conversions into the error types specific to a method,
+/// whose `Error` variant holds any other error.
+macro_rules! impl_from_error {
+    ($($ty:ident),*) => {
+        $(
+            impl From<Error> for $ty {
+                fn from(error: Error) -> Self {
+                    Self::Error(error)
+                }
+            }
+
+            impl From<io::Error> for $ty {
+                fn from(error: io::Error) -> Self {
+                    Self::Error(error.into())
+                }
+            }
+        )*
+    };
+}
+
+#[derive(Debug)]
+pub enum PinDialogError {
+    /// The maximum amount of pinned dialogs has been reached, and another
+    /// dialog must be unpinned before pinning a new one.
+    TooManyPinned,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum SendStickerError {
+    /// The sticker set the sticker belongs to is not valid (for example,
+    /// because it was deleted).
+    InvalidStickerSet,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum EditMessageMediaError {
+    /// No media was provided, or the media is empty.
+    EmptyMedia,
+    /// The media is not valid (for example, it refers to a file which does
+    /// not exist or can't be used in this message).
+    InvalidMedia,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum SendMediaError {
+    /// A self-destruct timer was requested, but the chat does not support
+    /// self-destructing media (such as channels and megagroups).
+    TtlNotSupported,
+    /// Telegram could not download the media from the given URL.
+    UrlFetchFailed,
+    /// The URL does not point to media that can be sent.
+    UrlMediaEmpty,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum AddChatUserError {
+    /// The user's privacy settings don't allow them to be added to chats.
+    PrivacyRestricted,
+    /// The user is already a member of the chat.
+    AlreadyParticipant,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum ClickButtonError {
+    /// The bot did not answer the button press in time.
+    BotTimeout,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum MessageLinkError {
+    /// The chat is not a channel or megagroup, so its messages have no link.
+    NotChannel,
+    /// Any other error, which is not specific to this method.
+    Error(Error),
+}
+
+#[derive(Debug)]
+pub enum SendQueuedError {
+    /// Telegram refused to send the queued message, which was removed from
+    /// the queue.
+    Rejected {
+        message: QueuedMessage,
+        error: RPCError,
+    },
+    /// Any other error, which is not specific to this method.
+    Error(Error),
 }
 
+impl_from_error!(
+    SignInError,
+    PinDialogError,
+    SendStickerError,
+    EditMessageMediaError,
+    SendMediaError,
+    AddChatUserError,
+    ClickButtonError,
+    MessageLinkError,
+    SendQueuedError
+);
+
 impl Client {
     /// Returns a new client instance connected to Telegram and returns it.
     ///
     /// This method will generate a new authorization key and connect to a
     /// default datacenter. To prevent logging in every single time, use
     /// [`with_session`] instead, which will reuse a previous session.
-    pub fn new() -> io::Result<Self> {
+    pub fn new() -> Result<Self, Error> {
         // TODO we probably should just require a session storage as input
-        let mut sender = MTSender::connect(DC_ADDRESSES[DEFAULT_DC_ID])?;
+        let mut sender = connect_with_fallback(
+            DEFAULT_DC_ID as i32,
+            DC_ADDRESSES[DEFAULT_DC_ID].parse().unwrap(),
+            false,
+        )?;
+        sender.generate_auth_key()?;
+        Self::with_sender(sender, Box::new(MemorySession::new()), DEFAULT_API_ID)
+    }
+
+    /// Like [`new`], but connects to Telegram's test servers instead, which
+    /// are meant for development and integration tests that should not risk
+    /// a real account.
+    ///
+    /// The test servers have their own accounts, with phone numbers of the
+    /// form `99966XYYYY` (`X` being the datacenter ID, `1` to `3`, and `YYYY`
+    /// random digits). No SMS is ever sent: the login code is always `X`
+    /// repeated five times (see [`test_login_code`]). The same `api_id` and
+    /// `api_hash` from <https://my.telegram.org> work on both environments.
+    ///
+    /// Test accounts may be deleted at any time, and numbers that were not
+    /// used before need to sign up first.
+    ///
+    /// [`new`]: #method.new
+    /// [`test_login_code`]: fn.test_login_code.html
+    pub fn new_test() -> Result<Self, Error> {
+        let mut sender = connect_with_fallback(
+            DEFAULT_DC_ID as i32,
+            TEST_DC_ADDRESSES[DEFAULT_DC_ID].parse().unwrap(),
+            true,
+        )?;
         sender.generate_auth_key()?;
-        Self::with_sender(sender, Box::new(MemorySession::new()))
+        let mut client = Self::with_sender(sender, Box::new(MemorySession::new()), DEFAULT_API_ID)?;
+        client.test_mode = true;
+        Ok(client)
     }
 
     /// Configures a new client instance from an existing session and returns
     /// it.
-    pub fn with_session(mut session: Box<dyn Session>) -> io::Result<Self> {
+    pub fn with_session(mut session: Box<dyn Session>) -> Result<Self, Error> {
         // TODO this doesn't look clean, and configuring the authkey
         //      on the sender this way also seems a bit weird.
         let auth_key;
@@ -142,36 +1031,100 @@ impl Client {
             session.save()?;
         }
 
-        let mut sender = MTSender::connect(server_address)?;
+        let mut sender = connect_with_fallback(server_id, server_address, false)?;
         if let Some(auth_key) = auth_key {
             sender.set_auth_key(auth_key);
+            if let Some(offset) = session.get_time_offset() {
+                sender.set_time_offset(offset);
+            }
         } else {
             let auth_key = sender.generate_auth_key()?;
             session.set_auth_key_data(server_id, &auth_key.to_bytes());
             session.save()?;
         }
 
-        Self::with_sender(sender, session)
+        Self::with_sender(sender, session, DEFAULT_API_ID)
+    }
+
+    /// Connects to the given datacenter reusing an authorization key that
+    /// was obtained elsewhere (for example, by a different library) and
+    /// returns a new client instance.
+    ///
+    /// No session file is involved: the key is only kept in memory, so it
+    /// is up to the caller to store it if it's needed again. The `api_id`
+    /// is the one used to initialize the connection.
+    ///
+    /// An error is returned if the datacenter is unknown or the key is not
+    /// exactly 256 bytes long.
+    pub fn from_auth_key(dc_id: i32, auth_key: &[u8], api_id: i32) -> Result<Self, Error> {
+        let server_address = match static_dc_address(dc_id, false) {
+            Some(address) => address,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown datacenter id: {}", dc_id),
+                )
+                .into())
+            }
+        };
+
+        let auth_key = AuthKey::from_bytes(auth_key)?.to_bytes();
+
+        let mut session = MemorySession::new();
+        session.set_user_datacenter(dc_id, &server_address);
+        session.set_auth_key_data(dc_id, &auth_key);
+
+        let mut sender = connect_with_fallback(dc_id, server_address, false)?;
+        sender.set_auth_key(auth_key);
+        Self::with_sender(sender, Box::new(session), api_id)
     }
 
     /// Creates a client instance with a sender
-    fn with_sender(sender: MTSender, session: Box<dyn Session>) -> io::Result<Self> {
-        // TODO user-provided api key
+    fn with_sender(
+        sender: MTSender,
+        session: Box<dyn Session>,
+        api_id: i32,
+    ) -> Result<Self, Error> {
         let mut client = Client {
-            api_id: 6,
+            api_id,
             sender,
             session,
             last_phone_hash: None,
+            message_queue: message_queue::MessageQueue::new(),
+            dc_options: Vec::new(),
+            prefer_ipv6: false,
+            test_mode: false,
+            max_message_length: None,
+            limits: types::Limits::default(),
+            me: None,
+            exported_senders: HashMap::new(),
         };
         client.init_connection()?;
+
+        // By now we have received messages from the server, so the time
+        // offset is known and can be reused on the next connection.
+        client.session.set_time_offset(client.sender.time_offset());
+        client.session.save()?;
         Ok(client)
     }
 
     /// Returns `true` if the current account is authorized. Otherwise,
     /// logging in will be required before being able to invoke requests.
-    pub fn is_authorized(&mut self) -> io::Result<bool> {
+    ///
+    /// If the account is authorized and the session has no update state yet,
+    /// the current state is stored, so that [`catch_up`] can later fetch
+    /// the updates that occur from now on.
+    ///
+    /// [`catch_up`]: #method.catch_up
+    pub fn is_authorized(&mut self) -> Result<bool, Error> {
         match self.invoke(&tl::functions::updates::GetState {})? {
-            Ok(_) => Ok(true),
+            Ok(state) => {
+                let state = tl::types::updates::State::from(state);
+                if self.session.get_update_state().is_none() {
+                    self.seed_update_state(&state)?;
+                }
+                Ok(true)
+            }
             Err(_) => Ok(false),
         }
     }
@@ -183,7 +1136,7 @@ impl Client {
         phone: &str,
         api_id: i32,
         api_hash: &str,
-    ) -> io::Result<tl::types::auth::SentCode> {
+    ) -> Result<tl::types::auth::SentCode, Error> {
         let request = tl::functions::auth::SendCode {
             phone_number: phone.to_string(),
             api_id,
@@ -199,27 +1152,7 @@ impl Client {
         let sent_code: tl::types::auth::SentCode = match self.invoke(&request)? {
             Ok(x) => x.into(),
             Err(RPCError { name, value, .. }) if name == "PHONE_MIGRATE" => {
-                let server_id = value.unwrap() as i32;
-                let server_address = DC_ADDRESSES[server_id as usize].parse().unwrap();
-                self.session.set_user_datacenter(server_id, &server_address);
-                self.session.save()?;
-
-                // Since we are not logged in (we're literally requesting for
-                // the code to login now), there's no need to export the current
-                // authorization and re-import it at a different datacenter.
-                //
-                // Just connect and generate a new authorization key with it
-                // before trying again. Don't want to replace `self.sender`
-                // unless the entire process succeeds.
-                self.sender = {
-                    let mut sender = MTSender::connect(server_address)?;
-                    let auth_key = sender.generate_auth_key()?;
-                    self.session
-                        .set_auth_key_data(server_id, &auth_key.to_bytes());
-                    self.session.save()?;
-                    sender
-                };
-
+                self.migrate_to_dc(value.unwrap() as i32)?;
                 self.init_invoke(&request)??.into()
             }
             Err(e) => return Err(e.into()),
@@ -229,6 +1162,54 @@ impl Client {
         Ok(sent_code)
     }
 
+    /// Moves the account's home to a different datacenter before logging in,
+    /// connecting to it with a new authorization key.
+    fn migrate_to_dc(&mut self, server_id: i32) -> Result<(), Error> {
+        info!("migrating to datacenter {}", server_id);
+        let server_address = static_dc_address(server_id, self.test_mode).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown datacenter id: {}", server_id),
+            )
+        })?;
+        self.session.set_user_datacenter(server_id, &server_address);
+        self.session.save()?;
+
+        // Since we are not logged in (we're literally requesting for
+        // the code to login now), there's no need to export the current
+        // authorization and re-import it at a different datacenter.
+        //
+        // Just connect and generate a new authorization key with it
+        // before trying again. Don't want to replace `self.sender`
+        // unless the entire process succeeds.
+        self.sender = {
+            let mut sender = self.connect_to_dc(server_id, server_address)?;
+            let auth_key = sender.generate_auth_key()?;
+            self.session
+                .set_auth_key_data(server_id, &auth_key.to_bytes());
+            self.session.save()?;
+            sender
+        };
+        Ok(())
+    }
+
+    /// Starts logging in by scanning a QR code from another Telegram
+    /// application where the account is already logged in, instead of
+    /// using a login code.
+    ///
+    /// The returned [`QrLogin`] has the URL to render as a QR code, and
+    /// can [`wait`] until it's scanned.
+    ///
+    /// [`QrLogin`]: struct.QrLogin.html
+    /// [`wait`]: struct.QrLogin.html#method.wait
+    pub fn export_login_token(
+        &mut self,
+        api_id: i32,
+        api_hash: &str,
+    ) -> Result<QrLogin<'_>, Error> {
+        QrLogin::new(self, api_id, api_hash)
+    }
+
     /// Signs in to the user account. To have the login code be sent, use
     /// [`request_login_code`] first.
     ///
@@ -240,6 +1221,7 @@ impl Client {
             return Err(SignInError::NoCodeSent);
         };
 
+        self.me = None;
         match self.invoke(&tl::functions::auth::SignIn {
             phone_number,
             phone_code_hash,
@@ -254,15 +1236,18 @@ impl Client {
                     terms_of_service: x.terms_of_service.map(|tos| tos.into()),
                 })
             }
+            #[allow(unreachable_patterns)]
+            Ok(_) => Err(unknown_constructor().into()),
             Err(RPCError { name, .. }) if name.starts_with("PHONE_CODE_") => {
                 Err(SignInError::InvalidCode)
             }
-            Err(error) => Err(SignInError::Other(error)),
+            Err(error) => Err(SignInError::Error(error.into())),
         }
     }
 
     /// Signs in to the bot account associated with this token.
-    pub fn bot_sign_in(&mut self, token: &str, api_id: i32, api_hash: &str) -> io::Result<()> {
+    pub fn bot_sign_in(&mut self, token: &str, api_id: i32, api_hash: &str) -> Result<(), Error> {
+        self.me = None;
         self.invoke(&tl::functions::auth::ImportBotAuthorization {
             flags: 0,
             api_id,
@@ -273,120 +1258,2448 @@ impl Client {
         Ok(())
     }
 
-    /// Resolves a username into the user that owns it, if any.
-    pub fn resolve_username(&mut self, username: &str) -> io::Result<Option<tl::types::User>> {
-        let tl::enums::contacts::ResolvedPeer::ResolvedPeer(tl::types::contacts::ResolvedPeer {
-            peer,
-            users,
-            ..
-        }) = self.invoke(&tl::functions::contacts::ResolveUsername {
-            username: username.into(),
+    /// Fetches the information about the logged-in user.
+    ///
+    /// This always makes a request. Use [`me`] to reuse the result of a
+    /// previous call instead.
+    ///
+    /// [`me`]: #method.me
+    pub fn get_me(&mut self) -> Result<tl::types::User, Error> {
+        let mut users = self.invoke(&tl::functions::users::GetUsers {
+            id: vec![tl::types::InputUserSelf {}.into()],
         })??;
 
-        match peer {
-            tl::enums::Peer::PeerUser(tl::types::PeerUser { user_id }) => {
-                return Ok(users
-                    .into_iter()
-                    .filter_map(|user| match user {
-                        tl::enums::User::User(user) => {
-                            if user.id == user_id {
-                                Some(user)
-                            } else {
-                                None
-                            }
-                        }
-                        tl::enums::User::UserEmpty(_) => None,
-                    })
-                    .next());
+        match users.pop() {
+            Some(tl::enums::User::User(user)) => Ok(user),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the logged-in user was not returned",
+            )
+            .into()),
+        }
+    }
+
+    /// Returns the logged-in user, which is fetched only the first time and
+    /// cached afterwards. The cache is cleared when signing in again.
+    ///
+    /// Use [`refresh_me`] to fetch it again, for example after the profile
+    /// changes.
+    ///
+    /// [`refresh_me`]: #method.refresh_me
+    pub fn me(&mut self) -> Result<&tl::types::User, Error> {
+        if self.me.is_none() {
+            self.me = Some(self.get_me()?);
+        }
+        Ok(self.me.as_ref().unwrap())
+    }
+
+    /// Fetches the logged-in user again, replacing the cached value used by
+    /// [`me`].
+    ///
+    /// [`me`]: #method.me
+    pub fn refresh_me(&mut self) -> Result<&tl::types::User, Error> {
+        self.me = None;
+        self.me()
+    }
+
+    /// Returns the two-factor authentication settings of the current account,
+    /// such as whether a password is set and its hint.
+    pub fn get_password_info(&mut self) -> Result<types::PasswordInfo, Error> {
+        let password: tl::types::account::Password = self
+            .invoke(&tl::functions::account::GetPassword {})??
+            .into();
+        Ok((&password).into())
+    }
+
+    /// Enables two-factor authentication on an account without a password,
+    /// with an optional hint to help remember it.
+    ///
+    /// To change an existing password, use [`change_password`] instead.
+    ///
+    /// [`change_password`]: #method.change_password
+    pub fn set_password(&mut self, new_password: &str, hint: Option<&str>) -> Result<(), Error> {
+        self.update_password("", new_password, hint)
+    }
+
+    /// Changes the two-factor authentication password of the account, which
+    /// requires the current password.
+    pub fn change_password(
+        &mut self,
+        current_password: &str,
+        new_password: &str,
+        hint: Option<&str>,
+    ) -> Result<(), Error> {
+        self.update_password(current_password, new_password, hint)
+    }
+
+    /// Returns a temporary password, which can be used instead of the
+    /// two-factor authentication password of the account to authorize
+    /// payments for the next `period` seconds.
+    ///
+    /// The returned `valid_until` is the Unix timestamp at which the
+    /// temporary password expires, and a new one should be requested.
+    pub fn get_tmp_password(
+        &mut self,
+        password: &str,
+        period: i32,
+    ) -> Result<tl::types::account::TmpPassword, Error> {
+        let current: tl::types::account::Password = self
+            .invoke(&tl::functions::account::GetPassword {})??
+            .into();
+
+        let tmp_password: tl::types::account::TmpPassword = self
+            .invoke(&tl::functions::account::GetTmpPassword {
+                password: check_password_srp(&current, password)?,
+                period,
+            })??
+            .into();
+        Ok(tmp_password)
+    }
+
+    fn update_password(
+        &mut self,
+        current_password: &str,
+        new_password: &str,
+        hint: Option<&str>,
+    ) -> Result<(), Error> {
+        let password: tl::types::account::Password = self
+            .invoke(&tl::functions::account::GetPassword {})??
+            .into();
+
+        let new_algo = match &password.new_algo {
+            tl::enums::PasswordKdfAlgo::PasswordKdfAlgoSHA256SHA256PBKDF2HMACSHA512iter100000SHA256ModPow(
+                algo,
+            ) => tl::types::PasswordKdfAlgoSHA256SHA256PBKDF2HMACSHA512iter100000SHA256ModPow {
+                salt1: two_factor_auth::extend_salt(&algo.salt1)?,
+                salt2: algo.salt2.clone(),
+                g: algo.g,
+                p: algo.p.clone(),
+            },
+            // Includes `PasswordKdfAlgoUnknown`.
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unknown password algorithm, please update the library",
+                ).into())
             }
-            tl::enums::Peer::PeerChat(_) => {}
-            tl::enums::Peer::PeerChannel(_) => {}
+        };
+        let new_password_hash = two_factor_auth::compute_password_hash(
+            &two_factor_auth::PasswordAlgo {
+                salt1: &new_algo.salt1,
+                salt2: &new_algo.salt2,
+                g: new_algo.g,
+                p: &new_algo.p,
+            },
+            new_password.as_bytes(),
+        )?;
+
+        self.invoke(&tl::functions::account::UpdatePasswordSettings {
+            password: check_password_srp(&password, current_password)?,
+            new_settings: tl::types::account::PasswordInputSettings {
+                new_algo: Some(new_algo.into()),
+                new_password_hash: Some(new_password_hash),
+                hint: Some(hint.unwrap_or("").to_string()),
+                email: None,
+                new_secure_settings: None,
+            }
+            .into(),
+        })??;
+        Ok(())
+    }
+
+    /// Returns all the sessions that are currently logged in to this account,
+    /// including the current one.
+    pub fn get_active_sessions(&mut self) -> Result<Vec<tl::types::Authorization>, Error> {
+        let authorizations: tl::types::account::Authorizations = self
+            .invoke(&tl::functions::account::GetAuthorizations {})??
+            .into();
+
+        Ok(authorizations
+            .authorizations
+            .into_iter()
+            .map(|authorization| authorization.into())
+            .collect())
+    }
+
+    /// Logs out the session with the given `hash`, as returned by
+    /// [`get_active_sessions`].
+    ///
+    /// [`get_active_sessions`]: #method.get_active_sessions
+    pub fn reset_session(&mut self, hash: i64) -> Result<(), Error> {
+        self.invoke(&tl::functions::account::ResetAuthorization { hash })??;
+        Ok(())
+    }
+
+    /// Logs out every session logged in to this account except the current
+    /// one.
+    pub fn reset_all_other_sessions(&mut self) -> Result<(), Error> {
+        self.invoke(&tl::functions::auth::ResetAuthorizations {})??;
+        Ok(())
+    }
+
+    /// Changes whether the current account appears as online to others.
+    ///
+    /// Telegram considers the account offline again after a while, so this
+    /// must be called periodically to remain online.
+    pub fn set_online(&mut self, online: bool) -> Result<(), Error> {
+        self.invoke(&tl::functions::account::UpdateStatus { offline: !online })??;
+        Ok(())
+    }
+
+    /// Returns the rules deciding who can see the desired information or
+    /// perform the desired action.
+    pub fn get_privacy(
+        &mut self,
+        key: types::PrivacyKey,
+    ) -> Result<Vec<types::PrivacyRule>, Error> {
+        let rules = self.invoke(&tl::functions::account::GetPrivacy {
+            key: key.to_input_privacy_key(),
+        })??;
+        Ok(types::PrivacyRule::from_raw(rules))
+    }
+
+    /// Replaces the rules deciding who can see the desired information or
+    /// perform the desired action, and returns the rules now in effect.
+    ///
+    /// An error with kind `InvalidInput` is returned, without making any
+    /// request, if the access hash of a user in the rules is unknown.
+    pub fn set_privacy(
+        &mut self,
+        key: types::PrivacyKey,
+        rules: &[types::PrivacyRule],
+    ) -> Result<Vec<types::PrivacyRule>, Error> {
+        let rules = self.invoke(&tl::functions::account::SetPrivacy {
+            key: key.to_input_privacy_key(),
+            rules: rules
+                .iter()
+                .map(|rule| rule.to_input_privacy_rule())
+                .collect::<io::Result<_>>()?,
+        })??;
+        Ok(types::PrivacyRule::from_raw(rules))
+    }
+
+    /// Changes the default notification settings for all peers of the given
+    /// kind. Only the settings that were set in `settings` are changed.
+    pub fn set_global_notify_settings(
+        &mut self,
+        peer_kind: types::NotifyPeerKind,
+        settings: types::NotifySettings,
+    ) -> Result<(), Error> {
+        self.invoke(&tl::functions::account::UpdateNotifySettings {
+            peer: peer_kind.to_input_notify_peer(),
+            settings: settings.to_input_settings(),
+        })??;
+        Ok(())
+    }
+
+    /// Returns the default notification settings for all peers of the given
+    /// kind.
+    pub fn get_notify_settings(
+        &mut self,
+        peer_kind: types::NotifyPeerKind,
+    ) -> Result<tl::types::PeerNotifySettings, Error> {
+        let settings: tl::types::PeerNotifySettings = self
+            .invoke(&tl::functions::account::GetNotifySettings {
+                peer: peer_kind.to_input_notify_peer(),
+            })??
+            .into();
+        Ok(settings)
+    }
+
+    /// Resolves a username into the user that owns it, if any.
+    pub fn resolve_username(&mut self, username: &str) -> Result<Option<tl::types::User>, Error> {
+        let tl::types::contacts::ResolvedPeer { peer, users, .. } = self
+            .invoke(&tl::functions::contacts::ResolveUsername {
+                username: username.into(),
+            })??
+            .into();
+
+        // Usernames of chats and channels (`PeerChat` and `PeerChannel`) are
+        // not resolved.
+        if let tl::enums::Peer::PeerUser(tl::types::PeerUser { user_id }) = peer {
+            return Ok(users
+                .into_iter()
+                .filter_map(|user| match user {
+                    tl::enums::User::User(user) => {
+                        if user.id == user_id {
+                            Some(user)
+                        } else {
+                            None
+                        }
+                    }
+                    // Includes `UserEmpty`.
+                    _ => None,
+                })
+                .next());
         }
 
         Ok(None)
     }
 
-    /// Sends a text message to the desired chat.
-    pub fn send_message<C: IntoInput<tl::enums::InputPeer>>(
+    /// Finds the user with the given phone number among the contacts of
+    /// the logged-in user, returning `None` if there is none.
+    ///
+    /// Any character other than digits (such as `+`, spaces or dashes) is
+    /// ignored. Telegram offers no way to resolve phone numbers that are
+    /// not in the contacts, so these must be imported first.
+    pub fn resolve_phone(&mut self, phone: &str) -> Result<Option<types::Entity>, Error> {
+        let phone = phone
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>();
+        if phone.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .iter_contacts()?
+            .find(|user| user.phone.as_deref() == Some(phone.as_str()))
+            .map(types::Entity::User))
+    }
+
+    /// Searches for users and chats whose name or username contains the
+    /// given query, including public ones the account has never seen.
+    ///
+    /// Results that are in the account's contacts or dialogs come first,
+    /// followed by the global results. The returned entities contain the
+    /// access hash needed to interact with them.
+    pub fn search_global(&mut self, query: &str, limit: i32) -> Result<Vec<types::Entity>, Error> {
+        let tl::types::contacts::Found {
+            my_results,
+            results,
+            chats,
+            users,
+        } = self
+            .invoke(&tl::functions::contacts::Search {
+                q: query.into(),
+                limit,
+            })??
+            .into();
+
+        // User and chat IDs may collide, so they are kept apart.
+        let mut user_map = HashMap::new();
+        for user in users {
+            if let tl::enums::User::User(user) = user {
+                user_map.insert(user.id, types::Entity::User(user));
+            }
+        }
+        let mut chat_map = HashMap::new();
+        for chat in chats {
+            match chat {
+                tl::enums::Chat::Chat(chat) => {
+                    chat_map.insert(chat.id, types::Entity::Chat(chat));
+                }
+                tl::enums::Chat::Channel(channel) => {
+                    chat_map.insert(channel.id, types::Entity::Channel(channel));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(my_results
+            .into_iter()
+            .chain(results)
+            .filter_map(|peer| match peer {
+                tl::enums::Peer::PeerUser(user) => user_map.remove(&user.user_id),
+                tl::enums::Peer::PeerChat(chat) => chat_map.remove(&chat.chat_id),
+                tl::enums::Peer::PeerChannel(channel) => chat_map.remove(&channel.channel_id),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Sends a text message to the desired chat, and returns the ID of the
+    /// message that was sent.
+    ///
+    /// The message may be either borrowed or owned, in which case it will be
+    /// moved into the request without being copied.
+    pub fn send_message<C: IntoInput<tl::enums::InputPeer>, M: Into<String>>(
+        &mut self,
+        chat: C,
+        message: M,
+    ) -> Result<i32, Error> {
+        let chat = chat.convert(self)?;
+        self.send_text(chat, message.into(), None)
+    }
+
+    /// Sends a text message that may be longer than what Telegram allows,
+    /// by splitting it into several messages sent one after another, and
+    /// returns the IDs of all the messages that were sent.
+    ///
+    /// The text is preferably split on newlines, or otherwise on whitespace.
+    /// The maximum length of each message can be changed with
+    /// [`set_max_message_length`].
+    ///
+    /// [`set_max_message_length`]: #method.set_max_message_length
+    pub fn send_long_message<C: IntoInput<tl::enums::InputPeer>>(
         &mut self,
         chat: C,
         message: &str,
-    ) -> io::Result<()> {
+    ) -> Result<Vec<i32>, Error> {
+        let chat = chat.convert(self)?;
+        split_message(message, self.max_message_length())
+            .into_iter()
+            .map(|chunk| self.send_text(clone_input_peer(&chat), chunk.to_string(), None))
+            .collect()
+    }
+
+    /// Sends a text message along with a custom reply keyboard, and returns
+    /// the ID of the message that was sent.
+    ///
+    /// The keyboard may also be used to remove the keyboard currently shown
+    /// to the users, or to force them to reply to the message. Only bots can
+    /// send keyboards.
+    pub fn send_message_with_keyboard<C: IntoInput<tl::enums::InputPeer>, M: Into<String>>(
+        &mut self,
+        chat: C,
+        message: M,
+        keyboard: &types::ReplyKeyboard,
+    ) -> Result<i32, Error> {
+        let markup = keyboard.to_reply_markup()?;
+        let chat = chat.convert(self)?;
+        self.send_text(chat, message.into(), Some(markup))
+    }
+
+    fn send_text(
+        &mut self,
+        chat: tl::enums::InputPeer,
+        message: String,
+        reply_markup: Option<tl::enums::ReplyMarkup>,
+    ) -> Result<i32, Error> {
+        let random_id = generate_random_id();
+        let mut request = tl::functions::messages::SendMessage::builder(chat, message, random_id);
+        if let Some(reply_markup) = reply_markup {
+            request = request.reply_markup(reply_markup);
+        }
+        let updates = self.invoke_and_process_updates(&request.build())??;
+
+        extract_message_from_updates(&updates, random_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the sent message was not found in the updates",
+            )
+            .into()
+        })
+    }
+
+    /// Notifies the other user of a private chat that a screenshot of the
+    /// conversation was taken, optionally in reply to a message, and returns
+    /// the ID of the service message that was sent.
+    pub fn send_screenshot_notification<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        reply_to: Option<i32>,
+    ) -> Result<i32, Error> {
+        let chat = chat.convert(self)?;
+        let random_id = generate_random_id();
+        let updates = self.invoke_and_process_updates(
+            &tl::functions::messages::SendScreenshotNotification {
+                peer: chat,
+                reply_to_msg_id: reply_to.unwrap_or(0),
+                random_id,
+            },
+        )??;
+
+        extract_message_from_updates(&updates, random_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the sent message was not found in the updates",
+            )
+            .into()
+        })
+    }
+
+    /// Sends an existing sticker to the desired chat, and returns the ID of
+    /// the message that was sent.
+    ///
+    /// The sticker is referenced by its document, which can be found in the
+    /// sticker sets returned by Telegram (see [`get_sticker_sets`]).
+    ///
+    /// [`get_sticker_sets`]: #method.get_sticker_sets
+    pub fn send_sticker<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        sticker: tl::enums::InputDocument,
+    ) -> Result<i32, SendStickerError> {
+        let chat = chat.convert(self)?;
+        let media = tl::types::InputMediaDocument {
+            id: sticker,
+            ttl_seconds: None,
+        };
+        match self.send_media(chat, media.into())? {
+            Ok(message_id) => Ok(message_id),
+            Err(RPCError { name, .. }) if name == "STICKERSET_INVALID" => {
+                Err(SendStickerError::InvalidStickerSet)
+            }
+            Err(error) => Err(SendStickerError::Error(error.into())),
+        }
+    }
+
+    /// Sends a previously-uploaded photo to the desired chat, and returns the
+    /// ID of the message that was sent.
+    ///
+    /// If `ttl_seconds` is set, the photo will self-destruct that many seconds
+    /// after being opened. This is only possible in private chats and small
+    /// groups, and [`SendMediaError::TtlNotSupported`] is returned otherwise,
+    /// without sending anything.
+    ///
+    /// The file can be obtained with [`upload_file`].
+    ///
+    /// [`SendMediaError::TtlNotSupported`]: enum.SendMediaError.html#variant.TtlNotSupported
+    /// [`upload_file`]: #method.upload_file
+    pub fn send_photo<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        file: tl::enums::InputFile,
+        ttl_seconds: Option<i32>,
+    ) -> Result<i32, SendMediaError> {
+        let chat = chat.convert(self)?;
+        if ttl_seconds.is_some() && !supports_ttl(&chat) {
+            return Err(SendMediaError::TtlNotSupported);
+        }
+        let media = tl::types::InputMediaUploadedPhoto {
+            file,
+            stickers: None,
+            ttl_seconds,
+        };
+        match self.send_media(chat, media.into())? {
+            Ok(message_id) => Ok(message_id),
+            Err(error) => Err(SendMediaError::Error(error.into())),
+        }
+    }
+
+    /// Sends media built with [`types::InputMedia`] to the desired chat,
+    /// and returns the ID of the message that was sent.
+    ///
+    /// [`types::InputMedia`]: types/struct.InputMedia.html
+    pub fn send_input_media<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        media: types::InputMedia,
+    ) -> Result<i32, SendMediaError> {
+        let chat = chat.convert(self)?;
+        match self.send_media(chat, media.media)? {
+            Ok(message_id) => Ok(message_id),
+            Err(RPCError { name, .. }) if name == "WEBPAGE_CURL_FAILED" => {
+                Err(SendMediaError::UrlFetchFailed)
+            }
+            Err(RPCError { name, .. }) if name == "WEBPAGE_MEDIA_EMPTY" => {
+                Err(SendMediaError::UrlMediaEmpty)
+            }
+            Err(error) => Err(SendMediaError::Error(error.into())),
+        }
+    }
+
+    /// Sends a previously-uploaded file as a document to the desired chat,
+    /// and returns the ID of the message that was sent.
+    ///
+    /// The document keeps the name the file was uploaded with. `ttl_seconds`
+    /// behaves the same as in [`send_photo`].
+    ///
+    /// [`send_photo`]: #method.send_photo
+    pub fn send_document<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        file: tl::enums::InputFile,
+        mime_type: &str,
+        ttl_seconds: Option<i32>,
+    ) -> Result<i32, SendMediaError> {
+        let chat = chat.convert(self)?;
+        if ttl_seconds.is_some() && !supports_ttl(&chat) {
+            return Err(SendMediaError::TtlNotSupported);
+        }
+        let file_name = match &file {
+            tl::enums::InputFile::InputFile(file) => file.name.clone(),
+            tl::enums::InputFile::InputFileBig(file) => file.name.clone(),
+            #[allow(unreachable_patterns)]
+            _ => return Err(SendMediaError::Error(unknown_constructor().into())),
+        };
+        let media = tl::types::InputMediaUploadedDocument {
+            nosound_video: false,
+            file,
+            thumb: None,
+            mime_type: mime_type.to_string(),
+            attributes: vec![tl::types::DocumentAttributeFilename { file_name }.into()],
+            stickers: None,
+            ttl_seconds,
+        };
+        match self.send_media(chat, media.into())? {
+            Ok(message_id) => Ok(message_id),
+            Err(error) => Err(SendMediaError::Error(error.into())),
+        }
+    }
+
+    /// Replaces the media of a message previously sent to the desired chat,
+    /// leaving its text untouched.
+    ///
+    /// The media must already be known to Telegram, such as a file that was
+    /// uploaded before or a document from a different message.
+    pub fn edit_message_media<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        message_id: i32,
+        media: tl::enums::InputMedia,
+    ) -> Result<(), EditMessageMediaError> {
         let chat = chat.convert(self)?;
-        self.invoke(&tl::functions::messages::SendMessage {
+        match self.invoke_and_process_updates(&tl::functions::messages::EditMessage {
             no_webpage: false,
-            silent: false,
-            background: false,
-            clear_draft: false,
             peer: chat,
-            reply_to_msg_id: None,
-            message: message.into(),
-            random_id: generate_random_message_id(),
+            id: message_id,
+            message: None,
+            media: Some(media),
             reply_markup: None,
             entities: None,
             schedule_date: None,
-        })??;
-        Ok(())
+        })? {
+            Ok(_) => Ok(()),
+            Err(RPCError { name, .. }) if name == "MEDIA_EMPTY" => {
+                Err(EditMessageMediaError::EmptyMedia)
+            }
+            Err(RPCError { name, .. }) if name == "MEDIA_INVALID" => {
+                Err(EditMessageMediaError::InvalidMedia)
+            }
+            Err(error) => Err(EditMessageMediaError::Error(error.into())),
+        }
     }
 
-    pub fn iter_dialogs(&mut self) -> dialogs::Dialogs {
-        dialogs::Dialogs::new(self)
+    /// Returns all the sticker sets installed by the current account.
+    pub fn get_sticker_sets(&mut self) -> Result<Vec<tl::types::StickerSet>, Error> {
+        match self.invoke(&tl::functions::messages::GetAllStickers { hash: 0 })?? {
+            tl::enums::messages::AllStickers::AllStickers(stickers) => Ok(stickers
+                .sets
+                .into_iter()
+                .map(tl::types::StickerSet::from)
+                .collect()),
+            // Includes `AllStickersNotModified`, which is not possible since no
+            // hash was provided.
+            _ => Ok(Vec::new()),
+        }
     }
 
-    /// Initializes the connection with Telegram. If this is never done on
-    /// a fresh session, then Telegram won't know which layer to use and a
-    /// very old one will be used (which we will fail to understand).
-    fn init_connection(&mut self) -> io::Result<()> {
-        // TODO store config
-        let _config = self.init_invoke(&tl::functions::help::GetConfig {})??;
-        Ok(())
+    /// Returns the sticker set with the given short name (as found in the
+    /// `t.me/addstickers/` links), along with the documents of its stickers,
+    /// which can be used to [`send_sticker`].
+    ///
+    /// `None` is returned if no sticker set has that name.
+    ///
+    /// [`send_sticker`]: #method.send_sticker
+    pub fn get_sticker_set(
+        &mut self,
+        short_name: &str,
+    ) -> Result<Option<tl::types::messages::StickerSet>, Error> {
+        match self.invoke(&tl::functions::messages::GetStickerSet {
+            stickerset: tl::types::InputStickerSetShortName {
+                short_name: short_name.to_string(),
+            }
+            .into(),
+        })? {
+            Ok(set) => Ok(Some(set.into())),
+            Err(RPCError { name, .. }) if name == "STICKERSET_INVALID" => Ok(None),
+            Err(error) => Err(error.into()),
+        }
     }
 
-    /// Wraps the request in `invokeWithLayer(initConnection(...))` and
-    /// invokes that. Should be used by the first request after connect.
-    fn init_invoke<R: RPC>(&mut self, request: &R) -> RequestResult<R::Return> {
-        let info = os_info::get();
+    /// Returns the sticker sets whose stickers are attached to the given
+    /// photo or document, along with the sticker used as their cover. The
+    /// short name of each set can be found in its `set`.
+    ///
+    /// Sets covered by several stickers are returned with the first of
+    /// them as their cover.
+    pub fn get_attached_stickers(
+        &mut self,
+        media: tl::enums::InputStickeredMedia,
+    ) -> Result<Vec<tl::types::StickerSetCovered>, Error> {
+        Ok(self
+            .invoke(&tl::functions::messages::GetAttachedStickers { media })??
+            .into_iter()
+            .filter_map(|covered| match covered {
+                tl::enums::StickerSetCovered::StickerSetCovered(covered) => Some(covered),
+                tl::enums::StickerSetCovered::StickerSetMultiCovered(covered) => {
+                    Some(tl::types::StickerSetCovered {
+                        set: covered.set,
+                        cover: covered
+                            .covers
+                            .into_iter()
+                            .next()
+                            .unwrap_or_else(|| tl::types::DocumentEmpty { id: 0 }.into()),
+                    })
+                }
+                #[allow(unreachable_patterns)]
+                _ => None,
+            })
+            .collect())
+    }
 
-        let mut system_lang_code = locate_locale::system();
-        if system_lang_code.is_empty() {
-            system_lang_code.push_str(DEFAULT_LOCALE);
+    /// Returns the stickers the current account used recently, most recent
+    /// first, whose documents can be used to [`send_sticker`] right away.
+    ///
+    /// [`send_sticker`]: #method.send_sticker
+    pub fn get_recent_stickers(&mut self) -> Result<Vec<tl::types::Document>, Error> {
+        match self.invoke(&tl::functions::messages::GetRecentStickers {
+            attached: false,
+            hash: 0,
+        })?? {
+            tl::enums::messages::RecentStickers::RecentStickers(stickers) => {
+                Ok(sticker_documents(stickers.stickers))
+            }
+            // Includes `RecentStickersNotModified`, which is not possible since no
+            // hash was provided.
+            _ => Ok(Vec::new()),
         }
+    }
 
-        let mut lang_code = locate_locale::user();
-        if lang_code.is_empty() {
-            lang_code.push_str(DEFAULT_LOCALE);
+    /// Returns the stickers the current account marked as favorite, whose
+    /// documents can be used to [`send_sticker`] right away.
+    ///
+    /// [`send_sticker`]: #method.send_sticker
+    pub fn get_faved_stickers(&mut self) -> Result<Vec<tl::types::Document>, Error> {
+        match self.invoke(&tl::functions::messages::GetFavedStickers { hash: 0 })?? {
+            tl::enums::messages::FavedStickers::FavedStickers(stickers) => {
+                Ok(sticker_documents(stickers.stickers))
+            }
+            // Includes `FavedStickersNotModified`, which is not possible since no
+            // hash was provided.
+            _ => Ok(Vec::new()),
         }
+    }
 
-        // TODO figure out what we're doing wrong because Telegram seems to
-        //      reply some constructor we are unaware of, even though we
-        //      explicitly did invokeWithLayer. this will fail, because
-        //      we want to return the right type (before we ignored it).
-        //
-        // a second call to getConfig will work just fine though.
-        //
-        // this also seems to have triggered RPC_CALL_FAIL
-        let data = self.invoke(&tl::functions::InvokeWithLayer {
-            layer: tl::LAYER,
-            query: tl::functions::InitConnection {
-                api_id: self.api_id,
-                device_model: format!("{} {}", info.os_type(), info.bitness()),
-                system_version: info.version().to_string(),
-                app_version: env!("CARGO_PKG_VERSION").into(),
-                system_lang_code,
-                lang_pack: "".into(),
-                lang_code,
-                proxy: None,
-                query: request.to_bytes().into(),
-            }
-            .to_bytes()
-            .into(),
-        })??;
+    /// Sends media which Telegram already has (such as a previously-uploaded
+    /// document) to the desired chat, and returns the ID of the message that
+    /// was sent.
+    fn send_media(
+        &mut self,
+        chat: tl::enums::InputPeer,
+        media: tl::enums::InputMedia,
+    ) -> RequestResult<i32> {
+        let random_id = generate_random_id();
+        let updates =
+            match self.invoke_and_process_updates(&tl::functions::messages::SendMedia {
+                silent: false,
+                background: false,
+                clear_draft: false,
+                peer: chat,
+                reply_to_msg_id: None,
+                media,
+                message: String::new(),
+                random_id,
+                reply_markup: None,
+                entities: None,
+                schedule_date: None,
+            })? {
+                Ok(updates) => updates,
+                Err(error) => return Ok(Err(error)),
+            };
 
-        Ok(Ok(R::Return::from_bytes(&data.0)?))
+        match extract_message_from_updates(&updates, random_id) {
+            Some(message_id) => Ok(Ok(message_id)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the sent message was not found in the updates",
+            )),
+        }
     }
 
-    /// Invokes a raw request, and returns its result.
-    pub fn invoke<R: RPC>(&mut self, request: &R) -> RequestResult<R::Return> {
-        self.sender.invoke(request)
+    /// Pins or unpins the dialog with the desired chat, so that it shows
+    /// at the top of the dialog list.
+    pub fn pin_dialog<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        pinned: bool,
+    ) -> Result<(), PinDialogError> {
+        let chat = chat.convert(self)?;
+        match self.invoke(&tl::functions::messages::ToggleDialogPin {
+            pinned,
+            peer: tl::types::InputDialogPeer { peer: chat }.into(),
+        })? {
+            Ok(_) => Ok(()),
+            Err(RPCError { name, .. }) if name == "PINNED_DIALOGS_TOO_MUCH" => {
+                Err(PinDialogError::TooManyPinned)
+            }
+            Err(error) => Err(PinDialogError::Error(error.into())),
+        }
+    }
+
+    /// Marks or unmarks the dialog with the desired chat as unread, without
+    /// changing which messages have been read.
+    ///
+    /// Nothing happens if the dialog was already in the desired state.
+    pub fn mark_dialog_unread<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        unread: bool,
+    ) -> Result<(), Error> {
+        let chat = chat.convert(self)?;
+        // A `false` result means nothing changed, which is fine.
+        self.invoke(&tl::functions::messages::MarkDialogUnread {
+            unread,
+            peer: tl::types::InputDialogPeer { peer: chat }.into(),
+        })??;
+        Ok(())
+    }
+
+    /// Clears the message drafts in every chat at once.
+    pub fn clear_all_drafts(&mut self) -> Result<(), Error> {
+        if self.invoke(&tl::functions::messages::ClearAllDrafts {})?? {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the drafts could not be cleared",
+            )
+            .into())
+        }
+    }
+
+    /// Creates a new small group with the given title and users, and returns
+    /// it.
+    ///
+    /// Users can't be added to small groups without inviting them, so at
+    /// least one user must be provided.
+    pub fn create_group(
+        &mut self,
+        title: &str,
+        users: &[tl::enums::InputUser],
+    ) -> Result<types::Entity, Error> {
+        let updates = self.invoke_and_process_updates(&tl::functions::messages::CreateChat {
+            users: users
+                .iter()
+                .map(|user| tl::enums::InputUser::from_bytes(&user.to_bytes()).unwrap())
+                .collect(),
+            title: title.to_string(),
+        })??;
+
+        extract_created_chat(updates).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the created group was not found in the updates",
+            )
+            .into()
+        })
+    }
+
+    /// Creates a new channel with the given title and description, and
+    /// returns it. If `megagroup` is `true`, the channel will be a group
+    /// where everyone can write instead of a broadcast channel.
+    ///
+    /// The returned channel includes its access hash, so it can be used as
+    /// input right away.
+    pub fn create_channel(
+        &mut self,
+        title: &str,
+        about: &str,
+        megagroup: bool,
+    ) -> Result<types::Entity, Error> {
+        let updates =
+            self.invoke_and_process_updates(&tl::functions::channels::CreateChannel {
+                broadcast: !megagroup,
+                megagroup,
+                title: title.to_string(),
+                about: about.to_string(),
+                geo_point: None,
+                address: None,
+            })??;
+
+        extract_created_chat(updates).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the created channel was not found in the updates",
+            )
+            .into()
+        })
+    }
+
+    /// Changes the title of the desired group or channel.
+    ///
+    /// An error with kind `InvalidInput` is returned, without making any
+    /// request, if the title is empty or too long, or the chat is not a
+    /// group or channel. Nothing happens if the title was already the same.
+    pub fn edit_chat_title<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        title: &str,
+    ) -> Result<(), Error> {
+        use tl::enums::InputPeer as P;
+
+        let length = title.chars().count();
+        if length == 0 || length > MAX_CHAT_TITLE_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the title must have between 1 and {} characters",
+                    MAX_CHAT_TITLE_LENGTH
+                ),
+            )
+            .into());
+        }
+
+        let chat = chat.convert(self)?;
+        let result = match &chat {
+            P::InputPeerChat(chat) => self
+                .invoke_and_process_updates(&tl::functions::messages::EditChatTitle {
+                    chat_id: chat.chat_id,
+                    title: title.to_string(),
+                })?
+                .map(drop),
+            P::InputPeerChannel(_) | P::InputPeerChannelFromMessage(_) => self
+                .invoke_and_process_updates(&tl::functions::channels::EditTitle {
+                    channel: input_channel(&chat),
+                    title: title.to_string(),
+                })?
+                .map(drop),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "only the title of groups and channels can be changed",
+                )
+                .into())
+            }
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(RPCError { name, .. }) if name == "CHAT_NOT_MODIFIED" => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Changes the description of the desired group or channel.
+    ///
+    /// Nothing happens if the description was already the same.
+    pub fn edit_chat_about<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        about: &str,
+    ) -> Result<(), Error> {
+        let chat = chat.convert(self)?;
+        match self.invoke(&tl::functions::messages::EditChatAbout {
+            peer: chat,
+            about: about.to_string(),
+        })? {
+            Ok(_) => Ok(()),
+            Err(RPCError { name, .. }) if name == "CHAT_ABOUT_NOT_MODIFIED" => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Adds the desired user to a group or channel, forwarding them up to
+    /// `fwd_limit` of the latest messages when the chat is a basic group.
+    ///
+    /// An error with kind `InvalidInput` is returned, without making any
+    /// request, if the chat is not a group or channel or the user is not
+    /// a user.
+    pub fn add_chat_user<C, U>(
+        &mut self,
+        chat: C,
+        user: U,
+        fwd_limit: i32,
+    ) -> Result<(), AddChatUserError>
+    where
+        C: IntoInput<tl::enums::InputPeer>,
+        U: IntoInput<tl::enums::InputPeer>,
+    {
+        use tl::enums::InputPeer as P;
+
+        let chat = chat.convert(self)?;
+        let user = self.chat_user(user)?;
+        let result = match &chat {
+            P::InputPeerChat(chat) => self
+                .invoke_and_process_updates(&tl::functions::messages::AddChatUser {
+                    chat_id: chat.chat_id,
+                    user_id: user,
+                    fwd_limit,
+                })?
+                .map(drop),
+            P::InputPeerChannel(_) | P::InputPeerChannelFromMessage(_) => self
+                .invoke_and_process_updates(&tl::functions::channels::InviteToChannel {
+                    channel: input_channel(&chat),
+                    users: vec![user],
+                })?
+                .map(drop),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "users can only be added to groups and channels",
+                )
+                .into())
+            }
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(RPCError { name, .. }) if name == "USER_PRIVACY_RESTRICTED" => {
+                Err(AddChatUserError::PrivacyRestricted)
+            }
+            Err(RPCError { name, .. }) if name == "USER_ALREADY_PARTICIPANT" => {
+                Err(AddChatUserError::AlreadyParticipant)
+            }
+            Err(error) => Err(AddChatUserError::Error(error.into())),
+        }
+    }
+
+    /// Removes the desired user from a group or channel.
+    ///
+    /// Users removed from a channel are not banned, so they can join it
+    /// again later. An error with kind `InvalidInput` is returned, without
+    /// making any request, if the chat is not a group or channel or the
+    /// user is not a user.
+    pub fn delete_chat_user<C, U>(&mut self, chat: C, user: U) -> Result<(), Error>
+    where
+        C: IntoInput<tl::enums::InputPeer>,
+        U: IntoInput<tl::enums::InputPeer>,
+    {
+        use tl::enums::InputPeer as P;
+
+        let chat = chat.convert(self)?;
+        let user = self.chat_user(user)?;
+        match &chat {
+            P::InputPeerChat(chat) => {
+                self.invoke_and_process_updates(&tl::functions::messages::DeleteChatUser {
+                    chat_id: chat.chat_id,
+                    user_id: user,
+                })??;
+            }
+            P::InputPeerChannel(_) | P::InputPeerChannelFromMessage(_) => {
+                // Channels have no way to remove a user other than banning
+                // them, so they're banned and immediately unbanned.
+                let user_bytes = user.to_bytes();
+                for &view_messages in &[true, false] {
+                    self.invoke_and_process_updates(&tl::functions::channels::EditBanned {
+                        channel: input_channel(&chat),
+                        user_id: tl::enums::InputUser::from_bytes(&user_bytes).unwrap(),
+                        banned_rights: types::Permissions {
+                            can_view_messages: !view_messages,
+                            ..types::Permissions::default()
+                        }
+                        .to_banned_rights(0),
+                    })??;
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "users can only be removed from groups and channels",
+                )
+                .into())
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts the given user into an input user for the chat membership
+    /// methods, failing if it refers to anything other than a user.
+    fn chat_user<U: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        user: U,
+    ) -> Result<tl::enums::InputUser, Error> {
+        match input_user(&user.convert(self)?) {
+            tl::enums::InputUser::InputUserEmpty(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only users can be added to or removed from chats",
+            )
+            .into()),
+            user => Ok(user),
+        }
+    }
+
+    /// Changes what the members of the group or channel are allowed to do
+    /// by default. Viewing the messages is always allowed.
+    pub fn set_default_permissions<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        permissions: types::Permissions,
+    ) -> Result<(), Error> {
+        let chat = chat.convert(self)?;
+        self.invoke_and_process_updates(&tl::functions::messages::EditChatDefaultBannedRights {
+            peer: chat,
+            banned_rights: types::Permissions {
+                can_view_messages: true,
+                ..permissions
+            }
+            .to_banned_rights(0),
+        })??;
+        Ok(())
+    }
+
+    /// Returns the description and the commands of the desired bot, or
+    /// `None` if the user is not a bot.
+    ///
+    /// A bot may use `"me"` to read back its own commands.
+    pub fn get_bot_info<U: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        user: U,
+    ) -> Result<Option<tl::types::BotInfo>, Error> {
+        let id = match input_user(&user.convert(self)?) {
+            tl::enums::InputUser::InputUserEmpty(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "only users can have bot information",
+                )
+                .into())
+            }
+            user => user,
+        };
+
+        let user: tl::types::UserFull = self
+            .invoke(&tl::functions::users::GetFullUser { id })??
+            .into();
+        Ok(user.bot_info.map(tl::types::BotInfo::from))
+    }
+
+    /// Returns the message pinned in the desired chat, or `None` if there
+    /// is no pinned message.
+    pub fn get_pinned_message<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+    ) -> Result<Option<types::Message>, Error> {
+        use tl::enums::InputPeer as P;
+
+        let chat = chat.convert(self)?;
+        let pinned_msg_id = match &chat {
+            P::InputPeerSelf(_) | P::InputPeerUser(_) | P::InputPeerUserFromMessage(_) => {
+                let user: tl::types::UserFull = self
+                    .invoke(&tl::functions::users::GetFullUser {
+                        id: input_user(&chat),
+                    })??
+                    .into();
+                user.pinned_msg_id
+            }
+            P::InputPeerChat(chat) => {
+                let full: tl::types::messages::ChatFull = self
+                    .invoke(&tl::functions::messages::GetFullChat {
+                        chat_id: chat.chat_id,
+                    })??
+                    .into();
+                match full.full_chat {
+                    tl::enums::ChatFull::ChatFull(chat) => chat.pinned_msg_id,
+                    tl::enums::ChatFull::ChannelFull(channel) => channel.pinned_msg_id,
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+            P::InputPeerChannel(_) | P::InputPeerChannelFromMessage(_) => {
+                let full: tl::types::messages::ChatFull = self
+                    .invoke(&tl::functions::channels::GetFullChannel {
+                        channel: input_channel(&chat),
+                    })??
+                    .into();
+                match full.full_chat {
+                    tl::enums::ChatFull::ChatFull(chat) => chat.pinned_msg_id,
+                    tl::enums::ChatFull::ChannelFull(channel) => channel.pinned_msg_id,
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+            // Includes `InputPeerEmpty`.
+            _ => None,
+        };
+
+        let pinned_msg_id = match pinned_msg_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        // The pinned message may have been deleted in the meantime.
+        self.get_message_by_id(&chat, pinned_msg_id)
+    }
+
+    /// Returns the message the given message is replying to, or `None` if
+    /// it's not a reply (or the message it replied to was deleted).
+    ///
+    /// The chat must be the one where the message was sent, since messages
+    /// in channels can only be fetched through the channel.
+    pub fn get_reply_to<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        message: &types::Message,
+    ) -> Result<Option<types::Message>, Error> {
+        let reply_to_msg_id = match message.reply_to_message_id() {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let chat = chat.convert(self)?;
+        self.get_message_by_id(&chat, reply_to_msg_id)
+    }
+
+    /// Returns the `t.me` link to the desired message, which can be shared
+    /// with others.
+    ///
+    /// Only messages in channels and megagroups have a link. For any other
+    /// chat, [`MessageLinkError::NotChannel`] is returned without making any
+    /// request.
+    ///
+    /// [`MessageLinkError::NotChannel`]: enum.MessageLinkError.html#variant.NotChannel
+    pub fn get_message_link<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        message_id: i32,
+    ) -> Result<String, MessageLinkError> {
+        let chat = chat.convert(self)?;
+        match chat {
+            tl::enums::InputPeer::InputPeerChannel(_)
+            | tl::enums::InputPeer::InputPeerChannelFromMessage(_) => {}
+            _ => return Err(MessageLinkError::NotChannel),
+        }
+
+        match self.invoke(&tl::functions::channels::ExportMessageLink {
+            channel: input_channel(&chat),
+            id: message_id,
+            grouped: false,
+        })? {
+            Ok(link) => Ok(tl::types::ExportedMessageLink::from(link).link),
+            Err(error) => Err(MessageLinkError::Error(error.into())),
+        }
+    }
+
+    /// Presses the inline button with the given callback `data` under the
+    /// desired message, which must have been sent by a bot.
+    ///
+    /// Returns the URL the bot wants to open if it answered with one, or
+    /// otherwise the text of the notification it wants to show, if any.
+    /// The callback data of a button can be found in [`Message::buttons`].
+    ///
+    /// [`Message::buttons`]: types/struct.Message.html#method.buttons
+    pub fn click_button<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        message_id: i32,
+        data: &[u8],
+    ) -> Result<Option<String>, ClickButtonError> {
+        let chat = chat.convert(self)?;
+        match self.invoke(&tl::functions::messages::GetBotCallbackAnswer {
+            game: false,
+            peer: chat,
+            msg_id: message_id,
+            data: Some(data.to_vec()),
+        })? {
+            Ok(answer) => {
+                let answer = tl::types::messages::BotCallbackAnswer::from(answer);
+                Ok(answer.url.or(answer.message))
+            }
+            Err(RPCError { name, .. }) if name == "BOT_RESPONSE_TIMEOUT" => {
+                Err(ClickButtonError::BotTimeout)
+            }
+            Err(error) => Err(ClickButtonError::Error(error.into())),
+        }
+    }
+
+    /// Fetches a single message from the desired chat, if it exists.
+    fn get_message_by_id(
+        &mut self,
+        chat: &tl::enums::InputPeer,
+        message_id: i32,
+    ) -> Result<Option<types::Message>, Error> {
+        let id = vec![tl::types::InputMessageID { id: message_id }.into()];
+        let messages = match chat {
+            tl::enums::InputPeer::InputPeerChannel(_)
+            | tl::enums::InputPeer::InputPeerChannelFromMessage(_) => {
+                self.invoke(&tl::functions::channels::GetMessages {
+                    channel: input_channel(chat),
+                    id,
+                })??
+            }
+            _ => self.invoke(&tl::functions::messages::GetMessages { id })??,
+        };
+
+        Ok(extract_messages(messages)
+            .into_iter()
+            .find(|message| message.id() == message_id))
+    }
+
+    /// Returns an iterator over the messages scheduled to be sent in the
+    /// desired chat, whose `date` is the time at which they will be sent.
+    ///
+    /// Telegram returns every scheduled message at once, so the iterator
+    /// doesn't make further requests.
+    pub fn iter_scheduled<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+    ) -> Result<impl Iterator<Item = types::Message>, Error> {
+        let peer = chat.convert(self)?;
+        let messages =
+            self.invoke(&tl::functions::messages::GetScheduledHistory { peer, hash: 0 })??;
+        Ok(extract_messages(messages).into_iter())
+    }
+
+    /// Deletes messages scheduled to be sent in the desired chat, so that
+    /// they're never sent.
+    pub fn delete_scheduled_messages<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        message_ids: &[i32],
+    ) -> Result<(), Error> {
+        let peer = chat.convert(self)?;
+        self.invoke_and_process_updates(&tl::functions::messages::DeleteScheduledMessages {
+            peer,
+            id: message_ids.to_vec(),
+        })??;
+        Ok(())
+    }
+
+    /// Returns information about the chat behind an invite link without
+    /// joining it, which can be used to ask for confirmation before joining.
+    ///
+    /// Either the full link (such as `https://t.me/+hash` or
+    /// `https://t.me/joinchat/hash`) or only its hash may be given.
+    pub fn check_chat_invite(&mut self, link: &str) -> Result<types::ChatInvitePreview, Error> {
+        let invite = self.invoke(&tl::functions::messages::CheckChatInvite {
+            hash: types::parse_invite_hash(link).to_string(),
+        })??;
+        Ok(invite.into())
+    }
+
+    /// Returns the link preview Telegram would show for the given URL
+    /// (with its title, description, photo, and so on) without sending any
+    /// message, or `None` if there is no preview or it's not ready yet.
+    pub fn get_web_page(&mut self, url: &str) -> Result<Option<tl::types::WebPage>, Error> {
+        match self.invoke(&tl::functions::messages::GetWebPage {
+            url: url.to_string(),
+            hash: 0,
+        })?? {
+            tl::enums::WebPage::WebPage(page) => Ok(Some(page)),
+            // Includes `WebPageEmpty`, `WebPagePending` and `WebPageNotModified`.
+            _ => Ok(None),
+        }
+    }
+
+    /// Reports the chat or user to Telegram's moderators for the given
+    /// reason, and returns whether the report was accepted.
+    ///
+    /// The message explains the report, and is only sent along with
+    /// [`ReportReason::Other`].
+    ///
+    /// [`ReportReason::Other`]: types/enum.ReportReason.html#variant.Other
+    pub fn report_peer<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+        reason: types::ReportReason,
+        message: &str,
+    ) -> Result<bool, Error> {
+        let chat = chat.convert(self)?;
+        Ok(self.invoke(&tl::functions::account::ReportPeer {
+            peer: chat,
+            reason: reason.to_input_report_reason(message),
+        })??)
+    }
+
+    /// Returns all the Telegram Passport values stored in the account.
+    ///
+    /// Their data is encrypted, see the [`passport`] module for details on
+    /// how to decrypt it.
+    ///
+    /// [`passport`]: passport/index.html
+    pub fn get_all_secure_values(&mut self) -> Result<Vec<tl::types::SecureValue>, Error> {
+        Ok(self
+            .invoke(&tl::functions::account::GetAllSecureValues {})??
+            .into_iter()
+            .map(tl::types::SecureValue::from)
+            .collect())
+    }
+
+    /// Returns up to `limit` groups and channels that both the logged-in
+    /// user and the desired user are members of.
+    ///
+    /// Chats which can no longer be accessed are not included.
+    pub fn get_common_chats<U: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        user: U,
+        limit: i32,
+    ) -> Result<Vec<types::Entity>, Error> {
+        let user = user.convert(self)?;
+        let mut entities = Vec::new();
+        let mut fetched = 0;
+        let mut max_id = 0;
+        while fetched < limit {
+            let batch_limit = (limit - fetched).min(COMMON_CHATS_LIMIT);
+            let chats = match self.invoke(&tl::functions::messages::GetCommonChats {
+                user_id: input_user(&user),
+                max_id,
+                limit: batch_limit,
+            })?? {
+                tl::enums::messages::Chats::Chats(chats) => chats.chats,
+                tl::enums::messages::Chats::ChatsSlice(chats) => chats.chats,
+                #[allow(unreachable_patterns)]
+                _ => Vec::new(),
+            };
+
+            let count = chats.len() as i32;
+            for chat in chats {
+                max_id = match &chat {
+                    tl::enums::Chat::ChatEmpty(chat) => chat.id,
+                    tl::enums::Chat::Chat(chat) => chat.id,
+                    tl::enums::Chat::ChatForbidden(chat) => chat.id,
+                    tl::enums::Chat::Channel(channel) => channel.id,
+                    tl::enums::Chat::ChannelForbidden(channel) => channel.id,
+                    #[allow(unreachable_patterns)]
+                    _ => max_id,
+                };
+                match chat {
+                    tl::enums::Chat::Chat(chat) => entities.push(types::Entity::Chat(chat)),
+                    tl::enums::Chat::Channel(channel) => {
+                        entities.push(types::Entity::Channel(channel))
+                    }
+                    _ => {}
+                }
+            }
+
+            fetched += count;
+            if count < batch_limit {
+                break;
+            }
+        }
+
+        Ok(entities)
+    }
+
+    /// Returns an iterator over all the contacts of the logged-in user.
+    ///
+    /// Telegram returns every contact at once, so the iterator doesn't make
+    /// further requests. Their phone number is included when it's visible.
+    pub fn iter_contacts(&mut self) -> Result<impl Iterator<Item = tl::types::User>, Error> {
+        let users = match self.invoke(&tl::functions::contacts::GetContacts { hash: 0 })?? {
+            tl::enums::contacts::Contacts::Contacts(contacts) => contacts.users,
+            // Includes `ContactsNotModified`.
+            _ => Vec::new(),
+        };
+
+        Ok(users.into_iter().filter_map(|user| match user {
+            tl::enums::User::User(user) => Some(user),
+            // Includes `UserEmpty`.
+            _ => None,
+        }))
+    }
+
+    /// Returns the phone contacts that were imported into the account, even
+    /// if they don't have a Telegram account.
+    pub fn get_saved_contacts(&mut self) -> Result<Vec<tl::types::SavedPhoneContact>, Error> {
+        Ok(self
+            .invoke(&tl::functions::contacts::GetSaved {})??
+            .into_iter()
+            .map(tl::types::SavedPhoneContact::from)
+            .collect())
+    }
+
+    pub fn iter_dialogs(&mut self) -> dialogs::Dialogs {
+        dialogs::Dialogs::new(self)
+    }
+
+    /// Returns an iterator over the message history of the desired chat.
+    ///
+    /// The newest messages are returned first. Call `reverse(true)` on the
+    /// returned iterator to start from the oldest message in the chat instead.
+    pub fn iter_messages<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+    ) -> Result<messages::Messages<'_>, Error> {
+        let chat = chat.convert(self)?;
+        Ok(messages::Messages::new(self, chat))
+    }
+
+    /// Returns an iterator over the administrator log of the desired channel
+    /// or megagroup, with the newest events first.
+    ///
+    /// Only administrators can see the log. Use the methods of the returned
+    /// iterator to filter which events are returned.
+    pub fn iter_admin_log<C: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        chat: C,
+    ) -> Result<adminlog::AdminLog<'_>, Error> {
+        let chat = chat.convert(self)?;
+        Ok(adminlog::AdminLog::new(self, input_channel(&chat)))
+    }
+
+    /// Returns an iterator over the profile photos of the desired user,
+    /// starting with the current one.
+    ///
+    /// The photos can be downloaded with [`download_photo`].
+    ///
+    /// [`download_photo`]: #method.download_photo
+    pub fn iter_profile_photos<U: IntoInput<tl::enums::InputPeer>>(
+        &mut self,
+        user: U,
+    ) -> Result<profile_photos::ProfilePhotos<'_>, Error> {
+        let user = user.convert(self)?;
+        Ok(profile_photos::ProfilePhotos::new(self, input_user(&user)))
+    }
+
+    /// Returns the current update state of the account, which includes the
+    /// total number of unread messages.
+    ///
+    /// This is the starting point from which missed updates are fetched.
+    pub fn get_state(&mut self) -> Result<tl::types::updates::State, Error> {
+        let state: tl::types::updates::State =
+            self.invoke(&tl::functions::updates::GetState {})??.into();
+        Ok(state)
+    }
+
+    /// Stores the given state in the session, without any channel state.
+    fn seed_update_state(&mut self, state: &tl::types::updates::State) -> Result<(), Error> {
+        self.session.set_update_state(UpdateState {
+            pts: state.pts,
+            qts: state.qts,
+            date: state.date,
+            seq: state.seq,
+            channels: HashMap::new(),
+        });
+        self.session.save()?;
+        Ok(())
+    }
+
+    /// Fetches the updates that occurred since the last time this method was
+    /// called, using the update state stored in the session.
+    ///
+    /// This should be called after connecting to recover any updates missed
+    /// while the client was offline. If the session has no update state yet,
+    /// the current state is fetched and stored, and no updates are returned.
+    ///
+    /// Channels that had too many updates to be included in the difference
+    /// will have their missed updates fetched separately.
+    pub fn catch_up(&mut self) -> Result<Vec<types::Update>, Error> {
+        let mut update_state = match self.session.get_update_state() {
+            Some(state) => state,
+            None => {
+                let state = self.get_state()?;
+                self.seed_update_state(&state)?;
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut updates = Vec::new();
+        let mut channel_hashes = HashMap::new();
+        let mut too_long_channels = Vec::new();
+        loop {
+            let difference = self.invoke(&tl::functions::updates::GetDifference {
+                pts: update_state.pts,
+                pts_total_limit: None,
+                date: update_state.date,
+                qts: update_state.qts,
+            })??;
+
+            let (new_messages, other_updates, users, chats, state, done) = match difference {
+                tl::enums::updates::Difference::DifferenceEmpty(empty) => {
+                    update_state.date = empty.date;
+                    update_state.seq = empty.seq;
+                    (Vec::new(), Vec::new(), Vec::new(), Vec::new(), None, true)
+                }
+                tl::enums::updates::Difference::Difference(difference) => (
+                    difference.new_messages,
+                    difference.other_updates,
+                    difference.users,
+                    difference.chats,
+                    Some(difference.state),
+                    true,
+                ),
+                tl::enums::updates::Difference::DifferenceSlice(difference) => (
+                    difference.new_messages,
+                    difference.other_updates,
+                    difference.users,
+                    difference.chats,
+                    Some(difference.intermediate_state),
+                    false,
+                ),
+                tl::enums::updates::Difference::DifferenceTooLong(too_long) => {
+                    // Too many updates were missed to fetch them all. Skip
+                    // the gap and continue from the state given to us.
+                    update_state.pts = too_long.pts;
+                    (Vec::new(), Vec::new(), Vec::new(), Vec::new(), None, false)
+                }
+                #[allow(unreachable_patterns)]
+                _ => return Err(unknown_constructor().into()),
+            };
+
+            // The access hash is needed to fetch the difference of channels.
+            chats.iter().for_each(|chat| {
+                if let tl::enums::Chat::Channel(channel) = chat {
+                    if let Some(access_hash) = channel.access_hash {
+                        channel_hashes.insert(channel.id, access_hash);
+                    }
+                }
+            });
+
+            let packed_chats = types::PackedChats::new(&users, &chats);
+            updates.extend(
+                new_messages.into_iter().map(|message| {
+                    types::Update::from_new_message(message).with_chats(&packed_chats)
+                }),
+            );
+            for update in other_updates {
+                match update {
+                    tl::enums::Update::UpdateChannelTooLong(too_long) => {
+                        too_long_channels.push(too_long);
+                    }
+                    update => updates.push(types::Update::from(update).with_chats(&packed_chats)),
+                }
+            }
+            if let Some(state) = state.map(tl::types::updates::State::from) {
+                update_state.pts = state.pts;
+                update_state.qts = state.qts;
+                update_state.date = state.date;
+                update_state.seq = state.seq;
+            }
+
+            // Store the state as we go, in case a later request fails.
+            self.session.set_update_state(update_state.clone());
+            if done {
+                break;
+            }
+        }
+
+        for too_long in too_long_channels {
+            let access_hash = match channel_hashes.get(&too_long.channel_id) {
+                Some(access_hash) => *access_hash,
+                None => continue,
+            };
+            let pts = match update_state.channels.get(&too_long.channel_id) {
+                Some(pts) => *pts,
+                None => match too_long.pts {
+                    Some(pts) => pts,
+                    None => continue,
+                },
+            };
+            updates.extend(self.get_channel_difference(
+                &mut update_state,
+                too_long.channel_id,
+                access_hash,
+                pts,
+            )?);
+        }
+
+        self.session.save()?;
+        Ok(updates)
+    }
+
+    /// Moves the client into a background thread which fetches updates with
+    /// [`catch_up`] every `poll_interval`, and returns a stream over them.
+    ///
+    /// The client can be recovered with [`UpdateStream::close`].
+    ///
+    /// [`catch_up`]: #method.catch_up
+    /// [`UpdateStream::close`]: struct.UpdateStream.html#method.close
+    pub fn stream_updates(self, poll_interval: Duration) -> Result<UpdateStream, Error> {
+        UpdateStream::spawn(self, poll_interval)
+    }
+
+    /// Fetches the updates missed in a channel since the given `pts`, and
+    /// stores the new `pts` of the channel in the session.
+    fn get_channel_difference(
+        &mut self,
+        update_state: &mut UpdateState,
+        channel_id: i32,
+        access_hash: i64,
+        mut pts: i32,
+    ) -> Result<Vec<types::Update>, Error> {
+        let mut updates = Vec::new();
+        loop {
+            let difference = self.invoke(&tl::functions::updates::GetChannelDifference {
+                force: false,
+                channel: tl::types::InputChannel {
+                    channel_id,
+                    access_hash,
+                }
+                .into(),
+                filter: tl::types::ChannelMessagesFilterEmpty {}.into(),
+                pts,
+                limit: CHANNEL_DIFFERENCE_LIMIT,
+            })??;
+
+            let done = match difference {
+                tl::enums::updates::ChannelDifference::ChannelDifferenceEmpty(empty) => {
+                    pts = empty.pts;
+                    empty.r#final
+                }
+                tl::enums::updates::ChannelDifference::ChannelDifferenceTooLong(too_long) => {
+                    // Too many updates were missed to fetch them all. Only
+                    // the latest messages are given, and the state is reset
+                    // to that of the dialog.
+                    if let tl::enums::Dialog::Dialog(dialog) = too_long.dialog {
+                        if let Some(dialog_pts) = dialog.pts {
+                            pts = dialog_pts;
+                        }
+                    }
+                    let packed_chats = types::PackedChats::new(&too_long.users, &too_long.chats);
+                    updates.extend(too_long.messages.into_iter().map(|message| {
+                        types::Update::from_new_message(message).with_chats(&packed_chats)
+                    }));
+                    too_long.r#final
+                }
+                tl::enums::updates::ChannelDifference::ChannelDifference(difference) => {
+                    pts = difference.pts;
+                    let packed_chats =
+                        types::PackedChats::new(&difference.users, &difference.chats);
+                    updates.extend(difference.new_messages.into_iter().map(|message| {
+                        types::Update::from_new_message(message).with_chats(&packed_chats)
+                    }));
+                    updates.extend(
+                        difference
+                            .other_updates
+                            .into_iter()
+                            .map(|update| types::Update::from(update).with_chats(&packed_chats)),
+                    );
+                    difference.r#final
+                }
+                #[allow(unreachable_patterns)]
+                _ => return Err(unknown_constructor().into()),
+            };
+
+            update_state.channels.insert(channel_id, pts);
+            self.session.set_update_state(update_state.clone());
+            if done {
+                break;
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Initializes the connection with Telegram. If this is never done on
+    /// a fresh session, then Telegram won't know which layer to use and a
+    /// very old one will be used (which we will fail to understand).
+    fn init_connection(&mut self) -> Result<(), Error> {
+        let config: tl::types::Config = self
+            .init_invoke(&tl::functions::help::GetConfig {})??
+            .into();
+
+        self.limits = types::Limits::from(&config);
+        self.dc_options = config
+            .dc_options
+            .into_iter()
+            .map(tl::types::DcOption::from)
+            .collect();
+        Ok(())
+    }
+
+    /// Returns the ID of the datacenter geographically closest to the
+    /// client, according to Telegram.
+    ///
+    /// A fresh client may use this before logging in to find out whether
+    /// it's worth moving to a different datacenter than the default one.
+    pub fn nearest_dc(&mut self) -> Result<i32, Error> {
+        let nearest: tl::types::NearestDc =
+            self.invoke(&tl::functions::help::GetNearestDc {})??.into();
+        Ok(nearest.nearest_dc)
+    }
+
+    /// Sends a ping to Telegram and returns how long it took for the pong
+    /// to arrive.
+    ///
+    /// This doesn't depend on the account being authorized, so it can be
+    /// used at any time to check whether the connection is still alive.
+    pub fn ping(&mut self) -> Result<Duration, Error> {
+        let ping_id = generate_random_id();
+        let start = Instant::now();
+        let pong: tl::types::Pong = self.invoke(&tl::functions::Ping { ping_id })??.into();
+        let elapsed = start.elapsed();
+
+        if pong.ping_id != ping_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the pong does not match the ping that was sent",
+            )
+            .into());
+        }
+        Ok(elapsed)
+    }
+
+    /// Changes whether IPv6 addresses are tried before IPv4 ones when
+    /// connecting to a datacenter. By default, IPv4 addresses go first,
+    /// and IPv6 ones are only used when those can't be reached.
+    pub fn set_prefer_ipv6(&mut self, prefer_ipv6: bool) {
+        self.prefer_ipv6 = prefer_ipv6;
+    }
+
+    /// Changes the length, in UTF-16 code units, at which the text given to
+    /// [`send_long_message`] is split. By default, and if the length is
+    /// larger, the most Telegram allows (see [`limits`]) is used.
+    ///
+    /// [`send_long_message`]: #method.send_long_message
+    /// [`limits`]: #method.limits
+    pub fn set_max_message_length(&mut self, length: usize) {
+        self.max_message_length = Some(length.max(1));
+    }
+
+    /// The length at which `send_long_message` splits the text.
+    fn max_message_length(&self) -> usize {
+        let limit = self.limits.message_length_max.max(1) as usize;
+        self.max_message_length
+            .map_or(limit, |length| length.min(limit))
+    }
+
+    /// Returns the limits Telegram advertised when the connection was
+    /// initialized, such as the maximum length of messages.
+    pub fn limits(&self) -> &types::Limits {
+        &self.limits
+    }
+
+    /// Drops the current connection and connects again to the account's
+    /// datacenter, reusing the same authorization.
+    ///
+    /// If the address of the datacenter is unreachable, the other addresses
+    /// known for it are tried, waiting longer after every failed round.
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        let (dc_id, dc_addr) = match self.session.get_user_datacenter() {
+            Some(dc) => dc,
+            None => (
+                DEFAULT_DC_ID as i32,
+                static_dc_address(DEFAULT_DC_ID as i32, self.test_mode).unwrap(),
+            ),
+        };
+
+        info!("reconnecting to datacenter {}", dc_id);
+        let mut sender = self.connect_to_dc(dc_id, dc_addr)?;
+        if let Some(auth_key) = self.session.get_auth_key_data(dc_id) {
+            sender.set_auth_key(auth_key);
+        }
+        sender.set_time_offset(self.sender.time_offset());
+        self.sender = sender;
+
+        // Remember the address that worked to try it first next time.
+        let peer_addr = self.sender.peer_addr()?;
+        if peer_addr != dc_addr {
+            self.session.set_user_datacenter(dc_id, &peer_addr);
+            self.session.save()?;
+        }
+
+        self.init_connection()
+    }
+
+    /// Connects to the given datacenter, trying all of its known addresses
+    /// (starting with `primary`) until one succeeds.
+    fn connect_to_dc(&self, dc_id: i32, primary: SocketAddr) -> io::Result<MTSender> {
+        let mut addresses = dc_addresses(&self.dc_options, dc_id, primary, self.prefer_ipv6);
+        for address in fallback_dc_addresses(dc_id, self.prefer_ipv6, self.test_mode) {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        let mut delay = CONNECT_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let mut last_error = None;
+            for address in addresses.iter() {
+                match MTSender::connect(address) {
+                    Ok(sender) => return Ok(sender),
+                    Err(error) => {
+                        warn!("failed to connect to {}: {}", address, error);
+                        last_error = Some(error);
+                    }
+                }
+            }
+
+            if attempt == CONNECT_ATTEMPTS {
+                // There's always at least the primary address.
+                return Err(last_error.unwrap());
+            }
+            debug!("retrying connection to datacenter {} in {:?}", dc_id, delay);
+            thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    /// Wraps the request in `invokeWithLayer(initConnection(...))` and
+    /// invokes that. Should be used by the first request after connect.
+    fn init_invoke<R: RPC>(&mut self, request: &R) -> RequestResult<R::Return> {
+        // TODO figure out what we're doing wrong because Telegram seems to
+        //      reply some constructor we are unaware of, even though we
+        //      explicitly did invokeWithLayer. this will fail, because
+        //      we want to return the right type (before we ignored it).
+        //
+        // a second call to getConfig will work just fine though.
+        //
+        // this also seems to have triggered RPC_CALL_FAIL
+        let data = self.sender.invoke(&self.init_request(request))??;
+
+        Ok(Ok(R::Return::from_bytes(&data.0)?))
+    }
+
+    /// Wraps the request in `invokeWithLayer(initConnection(...))`.
+    fn init_request<R: RPC>(&self, request: &R) -> tl::functions::InvokeWithLayer {
+        let info = os_info::get();
+
+        let mut system_lang_code = locate_locale::system();
+        if system_lang_code.is_empty() {
+            system_lang_code.push_str(DEFAULT_LOCALE);
+        }
+
+        let mut lang_code = locate_locale::user();
+        if lang_code.is_empty() {
+            lang_code.push_str(DEFAULT_LOCALE);
+        }
+
+        tl::functions::InvokeWithLayer {
+            layer: tl::LAYER,
+            query: tl::functions::InitConnection {
+                api_id: self.api_id,
+                device_model: format!("{} {}", info.os_type(), info.bitness()),
+                system_version: info.version().to_string(),
+                app_version: env!("CARGO_PKG_VERSION").into(),
+                system_lang_code,
+                lang_pack: "".into(),
+                lang_code,
+                proxy: None,
+                query: request.to_bytes().into(),
+            }
+            .to_bytes()
+            .into(),
+        }
+    }
+
+    /// Invokes a request in the given datacenter, instead of the user's,
+    /// connecting to it first if needed.
+    fn invoke_in_dc<R: RPC>(&mut self, dc_id: i32, request: &R) -> RequestResult<R::Return> {
+        if !self.exported_senders.contains_key(&dc_id) {
+            let sender = self.connect_exported(dc_id)?;
+            self.exported_senders.insert(dc_id, sender);
+        }
+
+        let result = self
+            .exported_senders
+            .get_mut(&dc_id)
+            .unwrap()
+            .invoke(request);
+        if result.is_err() {
+            // Connect again the next time it's needed.
+            self.exported_senders.remove(&dc_id);
+        }
+        result
+    }
+
+    /// Connects to the given datacenter and authorizes the connection as
+    /// the logged-in user.
+    ///
+    /// The authorization key for the datacenter is taken from the session,
+    /// or generated and saved into it if there was none.
+    fn connect_exported(&mut self, dc_id: i32) -> io::Result<MTSender> {
+        let primary = match static_dc_address(dc_id, self.test_mode) {
+            Some(address) => address,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown datacenter id: {}", dc_id),
+                ))
+            }
+        };
+
+        info!("connecting to datacenter {}", dc_id);
+        let mut sender = self.connect_to_dc(dc_id, primary)?;
+        if let Some(auth_key) = self.session.get_auth_key_data(dc_id) {
+            // Setting the key resets the offset, so it must be set after.
+            sender.set_auth_key(auth_key);
+            sender.set_time_offset(self.sender.time_offset());
+        } else {
+            let auth_key = sender.generate_auth_key()?;
+            self.session.set_auth_key_data(dc_id, &auth_key.to_bytes());
+            self.session.save()?;
+        }
+
+        let exported: tl::types::auth::ExportedAuthorization = self
+            .invoke(&tl::functions::auth::ExportAuthorization { dc_id })??
+            .into();
+        sender.invoke(
+            &self.init_request(&tl::functions::auth::ImportAuthorization {
+                id: exported.id,
+                bytes: exported.bytes,
+            }),
+        )??;
+        Ok(sender)
+    }
+
+    /// Returns the current time according to Telegram's servers.
+    ///
+    /// This should be preferred over the local time when computing dates to
+    /// be sent, such as a `schedule_date`.
+    pub fn server_time(&self) -> SystemTime {
+        self.sender.server_time()
+    }
+
+    /// Invokes a raw request, and returns its result.
+    ///
+    /// If Telegram reports that the connection was not initialized (which
+    /// may happen after reconnecting), the connection is initialized again
+    /// and the request is retried once.
+    ///
+    /// Transient internal errors (such as `RPC_CALL_FAIL`, or those with
+    /// code -503) are retried a couple of times after a short delay before
+    /// being returned.
+    ///
+    /// If a message from Telegram fails the integrity check, or a read times
+    /// out in the middle of a message, the connection can no longer be
+    /// trusted, so the client reconnects before returning the error. The
+    /// request is not retried, since it may have been processed already.
+    pub fn invoke<R: RPC>(&mut self, request: &R) -> RequestResult<R::Return> {
+        let result = retry_if_internal_error(self, INTERNAL_ERROR_RETRY_DELAY, |client| {
+            retry_if_not_inited(
+                client,
+                |client| client.sender.invoke(request),
+                |client| client.init_invoke(request),
+            )
+        });
+
+        self.reconnect_if_broken(result)
+    }
+
+    /// Reconnects if the error left the connection in an unusable state,
+    /// and returns the result unchanged.
+    fn reconnect_if_broken<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        match result {
+            Err(error) if is_integrity_error(&error) || self.sender.is_desynchronized() => {
+                warn!("{}, reconnecting", error);
+                self.reconnect()?;
+                Err(error)
+            }
+            result => result,
+        }
+    }
+
+    /// Like [`invoke`], but takes the request by value, which is convenient
+    /// when the request is built inline and not needed afterwards.
+    ///
+    /// [`invoke`]: #method.invoke
+    pub fn invoke_owned<R: RPC>(&mut self, request: R) -> RequestResult<R::Return> {
+        self.invoke(&request)
+    }
+
+    /// Invokes a raw request which returns updates (such as sending or editing
+    /// a message), and returns them after advancing the update state stored
+    /// in the session with them.
+    ///
+    /// This keeps the state consistent with what the account did, so that
+    /// [`catch_up`] doesn't fetch the changes made by the request again. If
+    /// the session has no update state yet, it is left untouched.
+    ///
+    /// [`catch_up`]: #method.catch_up
+    pub fn invoke_and_process_updates<R: RPC<Return = tl::enums::Updates>>(
+        &mut self,
+        request: &R,
+    ) -> RequestResult<tl::enums::Updates> {
+        let updates = match self.invoke(request)? {
+            Ok(updates) => updates,
+            Err(error) => return Ok(Err(error)),
+        };
+        if let Some(mut state) = self.session.get_update_state() {
+            apply_updates_state(&mut state, &updates);
+            self.session.set_update_state(state);
+        }
+        Ok(Ok(updates))
+    }
+
+    /// Invokes a sequence of raw requests, returning an iterator over their
+    /// results. This is the building block for paginating endpoints that
+    /// don't have a more convenient iterator yet.
+    ///
+    /// `make_request` is called with `None` to build the first request, and
+    /// then with the result of each request to build the next one. The
+    /// iteration stops as soon as it returns `None`.
+    ///
+    /// Unlike [`invoke`], errors returned by Telegram are not separate from
+    /// other errors. No request is made until the iterator is advanced.
+    ///
+    /// [`invoke`]: #method.invoke
+    pub fn invoke_stream<R, F>(&mut self, make_request: F) -> RequestStream<'_, R, F>
+    where
+        R: RPC,
+        F: FnMut(Option<&R::Return>) -> Option<R>,
+    {
+        RequestStream::new(self, make_request)
+    }
+
+    /// Invokes a raw request, waiting at most `timeout` for its result.
+    ///
+    /// This is useful for requests that are expected to take longer (or
+    /// shorter) than the network timeout. The connection keeps waiting for
+    /// the result until `timeout` elapses, even if the network timeout is
+    /// reached first. If the result doesn't arrive in time, an error with
+    /// kind `TimedOut` is returned.
+    pub fn invoke_with_timeout<R: RPC>(
+        &mut self,
+        request: &R,
+        timeout: Duration,
+    ) -> RequestResult<R::Return> {
+        let result = self.sender.invoke_with_timeout(request, timeout);
+        self.reconnect_if_broken(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dc_option(id: i32, ip_address: &str, ipv6: bool, media_only: bool) -> tl::types::DcOption {
+        tl::types::DcOption {
+            ipv6,
+            media_only,
+            tcpo_only: false,
+            cdn: false,
+            r#static: false,
+            id,
+            ip_address: ip_address.into(),
+            port: 443,
+            secret: None,
+        }
+    }
+
+    #[test]
+    fn check_dc_addresses_order() {
+        let options = vec![
+            dc_option(2, "2001:67c:4e8:f002::a", true, false),
+            dc_option(2, "149.154.167.51", false, false),
+            dc_option(1, "149.154.175.53", false, false),
+            dc_option(2, "149.154.167.151", false, true),
+            dc_option(2, "149.154.167.50", false, false),
+        ];
+        let primary = "149.154.167.51:443".parse().unwrap();
+
+        let addresses = dc_addresses(&options, 2, primary, false)
+            .into_iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            addresses,
+            vec![
+                "149.154.167.51:443",
+                "149.154.167.50:443",
+                "[2001:67c:4e8:f002::a]:443",
+                "149.154.167.151:443",
+            ]
+        );
+
+        let addresses = dc_addresses(&options, 2, primary, true)
+            .into_iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            addresses,
+            vec![
+                "149.154.167.51:443",
+                "[2001:67c:4e8:f002::a]:443",
+                "149.154.167.50:443",
+                "149.154.167.151:443",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_dc_addresses_without_options() {
+        let primary = "149.154.167.51:443".parse().unwrap();
+        assert_eq!(dc_addresses(&[], 2, primary, false), vec![primary]);
+    }
+
+    #[test]
+    fn check_static_dc_addresses() {
+        let ipv4: SocketAddr = "149.154.167.51:443".parse().unwrap();
+        let ipv6: SocketAddr = "[2001:67c:4e8:f002::a]:443".parse().unwrap();
+        assert_eq!(static_dc_addresses(2, false), vec![ipv4, ipv6]);
+        assert_eq!(static_dc_addresses(2, true), vec![ipv6, ipv4]);
+        assert!(static_dc_addresses(0, false).is_empty());
+        assert!(static_dc_addresses(6, false).is_empty());
+    }
+
+    #[test]
+    fn check_test_login_code() {
+        assert_eq!(test_login_code("+99966 2 1234"), Some("22222".to_string()));
+        assert_eq!(test_login_code("9996631234"), Some("33333".to_string()));
+        assert_eq!(test_login_code("9996641234"), None);
+        assert_eq!(test_login_code("34600000000"), None);
+    }
+
+    #[test]
+    fn check_test_dc_addresses() {
+        let test: SocketAddr = "149.154.167.40:443".parse().unwrap();
+        assert_eq!(static_dc_address(2, true), Some(test));
+        assert_eq!(fallback_dc_addresses(2, true, true), vec![test]);
+        assert!(static_dc_address(4, true).is_none());
+        assert!(static_dc_address(0, false).is_none());
+    }
+
+    fn not_inited() -> RPCError {
+        RPCError {
+            code: 400,
+            name: "CONNECTION_NOT_INITED".into(),
+            value: None,
+        }
+    }
+
+    #[test]
+    fn check_not_inited_is_retried_once() {
+        let mut calls = (0, 0);
+        let result = retry_if_not_inited(
+            &mut calls,
+            |calls| {
+                calls.0 += 1;
+                Ok(Err(not_inited()))
+            },
+            |calls| {
+                calls.1 += 1;
+                Ok(Ok(()))
+            },
+        );
+        assert_eq!(result.unwrap(), Ok(()));
+        assert_eq!(calls, (1, 1));
+    }
+
+    #[test]
+    fn check_not_inited_retry_failure_is_returned() {
+        let mut calls = (0, 0);
+        let result: RequestResult<()> = retry_if_not_inited(
+            &mut calls,
+            |calls| {
+                calls.0 += 1;
+                Ok(Err(not_inited()))
+            },
+            |calls| {
+                calls.1 += 1;
+                Ok(Err(not_inited()))
+            },
+        );
+        assert_eq!(result.unwrap(), Err(not_inited()));
+        assert_eq!(calls, (1, 1));
+    }
+
+    #[test]
+    fn check_other_errors_are_not_retried() {
+        let mut calls = (0, 0);
+        let result: RequestResult<()> = retry_if_not_inited(
+            &mut calls,
+            |calls| {
+                calls.0 += 1;
+                Ok(Err(RPCError {
+                    code: 420,
+                    name: "FLOOD_WAIT".into(),
+                    value: Some(31),
+                }))
+            },
+            |calls| {
+                calls.1 += 1;
+                Ok(Ok(()))
+            },
+        );
+        assert!(result.unwrap().is_err());
+        assert_eq!(calls, (1, 0));
+    }
+
+    #[test]
+    fn check_updates_advance_state_without_gaps() {
+        let sent = |pts, date| -> tl::enums::Updates {
+            tl::types::UpdateShortSentMessage {
+                out: true,
+                id: 1,
+                pts,
+                pts_count: 1,
+                date,
+                media: None,
+                entities: None,
+            }
+            .into()
+        };
+
+        let mut state = UpdateState {
+            pts: 10,
+            date: 100,
+            ..UpdateState::default()
+        };
+        apply_updates_state(&mut state, &sent(11, 101));
+        assert_eq!((state.pts, state.date), (11, 101));
+
+        // A gap is left for `catch_up` to fill.
+        apply_updates_state(&mut state, &sent(13, 102));
+        assert_eq!((state.pts, state.date), (11, 102));
+
+        let mut channel_state = UpdateState::default();
+        channel_state.channels.insert(5, 20);
+        let deleted: tl::enums::Updates = tl::types::UpdateShort {
+            update: tl::types::UpdateDeleteChannelMessages {
+                channel_id: 5,
+                messages: vec![1, 2],
+                pts: 22,
+                pts_count: 2,
+            }
+            .into(),
+            date: 0,
+        }
+        .into();
+        apply_updates_state(&mut channel_state, &deleted);
+        assert_eq!(channel_state.channels[&5], 22);
+        assert_eq!(channel_state.pts, 0);
+    }
+
+    fn internal_error(code: i32, name: &str) -> RPCError {
+        RPCError {
+            code,
+            name: name.into(),
+            value: None,
+        }
+    }
+
+    #[test]
+    fn check_internal_errors_are_retried() {
+        for &(code, name) in &[(-503, "Timeout"), (500, "RPC_CALL_FAIL")] {
+            let mut calls = 0;
+            let result = retry_if_internal_error(&mut calls, Duration::from_millis(0), |calls| {
+                *calls += 1;
+                if *calls == 1 {
+                    Ok(Err(internal_error(code, name)))
+                } else {
+                    Ok(Ok(()))
+                }
+            });
+            assert_eq!(result.unwrap(), Ok(()));
+            assert_eq!(calls, 2);
+        }
+    }
+
+    #[test]
+    fn check_internal_error_retries_are_limited() {
+        let mut calls = 0;
+        let result: RequestResult<()> =
+            retry_if_internal_error(&mut calls, Duration::from_millis(0), |calls| {
+                *calls += 1;
+                Ok(Err(internal_error(-503, "Timeout")))
+            });
+        assert_eq!(result.unwrap(), Err(internal_error(-503, "Timeout")));
+        assert_eq!(calls, INTERNAL_ERROR_RETRIES + 1);
+
+        let mut calls = 0;
+        let result: RequestResult<()> =
+            retry_if_internal_error(&mut calls, Duration::from_millis(0), |calls| {
+                *calls += 1;
+                Ok(Err(not_inited()))
+            });
+        assert!(result.unwrap().is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn check_input_peer_conversions() {
+        let user: tl::enums::InputPeer = tl::types::InputPeerUser {
+            user_id: 1,
+            access_hash: 2,
+        }
+        .into();
+        match input_user(&user) {
+            tl::enums::InputUser::InputUser(user) => {
+                assert_eq!((user.user_id, user.access_hash), (1, 2))
+            }
+            _ => panic!("unexpected input user"),
+        }
+        match input_channel(&user) {
+            tl::enums::InputChannel::InputChannelEmpty(_) => {}
+            _ => panic!("unexpected input channel"),
+        }
+
+        let channel: tl::enums::InputPeer = tl::types::InputPeerChannelFromMessage {
+            peer: user,
+            msg_id: 3,
+            channel_id: 4,
+        }
+        .into();
+        match input_channel(&channel) {
+            tl::enums::InputChannel::InputChannelFromMessage(channel) => {
+                assert_eq!((channel.msg_id, channel.channel_id), (3, 4));
+                match channel.peer {
+                    tl::enums::InputPeer::InputPeerUser(user) => assert_eq!(user.user_id, 1),
+                    _ => panic!("unexpected input peer"),
+                }
+            }
+            _ => panic!("unexpected input channel"),
+        }
+    }
+
+    #[test]
+    fn check_error_from_io() {
+        let rpc_error = RPCError {
+            code: 400,
+            name: "PEER_ID_INVALID".into(),
+            value: None,
+        };
+        let error = Error::from(io::Error::from(rpc_error));
+        match error.rpc_error() {
+            Some(error) => assert_eq!((error.code, &error.name[..]), (400, "PEER_ID_INVALID")),
+            None => panic!("unexpected error: {:?}", error),
+        }
+
+        let error = io::Error::from(DeserializeError::MessageBufferTooSmall);
+        assert!(matches!(Error::from(error), Error::Deserialize(_)));
+
+        let error = io::Error::new(io::ErrorKind::ConnectionReset, "reset by peer");
+        match Error::from(error) {
+            Error::Disconnected(error) => assert_eq!(error.to_string(), "reset by peer"),
+            error => panic!("unexpected error: {:?}", error),
+        }
+
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        assert!(matches!(Error::from(error), Error::Io(_)));
+    }
+
+    #[test]
+    fn check_method_errors_keep_error() {
+        let error = PinDialogError::from(io::Error::from(io::ErrorKind::BrokenPipe));
+        assert!(matches!(
+            error,
+            PinDialogError::Error(Error::Disconnected(_))
+        ));
+
+        let error = SignInError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(error, SignInError::Error(Error::Io(_))));
+
+        let error = SendMediaError::from(io::Error::from(RPCError {
+            code: 400,
+            name: "PEER_ID_INVALID".into(),
+            value: None,
+        }));
+        assert!(matches!(error, SendMediaError::Error(Error::Rpc(_))));
+    }
+
+    #[test]
+    fn check_supports_ttl() {
+        assert!(supports_ttl(&tl::types::InputPeerSelf {}.into()));
+        assert!(supports_ttl(
+            &tl::types::InputPeerUser {
+                user_id: 1,
+                access_hash: 2,
+            }
+            .into()
+        ));
+        assert!(!supports_ttl(
+            &tl::types::InputPeerChannel {
+                channel_id: 1,
+                access_hash: 2,
+            }
+            .into()
+        ));
+    }
+
+    #[test]
+    fn check_integrity_errors_are_detected() {
+        assert!(is_integrity_error(
+            &DecryptionError::MessageKeyMismatch.into()
+        ));
+        assert!(!is_integrity_error(&DecryptionError::InvalidBuffer.into()));
+        assert!(!is_integrity_error(&io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message integrity check failed"
+        )));
+    }
+
+    #[test]
+    fn check_split_message() {
+        assert_eq!(split_message("hello", 10), vec!["hello"]);
+        assert_eq!(split_message("", 10), Vec::<&str>::new());
+
+        // Newlines are preferred over other whitespace.
+        assert_eq!(split_message("ab cd\nef gh", 9), vec!["ab cd", "ef gh"]);
+        assert_eq!(split_message("ab cd ef", 6), vec!["ab cd", "ef"]);
+
+        // Words longer than the limit are split anywhere.
+        assert_eq!(split_message("abcdefg", 3), vec!["abc", "def", "g"]);
+
+        // The length is measured in UTF-16 code units, and characters
+        // outside the BMP take two of them.
+        assert_eq!(split_message("😀😀😀", 4), vec!["😀😀", "😀"]);
+        assert_eq!(split_message("ñññ", 2), vec!["ññ", "ñ"]);
+
+        // Whitespace-only chunks are not sent.
+        assert_eq!(split_message("ab\n\n\n\ncd", 3), vec!["ab", "cd"]);
     }
 }
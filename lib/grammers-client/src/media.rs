@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use grammers_mtproto::errors::RPCError;
+use grammers_tl_types::{self as tl, Deserializable, Serializable};
+use log::debug;
+
+use crate::{generate_random_id, types, unknown_constructor, Client, Error};
+
+/// The size of every part in which files are uploaded, which must be a
+/// multiple of 1 KiB and at most 512 KiB.
+const UPLOAD_PART_SIZE: usize = 512 * 1024;
+
+/// Files larger than this must be uploaded as "big" files.
+const BIG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The size of every chunk in which files are downloaded, which must be
+/// divisible by 4 KiB and at most 1 MiB.
+const DOWNLOAD_CHUNK_SIZE: i32 = 512 * 1024;
+
+impl Client {
+    /// Uploads a local file to Telegram, so that it can be sent as media.
+    ///
+    /// The returned file is only valid for a limited amount of time, so it
+    /// should be used shortly after. See [`upload_file_with_progress`] to be
+    /// notified of the progress of the upload.
+    ///
+    /// [`upload_file_with_progress`]: #method.upload_file_with_progress
+    pub fn upload_file<P: AsRef<Path>>(&mut self, path: P) -> Result<tl::enums::InputFile, Error> {
+        self.upload_file_with_progress(path, |_, _| {})
+    }
+
+    /// Like [`upload_file`], but calls `progress` after every uploaded part
+    /// with the amount of bytes uploaded so far and the total size of the
+    /// file. The last call happens once the upload is complete.
+    ///
+    /// [`upload_file`]: #method.upload_file
+    pub fn upload_file_with_progress<P: AsRef<Path>, F: FnMut(u64, Option<u64>)>(
+        &mut self,
+        path: P,
+        mut progress: F,
+    ) -> Result<tl::enums::InputFile, Error> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => String::new(),
+        };
+
+        let file_id = generate_random_id();
+        let big = size > BIG_FILE_SIZE;
+        let part_size = UPLOAD_PART_SIZE as u64;
+        let total_parts = ((size + part_size - 1) / part_size) as i32;
+
+        let mut uploaded = 0;
+        for file_part in 0..total_parts {
+            let mut bytes = Vec::with_capacity(UPLOAD_PART_SIZE);
+            (&mut file).take(part_size).read_to_end(&mut bytes)?;
+            uploaded += bytes.len() as u64;
+
+            let saved = if big {
+                self.invoke(&tl::functions::upload::SaveBigFilePart {
+                    file_id,
+                    file_part,
+                    file_total_parts: total_parts,
+                    bytes,
+                })??
+            } else {
+                self.invoke(&tl::functions::upload::SaveFilePart {
+                    file_id,
+                    file_part,
+                    bytes,
+                })??
+            };
+            if !saved {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to upload part {} of the file", file_part),
+                )
+                .into());
+            }
+
+            progress(uploaded, Some(size));
+        }
+        if total_parts == 0 {
+            progress(0, Some(0));
+        }
+
+        Ok(if big {
+            tl::types::InputFileBig {
+                id: file_id,
+                parts: total_parts,
+                name,
+            }
+            .into()
+        } else {
+            tl::types::InputFile {
+                id: file_id,
+                parts: total_parts,
+                name,
+                md5_checksum: String::new(),
+            }
+            .into()
+        })
+    }
+
+    /// Downloads the file contained in the media of a message (such as a
+    /// photo or a document) into the given path.
+    ///
+    /// An error with kind `InvalidInput` is returned if the media contains
+    /// no file. See [`download_media_with_progress`] to be notified of the
+    /// progress of the download.
+    ///
+    /// [`download_media_with_progress`]: #method.download_media_with_progress
+    pub fn download_media<P: AsRef<Path>>(
+        &mut self,
+        media: &tl::enums::MessageMedia,
+        path: P,
+    ) -> Result<(), Error> {
+        self.download_media_with_progress(media, path, |_, _| {})
+    }
+
+    /// Like [`download_media`], but calls `progress` after every downloaded
+    /// chunk with the amount of bytes downloaded so far and the total size of
+    /// the file, if known. The last call happens once the download is
+    /// complete.
+    ///
+    /// [`download_media`]: #method.download_media
+    pub fn download_media_with_progress<P: AsRef<Path>, F: FnMut(u64, Option<u64>)>(
+        &mut self,
+        media: &tl::enums::MessageMedia,
+        path: P,
+        mut progress: F,
+    ) -> Result<(), Error> {
+        let (location, size) = types::media_location(media).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the media contains no file")
+        })?;
+        self.download_location(location, size, path.as_ref(), false, &mut progress)
+    }
+
+    /// Like [`download_media`], but verifies every downloaded chunk against
+    /// the SHA-256 hashes Telegram provides (see [`get_file_hashes`]).
+    ///
+    /// An error with kind `InvalidData` is returned if any chunk doesn't
+    /// match its hash, in which case the file is incomplete.
+    ///
+    /// [`download_media`]: #method.download_media
+    /// [`get_file_hashes`]: #method.get_file_hashes
+    pub fn download_media_verified<P: AsRef<Path>>(
+        &mut self,
+        media: &tl::enums::MessageMedia,
+        path: P,
+    ) -> Result<(), Error> {
+        let (location, size) = types::media_location(media).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the media contains no file")
+        })?;
+        self.download_location(location, size, path.as_ref(), true, &mut |_, _| {})
+    }
+
+    /// Returns the SHA-256 hashes of the parts of the file, starting with
+    /// the part at `offset`. Telegram may return only some of them, so this
+    /// should be called again after the last one until all are known.
+    pub fn get_file_hashes(
+        &mut self,
+        location: tl::enums::InputFileLocation,
+        offset: i32,
+    ) -> Result<Vec<tl::types::FileHash>, Error> {
+        let request = tl::functions::upload::GetFileHashes { location, offset };
+        let hashes = match self.invoke(&request)? {
+            Err(RPCError {
+                name,
+                value: Some(file_dc_id),
+                ..
+            }) if name == "FILE_MIGRATE" => self.invoke_in_dc(file_dc_id as i32, &request)??,
+            result => result?,
+        };
+        Ok(hashes.into_iter().map(tl::types::FileHash::from).collect())
+    }
+
+    /// Downloads the largest size of a photo, such as a profile photo, into
+    /// the given path.
+    pub fn download_photo<P: AsRef<Path>>(
+        &mut self,
+        photo: &tl::types::Photo,
+        path: P,
+    ) -> Result<(), Error> {
+        let (location, size) = types::photo_location(photo).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the photo has no downloadable size",
+            )
+        })?;
+        self.download_location(location, size, path.as_ref(), false, &mut |_, _| {})
+    }
+
+    fn download_location(
+        &mut self,
+        location: tl::enums::InputFileLocation,
+        size: u64,
+        path: &Path,
+        verify: bool,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        let mut hashes = Vec::new();
+        let mut request = tl::functions::upload::GetFile {
+            precise: false,
+            cdn_supported: false,
+            location,
+            offset: 0,
+            limit: DOWNLOAD_CHUNK_SIZE,
+        };
+
+        // Files may be stored in a datacenter other than the user's, in
+        // which case Telegram asks to download them from there instead.
+        let mut dc_id = None;
+        let mut downloaded = 0;
+        loop {
+            let result = match dc_id {
+                Some(dc_id) => self.invoke_in_dc(dc_id, &request)?,
+                None => self.invoke(&request)?,
+            };
+            let bytes = match result {
+                Ok(tl::enums::upload::File::File(file)) => file.bytes,
+                Ok(tl::enums::upload::File::FileCdnRedirect(_)) => {
+                    // Not possible, since CDN support was not advertised.
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "downloading files from a CDN is not supported",
+                    )
+                    .into());
+                }
+                #[allow(unreachable_patterns)]
+                Ok(_) => return Err(unknown_constructor().into()),
+                Err(RPCError {
+                    name,
+                    value: Some(file_dc_id),
+                    ..
+                }) if name == "FILE_MIGRATE" && dc_id.is_none() => {
+                    debug!("file is stored in datacenter {}", file_dc_id);
+                    dc_id = Some(file_dc_id as i32);
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+
+            if verify {
+                self.verify_chunk(dc_id, &request, &bytes, &mut hashes)?;
+            }
+            file.write_all(&bytes)?;
+            downloaded += bytes.len() as u64;
+            progress(downloaded, Some(size));
+
+            if bytes.len() < request.limit as usize {
+                break;
+            }
+            request.offset += request.limit;
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Checks that the chunk downloaded by the request matches the hashes of
+    /// the parts it contains, fetching more hashes when they're not known.
+    fn verify_chunk(
+        &mut self,
+        dc_id: Option<i32>,
+        request: &tl::functions::upload::GetFile,
+        bytes: &[u8],
+        hashes: &mut Vec<tl::types::FileHash>,
+    ) -> Result<(), Error> {
+        let mut offset = request.offset;
+        let end = request.offset + bytes.len() as i32;
+        while offset < end {
+            let hash = match hashes.iter().find(|hash| hash.offset == offset) {
+                Some(hash) => hash,
+                None => {
+                    let hashes_request = tl::functions::upload::GetFileHashes {
+                        location: tl::enums::InputFileLocation::from_bytes(
+                            &request.location.to_bytes(),
+                        )
+                        .unwrap(),
+                        offset,
+                    };
+                    let result = match dc_id {
+                        Some(dc_id) => self.invoke_in_dc(dc_id, &hashes_request)??,
+                        None => self.invoke(&hashes_request)??,
+                    };
+                    *hashes = result.into_iter().map(tl::types::FileHash::from).collect();
+                    match hashes.iter().find(|hash| hash.offset == offset) {
+                        Some(hash) => hash,
+                        None => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "the hash of a downloaded part is unknown",
+                            )
+                            .into())
+                        }
+                    }
+                }
+            };
+
+            let start = (offset - request.offset) as usize;
+            let stop = (start + hash.limit.max(1) as usize).min(bytes.len());
+            if grammers_crypto::sha256(&bytes[start..stop])[..] != hash.hash[..] {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "a downloaded part does not match its hash",
+                )
+                .into());
+            }
+            offset += hash.limit.max(1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use grammers_mtproto::errors::RPCError;
+use grammers_mtsender::RequestResult;
+use grammers_tl_types as tl;
+use log::info;
+
+use crate::types::PackedType;
+use crate::{
+    clone_input_peer, extract_message_from_updates, generate_random_id, Client, Error,
+    SendQueuedError,
+};
+
+/// The minimum time to wait between two messages sent to the same chat.
+const CHAT_SEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The minimum time to wait between any two messages sent.
+const GLOBAL_SEND_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A handle to a message that was queued with [`Client::queue_message`].
+///
+/// [`Client::queue_message`]: struct.Client.html#method.queue_message
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct QueuedMessage(u64);
+
+struct Pending {
+    handle: QueuedMessage,
+    request: tl::functions::messages::SendMessage,
+}
+
+/// A FIFO of messages waiting to be sent, along with the information needed
+/// to throttle them.
+pub(crate) struct MessageQueue {
+    next_handle: u64,
+    pending: VecDeque<Pending>,
+    last_sent_to: HashMap<ChatKey, Instant>,
+    last_sent: Option<Instant>,
+}
+
+/// The kind and ID of a chat, since users, groups and channels may share
+/// the same ID.
+type ChatKey = (PackedType, i32);
+
+/// Identifies the chat an input peer refers to, for throttling purposes.
+fn chat_key(chat: &tl::enums::InputPeer) -> ChatKey {
+    match chat {
+        tl::enums::InputPeer::InputPeerChat(chat) => (PackedType::Chat, chat.chat_id),
+        tl::enums::InputPeer::InputPeerUser(user) => (PackedType::User, user.user_id),
+        tl::enums::InputPeer::InputPeerChannel(channel) => {
+            (PackedType::Channel, channel.channel_id)
+        }
+        tl::enums::InputPeer::InputPeerUserFromMessage(user) => (PackedType::User, user.user_id),
+        tl::enums::InputPeer::InputPeerChannelFromMessage(channel) => {
+            (PackedType::Channel, channel.channel_id)
+        }
+        // Includes `InputPeerEmpty` and `InputPeerSelf`.
+        _ => (PackedType::User, 0),
+    }
+}
+
+impl MessageQueue {
+    pub fn new() -> Self {
+        Self {
+            next_handle: 0,
+            pending: VecDeque::new(),
+            last_sent_to: HashMap::new(),
+            last_sent: None,
+        }
+    }
+
+    fn push(&mut self, request: tl::functions::messages::SendMessage) -> QueuedMessage {
+        let handle = QueuedMessage(self.next_handle);
+        self.next_handle += 1;
+        self.pending.push_back(Pending { handle, request });
+        handle
+    }
+
+    /// The instant at which a message to the given chat may be sent.
+    fn ready_at(&self, chat: ChatKey) -> Option<Instant> {
+        let chat_ready = self
+            .last_sent_to
+            .get(&chat)
+            .map(|instant| *instant + CHAT_SEND_INTERVAL);
+        let global_ready = self.last_sent.map(|instant| instant + GLOBAL_SEND_INTERVAL);
+        match (chat_ready, global_ready) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Pops the message that can be sent the soonest, along with the instant
+    /// at which it may be sent.
+    ///
+    /// Only the oldest queued message of each chat is considered, so that
+    /// the order of messages within the same chat is preserved.
+    fn pop(&mut self) -> Option<(Pending, Option<Instant>)> {
+        let mut seen = Vec::new();
+        let mut best: Option<(usize, Option<Instant>)> = None;
+        for (i, pending) in self.pending.iter().enumerate() {
+            let chat = chat_key(&pending.request.peer);
+            if seen.contains(&chat) {
+                continue;
+            }
+            seen.push(chat);
+
+            let ready_at = self.ready_at(chat);
+            best = match best {
+                Some((_, best_ready_at)) if best_ready_at <= ready_at => best,
+                _ => Some((i, ready_at)),
+            };
+        }
+
+        let (index, ready_at) = best?;
+        self.pending
+            .remove(index)
+            .map(|pending| (pending, ready_at))
+    }
+
+    fn mark_sent(&mut self, chat: &tl::enums::InputPeer) {
+        let now = Instant::now();
+        self.last_sent_to.insert(chat_key(chat), now);
+        self.last_sent = Some(now);
+    }
+}
+
+impl Client {
+    /// Queues a text message to be sent to the desired chat, and returns
+    /// a handle to it.
+    ///
+    /// Queued messages are only sent by [`send_queued`] and [`flush_queue`],
+    /// at a rate that should not trigger flood waits. Messages sent to the
+    /// same chat are always sent in the order they were queued.
+    ///
+    /// [`send_queued`]: #method.send_queued
+    /// [`flush_queue`]: #method.flush_queue
+    pub fn queue_message<C: crate::IntoInput<tl::enums::InputPeer>, M: Into<String>>(
+        &mut self,
+        chat: C,
+        message: M,
+    ) -> Result<QueuedMessage, Error> {
+        let chat = chat.convert(self)?;
+        // The random ID is generated now so that retrying the request can't
+        // cause the message to be sent twice.
+        Ok(self
+            .message_queue
+            .push(tl::functions::messages::SendMessage {
+                no_webpage: false,
+                silent: false,
+                background: false,
+                clear_draft: false,
+                peer: chat,
+                reply_to_msg_id: None,
+                message: message.into(),
+                random_id: generate_random_id(),
+                reply_markup: None,
+                entities: None,
+                schedule_date: None,
+            }))
+    }
+
+    /// Returns how many queued messages have not been sent yet.
+    pub fn queued_message_count(&self) -> usize {
+        self.message_queue.pending.len()
+    }
+
+    /// Sends the next queued message, blocking for as long as necessary to
+    /// respect the rate limits, and returns the handle of the message that
+    /// was sent. If there are no queued messages, `None` is returned.
+    ///
+    /// Should Telegram ask to wait before sending more messages, the method
+    /// will wait and try again. If Telegram refuses to send the message, the
+    /// handle of the message is returned along with the error.
+    pub fn send_queued(&mut self) -> Result<Option<QueuedMessage>, SendQueuedError> {
+        let (pending, ready_at) = match self.message_queue.pop() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        match self.send_throttled(&pending.request, ready_at) {
+            Ok(Ok(_)) => {}
+            Ok(Err(error)) => {
+                self.message_queue.mark_sent(&pending.request.peer);
+                return Err(SendQueuedError::Rejected {
+                    message: pending.handle,
+                    error,
+                });
+            }
+            Err(error) => {
+                // The message may or may not have been sent, but it's
+                // safe to retry because the random ID is the same.
+                self.message_queue.pending.push_front(pending);
+                return Err(error.into());
+            }
+        }
+
+        self.message_queue.mark_sent(&pending.request.peer);
+        Ok(Some(pending.handle))
+    }
+
+    /// Sends the same text message to every chat, one after another, and
+    /// returns the ID of the message sent to each of them (in the same order).
+    ///
+    /// Messages are sent at the same rate as queued ones, and flood waits
+    /// are waited out. If sending to a chat fails, the error is returned in
+    /// its position and the rest of chats are still sent the message.
+    /// Network errors, however, stop the broadcast.
+    pub fn broadcast_message(
+        &mut self,
+        chats: &[tl::enums::InputPeer],
+        text: &str,
+    ) -> Result<Vec<Result<i32, Error>>, Error> {
+        let mut results = Vec::with_capacity(chats.len());
+        for chat in chats {
+            let random_id = generate_random_id();
+            let request = tl::functions::messages::SendMessage {
+                no_webpage: false,
+                silent: false,
+                background: false,
+                clear_draft: false,
+                peer: clone_input_peer(chat),
+                reply_to_msg_id: None,
+                message: text.to_string(),
+                random_id,
+                reply_markup: None,
+                entities: None,
+                schedule_date: None,
+            };
+
+            let ready_at = self.message_queue.ready_at(chat_key(chat));
+            let result = self.send_throttled(&request, ready_at)?;
+            self.message_queue.mark_sent(chat);
+            results.push(match result {
+                Ok(updates) => extract_message_from_updates(&updates, random_id).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the sent message was not found in the updates",
+                    )
+                    .into()
+                }),
+                Err(error) => Err(error.into()),
+            });
+        }
+        Ok(results)
+    }
+
+    /// Sends the message once `ready_at` is reached, and sends it again
+    /// after waiting for as long as Telegram asks should it report a flood
+    /// wait.
+    fn send_throttled(
+        &mut self,
+        request: &tl::functions::messages::SendMessage,
+        ready_at: Option<Instant>,
+    ) -> RequestResult<tl::enums::Updates> {
+        if let Some(ready_at) = ready_at {
+            let now = Instant::now();
+            if ready_at > now {
+                thread::sleep(ready_at - now);
+            }
+        }
+
+        loop {
+            match self.invoke_and_process_updates(request)? {
+                Err(RPCError { name, value, .. }) if name == "FLOOD_WAIT" => {
+                    let delay = Duration::from_secs(value.unwrap_or(1) as u64);
+                    info!("sleeping on flood wait for {:?}", delay);
+                    thread::sleep(delay);
+                }
+                result => return Ok(result),
+            }
+        }
+    }
+
+    /// Sends all the queued messages, blocking until every one of them has
+    /// been sent.
+    ///
+    /// Messages which Telegram refuses to send don't stop the flush. Their
+    /// handles are returned along with the error, in the order they were
+    /// rejected. Any other error stops the flush, leaving the rest of
+    /// messages in the queue.
+    pub fn flush_queue(&mut self) -> Result<Vec<(QueuedMessage, RPCError)>, SendQueuedError> {
+        let mut rejected = Vec::new();
+        loop {
+            match self.send_queued() {
+                Ok(Some(_)) => {}
+                Ok(None) => break Ok(rejected),
+                Err(SendQueuedError::Rejected { message, error }) => {
+                    rejected.push((message, error))
+                }
+                Err(error) => break Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_chat_key_separates_kinds() {
+        let user: tl::enums::InputPeer = tl::types::InputPeerUser {
+            user_id: 1,
+            access_hash: 0,
+        }
+        .into();
+        let chat: tl::enums::InputPeer = tl::types::InputPeerChat { chat_id: 1 }.into();
+        let channel: tl::enums::InputPeer = tl::types::InputPeerChannel {
+            channel_id: 1,
+            access_hash: 0,
+        }
+        .into();
+        assert_ne!(chat_key(&user), chat_key(&chat));
+        assert_ne!(chat_key(&chat), chat_key(&channel));
+        assert_ne!(chat_key(&user), chat_key(&channel));
+
+        // Sending to one of them doesn't delay the others.
+        let mut queue = MessageQueue::new();
+        queue.mark_sent(&user);
+        let user_ready = queue.ready_at(chat_key(&user)).unwrap();
+        let chat_ready = queue.ready_at(chat_key(&chat)).unwrap();
+        assert!(chat_ready < user_ready);
+    }
+}
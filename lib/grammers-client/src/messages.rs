@@ -0,0 +1,157 @@
+use fallible_iterator::FallibleIterator;
+use grammers_tl_types as tl;
+
+use crate::{unknown_constructor, Client, Error};
+
+const MAX_MESSAGES_PER_REQUEST: i32 = 100;
+
+pub struct Messages<'a> {
+    client: &'a mut Client,
+    batch_stack: Vec<tl::enums::Message>,
+    total: Option<usize>,
+    done: bool,
+    reverse: bool,
+    request: tl::functions::messages::GetHistory,
+}
+
+fn message_id(message: &tl::enums::Message) -> i32 {
+    match message {
+        tl::enums::Message::Message(message) => message.id,
+        tl::enums::Message::MessageService(message) => message.id,
+        tl::enums::Message::MessageEmpty(message) => message.id,
+        #[allow(unreachable_patterns)]
+        _ => 0,
+    }
+}
+
+impl<'a> Messages<'a> {
+    pub fn new(client: &'a mut Client, peer: tl::enums::InputPeer) -> Self {
+        Self {
+            client,
+            batch_stack: Vec::with_capacity(MAX_MESSAGES_PER_REQUEST as usize),
+            total: None,
+            done: false,
+            reverse: false,
+            request: tl::functions::messages::GetHistory {
+                peer,
+                offset_id: 0,
+                offset_date: 0,
+                add_offset: 0,
+                limit: MAX_MESSAGES_PER_REQUEST,
+                max_id: 0,
+                min_id: 0,
+                hash: 0,
+            },
+        }
+    }
+
+    /// Changes the order in which messages are returned. By default, the
+    /// newest messages are returned first. If `reverse` is `true`, the
+    /// iteration will instead start from the very first message in the
+    /// chat and walk upwards until it reaches the newest one.
+    ///
+    /// This should be set before the iterator is advanced.
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        if reverse {
+            // With a negative `add_offset` of exactly `-limit`, the server
+            // returns the messages *at and after* `offset_id` (instead of
+            // the ones before it). Message IDs start at 1.
+            self.request.offset_id = self.request.offset_id.max(1);
+            self.request.add_offset = -self.request.limit;
+        } else {
+            self.request.offset_id = 0;
+            self.request.add_offset = 0;
+        }
+        self
+    }
+
+    /// If the batch index is beyond the buffer length, it fills the buffer.
+    fn ensure_buffer(&mut self) -> Result<(), Error> {
+        if self.batch_stack.is_empty() && !self.done {
+            self.fill_buffer()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn update_messages(&mut self, messages: Vec<tl::enums::Message>) {
+        // Messages always arrive sorted by ID in descending order.
+        if messages.len() < self.request.limit as usize {
+            self.done = true;
+        }
+
+        // Figure out the offset for the next request before consuming them.
+        if self.reverse {
+            // Continue right after the newest message we got.
+            if let Some(message) = messages.first() {
+                self.request.offset_id = message_id(message) + 1;
+            }
+        } else if let Some(message) = messages.last() {
+            // Continue right before the oldest message we got.
+            self.request.offset_id = message_id(message);
+        }
+
+        // The stack is popped from the end, so the message that should be
+        // returned first must be the last one pushed.
+        if self.reverse {
+            self.batch_stack.extend(messages);
+        } else {
+            self.batch_stack.extend(messages.into_iter().rev());
+        }
+    }
+
+    fn fill_buffer(&mut self) -> Result<(), Error> {
+        match self.client.invoke(&self.request)?? {
+            tl::enums::messages::Messages::Messages(tl::types::messages::Messages {
+                messages,
+                ..
+            }) => {
+                self.total = Some(messages.len());
+                self.done = true;
+                self.update_messages(messages);
+            }
+            tl::enums::messages::Messages::MessagesSlice(tl::types::messages::MessagesSlice {
+                count,
+                messages,
+                ..
+            }) => {
+                self.total = Some(count as usize);
+                self.update_messages(messages);
+            }
+            tl::enums::messages::Messages::ChannelMessages(
+                tl::types::messages::ChannelMessages {
+                    count, messages, ..
+                },
+            ) => {
+                self.total = Some(count as usize);
+                self.update_messages(messages);
+            }
+            tl::enums::messages::Messages::MessagesNotModified(messages) => {
+                self.total = Some(messages.count as usize);
+                self.done = true;
+            }
+            #[allow(unreachable_patterns)]
+            _ => return Err(unknown_constructor().into()),
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FallibleIterator for Messages<'a> {
+    type Item = tl::enums::Message;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        self.ensure_buffer()?;
+        Ok(self.batch_stack.pop())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if let Some(total) = self.total {
+            (total, Some(total))
+        } else {
+            (0, None)
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers for bots to read the data users share through Telegram Passport.
+//!
+//! When a user shares their data, the bot receives a service message with a
+//! `messageActionSecureValuesSentMe` action. Its credentials are decrypted
+//! first with [`decrypt_credentials`], which results in a JSON document
+//! containing the `secret` and `data_hash` of every value. Those are then
+//! used to [`decrypt_secure_data`].
+//!
+//! [`decrypt_credentials`]: fn.decrypt_credentials.html
+//! [`decrypt_secure_data`]: fn.decrypt_secure_data.html
+use std::io;
+
+use grammers_crypto::passport;
+use grammers_tl_types as tl;
+
+use crate::unknown_constructor;
+
+/// Returns the values and encrypted credentials shared with the bot, if the
+/// action corresponds to a user sharing their Telegram Passport data.
+pub fn secure_values(
+    action: &tl::enums::MessageAction,
+) -> Option<(
+    &[tl::enums::SecureValue],
+    &tl::enums::SecureCredentialsEncrypted,
+)> {
+    match action {
+        tl::enums::MessageAction::MessageActionSecureValuesSentMe(action) => {
+            Some((&action.values, &action.credentials))
+        }
+        _ => None,
+    }
+}
+
+/// Decrypts the credentials using the bot's private key in PEM format, and
+/// returns the resulting JSON document.
+pub fn decrypt_credentials(
+    credentials: &tl::enums::SecureCredentialsEncrypted,
+    private_key_pem: &[u8],
+) -> io::Result<Vec<u8>> {
+    let credentials = match credentials {
+        tl::enums::SecureCredentialsEncrypted::SecureCredentialsEncrypted(x) => x,
+        #[allow(unreachable_patterns)]
+        _ => return Err(unknown_constructor()),
+    };
+
+    let secret = passport::decrypt_credentials_secret(private_key_pem, &credentials.secret)?;
+    passport::decrypt_secure_data(&credentials.data, &credentials.hash, &secret)
+}
+
+/// Decrypts the data of a value with the `secret` found in the decrypted
+/// credentials, and returns the resulting JSON document.
+///
+/// The credentials also contain the expected `data_hash`, which should be
+/// compared with the one of the value to make sure it's the right one.
+pub fn decrypt_secure_data(value: &tl::enums::SecureData, secret: &[u8]) -> io::Result<Vec<u8>> {
+    let value = match value {
+        tl::enums::SecureData::SecureData(x) => x,
+        #[allow(unreachable_patterns)]
+        _ => return Err(unknown_constructor()),
+    };
+    passport::decrypt_secure_data(&value.data, &value.data_hash, secret)
+}
@@ -0,0 +1,25 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The items most programs need, so that they can be imported at once:
+//!
+//! ```
+//! use grammers_client::prelude::*;
+//! ```
+//!
+//! This includes the [`Client`], the sessions it can use, the traits
+//! required to work with its methods and the most common [`types`]. The
+//! raw Telegram types are available as `tl`.
+//!
+//! [`Client`]: ../struct.Client.html
+//! [`types`]: ../types/index.html
+pub use crate::types::{Dialog, Entity, Media, Message, Update};
+pub use crate::{Client, Error, IntoInput, RPCError};
+pub use fallible_iterator::FallibleIterator;
+pub use grammers_session::{MemorySession, Session, TextSession};
+pub use grammers_tl_types as tl;
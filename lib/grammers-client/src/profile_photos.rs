@@ -0,0 +1,79 @@
+use fallible_iterator::FallibleIterator;
+use grammers_tl_types as tl;
+
+use crate::{unknown_constructor, Client, Error};
+
+const MAX_PHOTOS_PER_REQUEST: i32 = 100;
+
+/// An iterator over the profile photos of a user, which returns the current
+/// photo first.
+pub struct ProfilePhotos<'a> {
+    client: &'a mut Client,
+    batch_stack: Vec<tl::types::Photo>,
+    done: bool,
+    request: tl::functions::photos::GetUserPhotos,
+}
+
+impl<'a> ProfilePhotos<'a> {
+    pub fn new(client: &'a mut Client, user: tl::enums::InputUser) -> Self {
+        Self {
+            client,
+            batch_stack: Vec::with_capacity(MAX_PHOTOS_PER_REQUEST as usize),
+            done: false,
+            request: tl::functions::photos::GetUserPhotos {
+                user_id: user,
+                offset: 0,
+                max_id: 0,
+                limit: MAX_PHOTOS_PER_REQUEST,
+            },
+        }
+    }
+
+    /// If the batch index is beyond the buffer length, it fills the buffer.
+    fn ensure_buffer(&mut self) -> Result<(), Error> {
+        if self.batch_stack.is_empty() && !self.done {
+            self.fill_buffer()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn fill_buffer(&mut self) -> Result<(), Error> {
+        let photos = match self.client.invoke(&self.request)?? {
+            tl::enums::photos::Photos::Photos(photos) => {
+                // Everything was returned at once.
+                self.done = true;
+                photos.photos
+            }
+            tl::enums::photos::Photos::PhotosSlice(photos) => {
+                let fetched = self.request.offset + photos.photos.len() as i32;
+                if photos.photos.is_empty() || fetched >= photos.count {
+                    self.done = true;
+                }
+                photos.photos
+            }
+            #[allow(unreachable_patterns)]
+            _ => return Err(unknown_constructor().into()),
+        };
+        self.request.offset += photos.len() as i32;
+
+        // The stack is popped from the end, so the first photo goes last.
+        self.batch_stack
+            .extend(photos.into_iter().rev().filter_map(|photo| match photo {
+                tl::enums::Photo::Photo(photo) => Some(photo),
+                // Includes `PhotoEmpty`.
+                _ => None,
+            }));
+        Ok(())
+    }
+}
+
+impl<'a> FallibleIterator for ProfilePhotos<'a> {
+    type Item = tl::types::Photo;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        self.ensure_buffer()?;
+        Ok(self.batch_stack.pop())
+    }
+}
@@ -0,0 +1,144 @@
+use std::convert::TryInto;
+use std::thread;
+use std::time::Duration;
+
+use grammers_crypto::base64;
+use grammers_tl_types as tl;
+
+use crate::{unknown_constructor, Client, Error};
+
+/// How often Telegram is asked whether the token was accepted.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A login in progress, which completes once another Telegram application
+/// where the account is logged in scans the QR code of its [`url`].
+///
+/// [`url`]: #method.url
+pub struct QrLogin<'a> {
+    client: &'a mut Client,
+    api_id: i32,
+    api_hash: String,
+    token: Vec<u8>,
+    expires: i32,
+}
+
+impl<'a> QrLogin<'a> {
+    pub(crate) fn new(client: &'a mut Client, api_id: i32, api_hash: &str) -> Result<Self, Error> {
+        let mut login = Self {
+            client,
+            api_id,
+            api_hash: api_hash.to_string(),
+            token: Vec::new(),
+            expires: 0,
+        };
+        // If the account was already logged in, there is no token, but the
+        // next export will succeed again, so `wait` returns right away.
+        login.export()?;
+        Ok(login)
+    }
+
+    /// The `tg://login` URL to render as a QR code.
+    ///
+    /// It changes after the token expires, so it should be rendered again
+    /// every time [`wait`] returns `None`.
+    ///
+    /// [`wait`]: #method.wait
+    pub fn url(&self) -> String {
+        format!("tg://login?token={}", base64::encode_url(&self.token))
+    }
+
+    /// The Unix timestamp at which the current token expires.
+    pub fn expires(&self) -> i32 {
+        self.expires
+    }
+
+    /// Waits until the QR code is scanned and the login completes, and
+    /// returns the logged-in user.
+    ///
+    /// If the token expires first, a new one is exported and `None` is
+    /// returned, so that the new [`url`] can be shown before waiting again.
+    ///
+    /// Accounts with two-step verification enabled fail with the
+    /// `SESSION_PASSWORD_NEEDED` error.
+    ///
+    /// [`url`]: #method.url
+    pub fn wait(&mut self) -> Result<Option<tl::types::User>, Error> {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let previous_token = self.token.clone();
+            if let Some(user) = self.export()? {
+                self.client.me = None;
+                return Ok(Some(user));
+            }
+            if self.token != previous_token {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Exports the login token, which either returns the same token if it
+    /// was not accepted yet, a new one if it expired, or the authorization
+    /// if it was accepted.
+    fn export(&mut self) -> Result<Option<tl::types::User>, Error> {
+        let token = self
+            .client
+            .invoke(&tl::functions::auth::ExportLoginToken {
+                api_id: self.api_id,
+                api_hash: self.api_hash.clone(),
+                except_ids: Vec::new(),
+            })??;
+        self.handle_token(token)
+    }
+
+    fn handle_token(
+        &mut self,
+        token: tl::enums::auth::LoginToken,
+    ) -> Result<Option<tl::types::User>, Error> {
+        match token {
+            tl::enums::auth::LoginToken::LoginToken(token) => {
+                self.token = token.token;
+                self.expires = token.expires;
+                Ok(None)
+            }
+            tl::enums::auth::LoginToken::LoginTokenMigrateTo(migrate) => {
+                // The account lives in a different datacenter, where the
+                // token has to be imported to complete the login.
+                self.client.migrate_to_dc(migrate.dc_id)?;
+                let token = self
+                    .client
+                    .init_invoke(&tl::functions::auth::ImportLoginToken {
+                        token: migrate.token,
+                    })??;
+                match token {
+                    tl::enums::auth::LoginToken::LoginTokenMigrateTo(_) => {
+                        Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "login token migrated more than once",
+                        )
+                        .into())
+                    }
+                    token => self.handle_token(token),
+                }
+            }
+            tl::enums::auth::LoginToken::LoginTokenSuccess(success) => {
+                match success.authorization {
+                    tl::enums::auth::Authorization::Authorization(authorization) => {
+                        // Safe to unwrap, Telegram won't send `UserEmpty` here.
+                        Ok(Some(authorization.user.try_into().unwrap()))
+                    }
+                    tl::enums::auth::Authorization::AuthorizationSignUpRequired(_) => {
+                        Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "a login token cannot be used to sign up",
+                        )
+                        .into())
+                    }
+                    #[allow(unreachable_patterns)]
+                    _ => Err(unknown_constructor().into()),
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(unknown_constructor().into()),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use fallible_iterator::FallibleIterator;
+use grammers_tl_types::RPC;
+
+use crate::{Client, Error};
+
+/// An iterator over the results of a sequence of requests, where each
+/// request is built from the result of the previous one.
+///
+/// This is returned by [`Client::invoke_stream`].
+///
+/// [`Client::invoke_stream`]: struct.Client.html#method.invoke_stream
+pub struct RequestStream<'a, R: RPC, F> {
+    client: &'a mut Client,
+    make_request: F,
+    request: Option<R>,
+    started: bool,
+}
+
+impl<'a, R, F> RequestStream<'a, R, F>
+where
+    R: RPC,
+    F: FnMut(Option<&R::Return>) -> Option<R>,
+{
+    pub(crate) fn new(client: &'a mut Client, make_request: F) -> Self {
+        Self {
+            client,
+            make_request,
+            request: None,
+            started: false,
+        }
+    }
+}
+
+impl<'a, R, F> FallibleIterator for RequestStream<'a, R, F>
+where
+    R: RPC,
+    F: FnMut(Option<&R::Return>) -> Option<R>,
+{
+    type Item = R::Return;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        if !self.started {
+            self.started = true;
+            self.request = (self.make_request)(None);
+        }
+
+        // The request is taken out so that the stream ends if it fails.
+        let request = match self.request.take() {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+
+        let response = self.client.invoke(&request)??;
+        self.request = (self.make_request)(Some(&response));
+        Ok(Some(response))
+    }
+}
@@ -0,0 +1,114 @@
+use grammers_tl_types as tl;
+
+/// An entry in the administrator log of a channel or megagroup.
+pub struct AdminLogEntry {
+    /// The identifier of the entry.
+    pub id: i64,
+    /// The Unix timestamp at which the event occurred.
+    pub date: i32,
+    /// The ID of the user who performed the action.
+    pub user_id: i32,
+    /// What happened.
+    pub event: AdminLogEvent,
+}
+
+/// An action recorded in the administrator log of a channel or megagroup.
+pub enum AdminLogEvent {
+    /// The title was changed.
+    TitleChanged { prev: String, new: String },
+    /// The description was changed.
+    AboutChanged { prev: String, new: String },
+    /// The public username was changed (empty if there is none).
+    UsernameChanged { prev: String, new: String },
+    /// The photo was changed.
+    PhotoChanged {
+        prev: tl::enums::Photo,
+        new: tl::enums::Photo,
+    },
+    /// A message was pinned (or unpinned, if the message is empty).
+    MessagePinned(tl::enums::Message),
+    /// A message was edited.
+    MessageEdited {
+        prev: tl::enums::Message,
+        new: tl::enums::Message,
+    },
+    /// A message was deleted.
+    MessageDeleted(tl::enums::Message),
+    /// A user joined.
+    ParticipantJoined,
+    /// A user left.
+    ParticipantLeft,
+    /// A user was invited.
+    ParticipantInvited(tl::enums::ChannelParticipant),
+    /// A user was banned, unbanned, kicked or had their rights restricted.
+    ParticipantBanToggled {
+        prev: tl::enums::ChannelParticipant,
+        new: tl::enums::ChannelParticipant,
+    },
+    /// A user was promoted or demoted, or their admin rights changed.
+    ParticipantAdminToggled {
+        prev: tl::enums::ChannelParticipant,
+        new: tl::enums::ChannelParticipant,
+    },
+    /// Any other action that does not have a more convenient variant yet.
+    Raw(tl::enums::ChannelAdminLogEventAction),
+}
+
+impl From<tl::enums::ChannelAdminLogEventAction> for AdminLogEvent {
+    fn from(action: tl::enums::ChannelAdminLogEventAction) -> Self {
+        use tl::enums::ChannelAdminLogEventAction as A;
+
+        match action {
+            A::ChannelAdminLogEventActionChangeTitle(a) => Self::TitleChanged {
+                prev: a.prev_value,
+                new: a.new_value,
+            },
+            A::ChannelAdminLogEventActionChangeAbout(a) => Self::AboutChanged {
+                prev: a.prev_value,
+                new: a.new_value,
+            },
+            A::ChannelAdminLogEventActionChangeUsername(a) => Self::UsernameChanged {
+                prev: a.prev_value,
+                new: a.new_value,
+            },
+            A::ChannelAdminLogEventActionChangePhoto(a) => Self::PhotoChanged {
+                prev: a.prev_photo,
+                new: a.new_photo,
+            },
+            A::ChannelAdminLogEventActionUpdatePinned(a) => Self::MessagePinned(a.message),
+            A::ChannelAdminLogEventActionEditMessage(a) => Self::MessageEdited {
+                prev: a.prev_message,
+                new: a.new_message,
+            },
+            A::ChannelAdminLogEventActionDeleteMessage(a) => Self::MessageDeleted(a.message),
+            A::ChannelAdminLogEventActionParticipantJoin(_) => Self::ParticipantJoined,
+            A::ChannelAdminLogEventActionParticipantLeave(_) => Self::ParticipantLeft,
+            A::ChannelAdminLogEventActionParticipantInvite(a) => {
+                Self::ParticipantInvited(a.participant)
+            }
+            A::ChannelAdminLogEventActionParticipantToggleBan(a) => Self::ParticipantBanToggled {
+                prev: a.prev_participant,
+                new: a.new_participant,
+            },
+            A::ChannelAdminLogEventActionParticipantToggleAdmin(a) => {
+                Self::ParticipantAdminToggled {
+                    prev: a.prev_participant,
+                    new: a.new_participant,
+                }
+            }
+            action => Self::Raw(action),
+        }
+    }
+}
+
+impl From<tl::enums::ChannelAdminLogEvent> for AdminLogEntry {
+    fn from(event: tl::enums::ChannelAdminLogEvent) -> Self {
+        let event = tl::types::ChannelAdminLogEvent::from(event);
+        Self {
+            id: event.id,
+            date: event.date,
+            user_id: event.user_id,
+            event: event.action.into(),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use grammers_tl_types as tl;
+
+use super::Entity;
+
+/// Information about a chat obtained from an invite link, before (or
+/// without) joining it.
+pub struct ChatInvitePreview {
+    /// The title of the chat.
+    pub title: String,
+    /// How many members the chat has, if known.
+    pub participants_count: Option<i32>,
+    /// Whether the current account is already a member of the chat.
+    pub already_joined: bool,
+    /// The chat itself, which is only known if it was already joined.
+    pub chat: Option<Entity>,
+}
+
+impl From<tl::enums::ChatInvite> for ChatInvitePreview {
+    fn from(invite: tl::enums::ChatInvite) -> Self {
+        match invite {
+            tl::enums::ChatInvite::ChatInvite(invite) => Self {
+                title: invite.title,
+                participants_count: Some(invite.participants_count),
+                already_joined: false,
+                chat: None,
+            },
+            tl::enums::ChatInvite::ChatInviteAlready(invite) => match invite.chat {
+                tl::enums::Chat::Chat(chat) => Self {
+                    title: chat.title.clone(),
+                    participants_count: Some(chat.participants_count),
+                    already_joined: true,
+                    chat: Some(Entity::Chat(chat)),
+                },
+                tl::enums::Chat::Channel(channel) => Self {
+                    title: channel.title.clone(),
+                    participants_count: channel.participants_count,
+                    already_joined: true,
+                    chat: Some(Entity::Channel(channel)),
+                },
+                tl::enums::Chat::ChatForbidden(chat) => Self {
+                    title: chat.title,
+                    participants_count: None,
+                    already_joined: true,
+                    chat: None,
+                },
+                tl::enums::Chat::ChannelForbidden(channel) => Self {
+                    title: channel.title,
+                    participants_count: None,
+                    already_joined: true,
+                    chat: None,
+                },
+                // Includes `ChatEmpty`.
+                _ => Self {
+                    title: String::new(),
+                    participants_count: None,
+                    already_joined: true,
+                    chat: None,
+                },
+            },
+            #[allow(unreachable_patterns)]
+            _ => Self {
+                title: String::new(),
+                participants_count: None,
+                already_joined: false,
+                chat: None,
+            },
+        }
+    }
+}
+
+/// Extracts the hash out of an invite link, such as `https://t.me/+hash`
+/// or `t.me/joinchat/hash`. Anything else is assumed to be the hash itself.
+pub(crate) fn parse_invite_hash(link: &str) -> &str {
+    let link = link.trim();
+    let path = link
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+
+    for prefix in ["t.me/", "telegram.me/", "telegram.dog/"].iter() {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            let rest = rest.trim_end_matches('/');
+            if let Some(hash) = rest.strip_prefix('+') {
+                return hash;
+            }
+            if let Some(hash) = rest.strip_prefix("joinchat/") {
+                return hash;
+            }
+        }
+    }
+    if let Some(hash) = link.strip_prefix("tg://join?invite=") {
+        return hash;
+    }
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_invite_hash() {
+        assert_eq!(parse_invite_hash("AbCd_123"), "AbCd_123");
+        assert_eq!(parse_invite_hash("https://t.me/+AbCd_123"), "AbCd_123");
+        assert_eq!(parse_invite_hash("t.me/joinchat/AbCd_123"), "AbCd_123");
+        assert_eq!(
+            parse_invite_hash("https://telegram.me/joinchat/AbCd_123/"),
+            "AbCd_123"
+        );
+        assert_eq!(parse_invite_hash("tg://join?invite=AbCd_123"), "AbCd_123");
+    }
+}
@@ -7,3 +7,14 @@ pub struct Dialog {
     pub entity: types::Entity,
     pub last_message: Option<tl::enums::Message>,
 }
+
+impl Dialog {
+    /// Returns the last message sent to the dialog, if any, which can be
+    /// used to show a preview of the conversation.
+    ///
+    /// The message is part of the same response the dialog came in, so no
+    /// additional request is made.
+    pub fn last_message(&self) -> Option<&tl::enums::Message> {
+        self.last_message.as_ref()
+    }
+}
@@ -1,5 +1,7 @@
 use grammers_tl_types as tl;
 
+use super::{PackedChat, PackedType, Permissions};
+
 pub enum Entity {
     User(tl::types::User),
     Chat(tl::types::Chat),
@@ -7,6 +9,8 @@ pub enum Entity {
 }
 
 impl Entity {
+    /// Returns the identifier of the entity. Note that users, chats and
+    /// channels may share the same identifier.
     pub fn id(&self) -> i32 {
         match self {
             Self::User(user) => user.id,
@@ -15,33 +19,238 @@ impl Entity {
         }
     }
 
+    /// Returns the access hash needed to use this entity as input, if
+    /// known. Small group chats don't need one, so they never have it.
+    pub fn access_hash(&self) -> Option<i64> {
+        match self {
+            Self::User(user) => user.access_hash,
+            Self::Chat(_) => None,
+            Self::Channel(channel) => channel.access_hash,
+        }
+    }
+
+    /// Returns what the members of the group or channel are allowed to do
+    /// by default, or `None` for users or if it's not known.
+    pub fn permissions(&self) -> Option<Permissions> {
+        let rights = match self {
+            Self::User(_) => None,
+            Self::Chat(chat) => chat.default_banned_rights.as_ref(),
+            Self::Channel(channel) => channel.default_banned_rights.as_ref(),
+        };
+        rights.map(Permissions::from)
+    }
+
+    /// Packs the entity into a [`PackedChat`], which can be stored and used
+    /// as input later on.
+    ///
+    /// [`PackedChat`]: struct.PackedChat.html
+    pub fn pack(&self) -> PackedChat {
+        PackedChat {
+            ty: match self {
+                Self::User(_) => PackedType::User,
+                Self::Chat(_) => PackedType::Chat,
+                Self::Channel(_) => PackedType::Channel,
+            },
+            id: self.id(),
+            access_hash: self.access_hash(),
+        }
+    }
+
     pub fn to_input_peer(&self) -> tl::enums::InputPeer {
+        let access_hash = self.access_hash().unwrap_or(0);
         match self {
             Self::User(user) => tl::types::InputPeerUser {
                 user_id: user.id,
-                access_hash: user.access_hash.unwrap_or(0),
+                access_hash,
             }
             .into(),
             Self::Chat(chat) => tl::types::InputPeerChat { chat_id: chat.id }.into(),
             Self::Channel(channel) => tl::types::InputPeerChannel {
                 channel_id: channel.id,
-                access_hash: channel.access_hash.unwrap_or(0),
+                access_hash,
             }
             .into(),
         }
     }
 
+    /// Returns a human-readable name for the entity, which is never empty.
+    ///
+    /// Users are displayed by their full name, or their username if they
+    /// have no name, or their ID as a last resort. Deleted accounts are
+    /// displayed as "Deleted Account". Chats and channels use their title.
     pub fn display(&self) -> String {
         match self {
             Self::User(user) => {
-                if let Some(name) = &user.first_name {
-                    name.clone()
+                if user.deleted {
+                    return "Deleted Account".to_string();
+                }
+
+                let name = match (&user.first_name, &user.last_name) {
+                    (Some(first), Some(last)) => format!("{} {}", first, last),
+                    (Some(name), None) | (None, Some(name)) => name.clone(),
+                    (None, None) => String::new(),
+                };
+                let name = name.trim();
+
+                if !name.is_empty() {
+                    name.to_string()
+                } else if let Some(username) = &user.username {
+                    format!("@{}", username)
                 } else {
-                    "Deleted Account".to_string()
+                    user.id.to_string()
                 }
             }
-            Self::Chat(chat) => chat.title.clone(),
-            Self::Channel(channel) => channel.title.clone(),
+            Self::Chat(chat) => display_title(&chat.title, chat.id),
+            Self::Channel(channel) => display_title(&channel.title, channel.id),
+        }
+    }
+}
+
+fn display_title(title: &str, id: i32) -> String {
+    if title.trim().is_empty() {
+        id.to_string()
+    } else {
+        title.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_user() -> tl::types::User {
+        tl::types::User {
+            is_self: false,
+            contact: false,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            id: 123,
+            access_hash: None,
+            first_name: None,
+            last_name: None,
+            username: None,
+            phone: None,
+            photo: None,
+            status: None,
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: None,
         }
     }
+
+    #[test]
+    fn check_access_hash() {
+        assert_eq!(Entity::User(empty_user()).access_hash(), None);
+
+        let mut user = empty_user();
+        user.access_hash = Some(42);
+        let entity = Entity::User(user);
+        assert_eq!(entity.id(), 123);
+        assert_eq!(entity.access_hash(), Some(42));
+    }
+
+    #[test]
+    fn check_user_display_name() {
+        let mut user = empty_user();
+        user.first_name = Some("Alice".into());
+        assert_eq!(Entity::User(user).display(), "Alice");
+
+        let mut user = empty_user();
+        user.first_name = Some("Alice".into());
+        user.last_name = Some("Smith".into());
+        assert_eq!(Entity::User(user).display(), "Alice Smith");
+
+        let mut user = empty_user();
+        user.last_name = Some("Smith".into());
+        assert_eq!(Entity::User(user).display(), "Smith");
+    }
+
+    #[test]
+    fn check_user_display_username() {
+        let mut user = empty_user();
+        user.username = Some("alice".into());
+        assert_eq!(Entity::User(user).display(), "@alice");
+
+        let mut user = empty_user();
+        user.first_name = Some(" ".into());
+        user.username = Some("alice".into());
+        assert_eq!(Entity::User(user).display(), "@alice");
+    }
+
+    #[test]
+    fn check_user_display_id() {
+        assert_eq!(Entity::User(empty_user()).display(), "123");
+    }
+
+    #[test]
+    fn check_user_display_deleted() {
+        let mut user = empty_user();
+        user.deleted = true;
+        assert_eq!(Entity::User(user).display(), "Deleted Account");
+    }
+
+    #[test]
+    fn check_channel_display() {
+        let channel = |title: &str| tl::types::Channel {
+            creator: false,
+            left: false,
+            broadcast: true,
+            verified: false,
+            megagroup: false,
+            restricted: false,
+            signatures: false,
+            min: false,
+            scam: false,
+            has_link: false,
+            has_geo: false,
+            slowmode_enabled: false,
+            id: 456,
+            access_hash: None,
+            title: title.into(),
+            username: None,
+            photo: tl::types::ChatPhotoEmpty {}.into(),
+            date: 0,
+            version: 0,
+            restriction_reason: None,
+            admin_rights: None,
+            banned_rights: None,
+            default_banned_rights: None,
+            participants_count: None,
+        };
+
+        assert_eq!(Entity::Channel(channel("News")).display(), "News");
+        assert_eq!(Entity::Channel(channel("")).display(), "456");
+    }
+
+    #[test]
+    fn check_chat_display() {
+        let chat = |title: &str| tl::types::Chat {
+            creator: false,
+            kicked: false,
+            left: false,
+            deactivated: false,
+            id: 789,
+            title: title.into(),
+            photo: tl::types::ChatPhotoEmpty {}.into(),
+            participants_count: 0,
+            date: 0,
+            version: 0,
+            migrated_to: None,
+            admin_rights: None,
+            default_banned_rights: None,
+        };
+
+        assert_eq!(Entity::Chat(chat("Friends")).display(), "Friends");
+        assert_eq!(Entity::Chat(chat("")).display(), "789");
+    }
 }
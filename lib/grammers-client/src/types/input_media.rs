@@ -0,0 +1,40 @@
+use grammers_tl_types as tl;
+
+/// Media that can be sent to a chat with [`Client::send_input_media`].
+///
+/// [`Client::send_input_media`]: ../struct.Client.html#method.send_input_media
+pub struct InputMedia {
+    pub(crate) media: tl::enums::InputMedia,
+}
+
+impl InputMedia {
+    /// Creates a photo that Telegram will download from the given URL,
+    /// without having to upload it first.
+    pub fn photo_url<S: Into<String>>(url: S) -> Self {
+        Self {
+            media: tl::types::InputMediaPhotoExternal {
+                url: url.into(),
+                ttl_seconds: None,
+            }
+            .into(),
+        }
+    }
+
+    /// Creates a document that Telegram will download from the given URL,
+    /// without having to upload it first.
+    pub fn document_url<S: Into<String>>(url: S) -> Self {
+        Self {
+            media: tl::types::InputMediaDocumentExternal {
+                url: url.into(),
+                ttl_seconds: None,
+            }
+            .into(),
+        }
+    }
+}
+
+impl From<tl::enums::InputMedia> for InputMedia {
+    fn from(media: tl::enums::InputMedia) -> Self {
+        Self { media }
+    }
+}
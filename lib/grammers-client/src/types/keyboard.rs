@@ -0,0 +1,304 @@
+use grammers_tl_types as tl;
+use std::io;
+
+enum KeyboardKind {
+    Show,
+    Remove,
+    ForceReply,
+}
+
+/// A builder for the custom reply keyboard shown to users in place of
+/// their normal keyboard. Only bots can send these.
+///
+/// Besides showing buttons, the keyboard can also be used to [`remove`] a
+/// previously shown keyboard, or to [`force_reply`] to the message.
+///
+/// [`remove`]: #method.remove
+/// [`force_reply`]: #method.force_reply
+pub struct ReplyKeyboard {
+    kind: KeyboardKind,
+    rows: Vec<Vec<String>>,
+    resize: bool,
+    single_use: bool,
+    selective: bool,
+}
+
+impl ReplyKeyboard {
+    /// Creates a new, empty keyboard. Buttons are added with [`row`].
+    ///
+    /// [`row`]: #method.row
+    pub fn new() -> Self {
+        Self::with_kind(KeyboardKind::Show)
+    }
+
+    /// Creates a markup that hides the keyboard currently shown, if any.
+    pub fn remove() -> Self {
+        Self::with_kind(KeyboardKind::Remove)
+    }
+
+    /// Creates a markup that makes the user's client show the reply
+    /// interface, as if they had selected the message and tapped "Reply".
+    pub fn force_reply() -> Self {
+        Self::with_kind(KeyboardKind::ForceReply)
+    }
+
+    fn with_kind(kind: KeyboardKind) -> Self {
+        Self {
+            kind,
+            rows: Vec::new(),
+            resize: false,
+            single_use: false,
+            selective: false,
+        }
+    }
+
+    /// Adds a new row of text buttons to the keyboard.
+    pub fn row<I: IntoIterator<Item = S>, S: Into<String>>(mut self, buttons: I) -> Self {
+        self.rows
+            .push(buttons.into_iter().map(|text| text.into()).collect());
+        self
+    }
+
+    /// Whether the keyboard should be resized vertically to fit its buttons.
+    pub fn resize(mut self, resize: bool) -> Self {
+        self.resize = resize;
+        self
+    }
+
+    /// Whether the keyboard should be hidden as soon as it's been used.
+    pub fn single_use(mut self, single_use: bool) -> Self {
+        self.single_use = single_use;
+        self
+    }
+
+    /// Whether the markup should only apply to the users mentioned in the
+    /// message, or to the sender of the message being replied to.
+    pub fn selective(mut self, selective: bool) -> Self {
+        self.selective = selective;
+        self
+    }
+
+    /// Builds the raw markup, failing if the keyboard is not valid.
+    pub(crate) fn to_reply_markup(&self) -> io::Result<tl::enums::ReplyMarkup> {
+        let has_buttons = self.rows.iter().any(|row| !row.is_empty());
+        match self.kind {
+            KeyboardKind::Show if !has_buttons => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a reply keyboard must have at least one button",
+            )),
+            KeyboardKind::Show => Ok(tl::types::ReplyKeyboardMarkup {
+                resize: self.resize,
+                single_use: self.single_use,
+                selective: self.selective,
+                rows: self
+                    .rows
+                    .iter()
+                    .filter(|row| !row.is_empty())
+                    .map(|row| {
+                        tl::types::KeyboardButtonRow {
+                            buttons: row
+                                .iter()
+                                .map(|text| tl::types::KeyboardButton { text: text.clone() }.into())
+                                .collect(),
+                        }
+                        .into()
+                    })
+                    .collect(),
+            }
+            .into()),
+            KeyboardKind::Remove | KeyboardKind::ForceReply if has_buttons => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a markup to remove the keyboard or force a reply cannot have buttons",
+            )),
+            KeyboardKind::Remove => Ok(tl::types::ReplyKeyboardHide {
+                selective: self.selective,
+            }
+            .into()),
+            KeyboardKind::ForceReply => Ok(tl::types::ReplyKeyboardForceReply {
+                single_use: self.single_use,
+                selective: self.selective,
+            }
+            .into()),
+        }
+    }
+}
+
+impl Default for ReplyKeyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A button in the keyboard or inline markup of a received message,
+/// classified by what most applications care about.
+pub enum Button<'a> {
+    /// A button that sends its text as a message when pressed.
+    Text(&'a str),
+    /// A button that sends the `data` back to the bot when pressed.
+    Callback { text: &'a str, data: &'a [u8] },
+    /// A button that opens the `url` when pressed.
+    Url { text: &'a str, url: &'a str },
+    /// A button that starts an inline query to the bot with the `query`,
+    /// either in the same chat or in a chat chosen by the user.
+    SwitchInline {
+        text: &'a str,
+        query: &'a str,
+        same_peer: bool,
+    },
+    /// Any other button that does not have a more convenient variant yet.
+    Raw(&'a tl::enums::KeyboardButton),
+}
+
+impl<'a> Button<'a> {
+    /// Returns the text shown on the button.
+    pub fn text(&self) -> &'a str {
+        use tl::enums::KeyboardButton as B;
+
+        match self {
+            Self::Text(text)
+            | Self::Callback { text, .. }
+            | Self::Url { text, .. }
+            | Self::SwitchInline { text, .. } => text,
+            Self::Raw(button) => match button {
+                B::InputKeyboardButtonUrlAuth(b) => &b.text,
+                B::KeyboardButton(b) => &b.text,
+                B::KeyboardButtonBuy(b) => &b.text,
+                B::KeyboardButtonCallback(b) => &b.text,
+                B::KeyboardButtonGame(b) => &b.text,
+                B::KeyboardButtonRequestGeoLocation(b) => &b.text,
+                B::KeyboardButtonRequestPhone(b) => &b.text,
+                B::KeyboardButtonRequestPoll(b) => &b.text,
+                B::KeyboardButtonSwitchInline(b) => &b.text,
+                B::KeyboardButtonUrl(b) => &b.text,
+                B::KeyboardButtonUrlAuth(b) => &b.text,
+                #[allow(unreachable_patterns)]
+                _ => "",
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a tl::enums::KeyboardButton> for Button<'a> {
+    fn from(button: &'a tl::enums::KeyboardButton) -> Self {
+        use tl::enums::KeyboardButton as B;
+
+        match button {
+            B::KeyboardButton(b) => Self::Text(&b.text),
+            B::KeyboardButtonCallback(b) => Self::Callback {
+                text: &b.text,
+                data: &b.data,
+            },
+            B::KeyboardButtonUrl(b) => Self::Url {
+                text: &b.text,
+                url: &b.url,
+            },
+            B::KeyboardButtonSwitchInline(b) => Self::SwitchInline {
+                text: &b.text,
+                query: &b.query,
+                same_peer: b.same_peer,
+            },
+            button => Self::Raw(button),
+        }
+    }
+}
+
+/// Returns the rows of buttons in the markup, which are empty if the markup
+/// has no buttons (for example, because it removes the keyboard).
+pub(crate) fn markup_buttons(markup: &tl::enums::ReplyMarkup) -> Vec<Vec<Button<'_>>> {
+    let rows = match markup {
+        tl::enums::ReplyMarkup::ReplyInlineMarkup(markup) => &markup.rows,
+        tl::enums::ReplyMarkup::ReplyKeyboardMarkup(markup) => &markup.rows,
+        tl::enums::ReplyMarkup::ReplyKeyboardHide(_)
+        | tl::enums::ReplyMarkup::ReplyKeyboardForceReply(_) => return Vec::new(),
+        #[allow(unreachable_patterns)]
+        _ => return Vec::new(),
+    };
+
+    rows.iter()
+        .map(|row| match row {
+            tl::enums::KeyboardButtonRow::KeyboardButtonRow(row) => {
+                row.buttons.iter().map(Button::from).collect()
+            }
+            #[allow(unreachable_patterns)]
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_remove_and_force_reply() {
+        match ReplyKeyboard::remove().selective(true).to_reply_markup() {
+            Ok(tl::enums::ReplyMarkup::ReplyKeyboardHide(hide)) => assert!(hide.selective),
+            _ => panic!("unexpected markup"),
+        }
+        match ReplyKeyboard::force_reply().to_reply_markup() {
+            Ok(tl::enums::ReplyMarkup::ReplyKeyboardForceReply(_)) => {}
+            _ => panic!("unexpected markup"),
+        }
+    }
+
+    #[test]
+    fn check_buttons_validation() {
+        assert!(ReplyKeyboard::new().to_reply_markup().is_err());
+        assert!(ReplyKeyboard::new()
+            .row(Vec::<String>::new())
+            .to_reply_markup()
+            .is_err());
+        assert!(ReplyKeyboard::remove()
+            .row(vec!["a"])
+            .to_reply_markup()
+            .is_err());
+        assert!(ReplyKeyboard::force_reply()
+            .row(vec!["a"])
+            .to_reply_markup()
+            .is_err());
+
+        match ReplyKeyboard::new()
+            .row(vec!["a", "b"])
+            .row(vec!["c"])
+            .to_reply_markup()
+        {
+            Ok(tl::enums::ReplyMarkup::ReplyKeyboardMarkup(markup)) => {
+                assert_eq!(markup.rows.len(), 2)
+            }
+            _ => panic!("unexpected markup"),
+        }
+    }
+
+    #[test]
+    fn check_markup_buttons() {
+        let markup = tl::types::ReplyInlineMarkup {
+            rows: vec![tl::types::KeyboardButtonRow {
+                buttons: vec![
+                    tl::types::KeyboardButtonCallback {
+                        text: "a".into(),
+                        data: vec![1, 2],
+                    }
+                    .into(),
+                    tl::types::KeyboardButtonGame { text: "b".into() }.into(),
+                ],
+            }
+            .into()],
+        }
+        .into();
+
+        let rows = markup_buttons(&markup);
+        assert_eq!(rows.len(), 1);
+        match rows[0][0] {
+            Button::Callback { text, data } => {
+                assert_eq!(text, "a");
+                assert_eq!(data, &[1, 2]);
+            }
+            _ => panic!("unexpected button"),
+        }
+        assert!(matches!(rows[0][1], Button::Raw(_)));
+        assert_eq!(rows[0][1].text(), "b");
+
+        let hide = ReplyKeyboard::remove().to_reply_markup().unwrap();
+        assert!(markup_buttons(&hide).is_empty());
+    }
+}
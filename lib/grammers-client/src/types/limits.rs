@@ -0,0 +1,56 @@
+use grammers_tl_types as tl;
+
+/// The limits Telegram advertises in its configuration, such as the maximum
+/// length of messages or the size of groups.
+///
+/// Until the configuration is fetched, sensible defaults are used instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    /// The maximum amount of members in a small group chat.
+    pub chat_size_max: i32,
+    /// The maximum amount of members in a megagroup.
+    pub megagroup_size_max: i32,
+    /// The maximum amount of messages that can be forwarded at once.
+    pub forwarded_count_max: i32,
+    /// The maximum length, in UTF-16 code units, of a text message.
+    pub message_length_max: i32,
+    /// The maximum length, in UTF-16 code units, of a media caption.
+    pub caption_length_max: i32,
+    /// The maximum amount of pinned dialogs.
+    pub pinned_dialogs_count_max: i32,
+    /// How many seconds after being sent a message can still be edited.
+    pub edit_time_limit: i32,
+    /// How many seconds after being sent a message can still be deleted
+    /// for everyone.
+    pub revoke_time_limit: i32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            chat_size_max: 200,
+            megagroup_size_max: 200_000,
+            forwarded_count_max: 100,
+            message_length_max: 4096,
+            caption_length_max: 1024,
+            pinned_dialogs_count_max: 5,
+            edit_time_limit: 48 * 60 * 60,
+            revoke_time_limit: 48 * 60 * 60,
+        }
+    }
+}
+
+impl From<&tl::types::Config> for Limits {
+    fn from(config: &tl::types::Config) -> Self {
+        Self {
+            chat_size_max: config.chat_size_max,
+            megagroup_size_max: config.megagroup_size_max,
+            forwarded_count_max: config.forwarded_count_max,
+            message_length_max: config.message_length_max,
+            caption_length_max: config.caption_length_max,
+            pinned_dialogs_count_max: config.pinned_dialogs_count_max,
+            edit_time_limit: config.edit_time_limit,
+            revoke_time_limit: config.revoke_time_limit,
+        }
+    }
+}
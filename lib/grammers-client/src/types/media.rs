@@ -0,0 +1,238 @@
+use grammers_tl_types as tl;
+
+/// The media attached to a message, classified by what most applications
+/// care about.
+///
+/// Media that does not fit any of the variants (such as games or invoices)
+/// is not represented.
+pub enum Media<'a> {
+    /// A photo, available in several sizes.
+    Photo(&'a tl::types::Photo),
+    /// Any kind of file, including videos, voice notes and stickers.
+    Document {
+        document: &'a tl::types::Document,
+        mime_type: &'a str,
+        /// The original name of the file, if it had one.
+        file_name: Option<&'a str>,
+    },
+    /// A geographical location, which may also be a venue or a location
+    /// that's being shared live.
+    Geo { latitude: f64, longitude: f64 },
+    /// A shared contact. The user ID is `0` if the contact has no account.
+    Contact {
+        phone_number: &'a str,
+        first_name: &'a str,
+        last_name: &'a str,
+        user_id: i32,
+    },
+    /// A poll, along with its results so far.
+    Poll {
+        poll: &'a tl::enums::Poll,
+        results: &'a tl::enums::PollResults,
+    },
+    /// The preview of a link contained in the message.
+    WebPage(&'a tl::types::WebPage),
+}
+
+impl<'a> Media<'a> {
+    /// Classifies the raw media, returning `None` if it's empty or of an
+    /// unsupported kind.
+    pub(crate) fn from_raw(media: &'a tl::enums::MessageMedia) -> Option<Self> {
+        use tl::enums::MessageMedia as M;
+
+        match media {
+            M::MessageMediaPhoto(media) => match &media.photo {
+                Some(tl::enums::Photo::Photo(photo)) => Some(Self::Photo(photo)),
+                _ => None,
+            },
+            M::MessageMediaDocument(media) => match &media.document {
+                Some(tl::enums::Document::Document(document)) => Some(Self::Document {
+                    document,
+                    mime_type: &document.mime_type,
+                    file_name: document.attributes.iter().find_map(|attr| match attr {
+                        tl::enums::DocumentAttribute::DocumentAttributeFilename(attr) => {
+                            Some(attr.file_name.as_str())
+                        }
+                        _ => None,
+                    }),
+                }),
+                _ => None,
+            },
+            M::MessageMediaGeo(tl::types::MessageMediaGeo { geo })
+            | M::MessageMediaGeoLive(tl::types::MessageMediaGeoLive { geo, .. })
+            | M::MessageMediaVenue(tl::types::MessageMediaVenue { geo, .. }) => match geo {
+                tl::enums::GeoPoint::GeoPoint(point) => Some(Self::Geo {
+                    latitude: point.lat,
+                    longitude: point.long,
+                }),
+                // Includes `GeoPointEmpty`.
+                _ => None,
+            },
+            M::MessageMediaContact(contact) => Some(Self::Contact {
+                phone_number: &contact.phone_number,
+                first_name: &contact.first_name,
+                last_name: &contact.last_name,
+                user_id: contact.user_id,
+            }),
+            M::MessageMediaPoll(media) => Some(Self::Poll {
+                poll: &media.poll,
+                results: &media.results,
+            }),
+            M::MessageMediaWebPage(media) => match &media.webpage {
+                tl::enums::WebPage::WebPage(page) => Some(Self::WebPage(page)),
+                _ => None,
+            },
+            // Empty, games, invoices and unsupported media.
+            _ => None,
+        }
+    }
+}
+
+/// Returns the location from where the given media can be downloaded, along
+/// with its size, or `None` if it contains no file.
+///
+/// For photos, the largest size is used. Link previews use their document,
+/// if they have one, or their photo otherwise.
+pub(crate) fn media_location(
+    media: &tl::enums::MessageMedia,
+) -> Option<(tl::enums::InputFileLocation, u64)> {
+    match media {
+        tl::enums::MessageMedia::MessageMediaPhoto(tl::types::MessageMediaPhoto {
+            photo: Some(tl::enums::Photo::Photo(photo)),
+            ..
+        }) => photo_location(photo),
+        tl::enums::MessageMedia::MessageMediaDocument(tl::types::MessageMediaDocument {
+            document: Some(tl::enums::Document::Document(document)),
+            ..
+        }) => Some(document_location(document)),
+        tl::enums::MessageMedia::MessageMediaWebPage(tl::types::MessageMediaWebPage {
+            webpage: tl::enums::WebPage::WebPage(page),
+        }) => match (&page.document, &page.photo) {
+            (Some(tl::enums::Document::Document(document)), _) => Some(document_location(document)),
+            (_, Some(tl::enums::Photo::Photo(photo))) => photo_location(photo),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the location of the document, along with its size in bytes.
+fn document_location(document: &tl::types::Document) -> (tl::enums::InputFileLocation, u64) {
+    (
+        tl::types::InputDocumentFileLocation {
+            id: document.id,
+            access_hash: document.access_hash,
+            file_reference: document.file_reference.clone(),
+            thumb_size: String::new(),
+        }
+        .into(),
+        document.size as u64,
+    )
+}
+
+/// Returns the location of the largest size of the photo, along with its
+/// size in bytes.
+pub(crate) fn photo_location(
+    photo: &tl::types::Photo,
+) -> Option<(tl::enums::InputFileLocation, u64)> {
+    photo
+        .sizes
+        .iter()
+        .filter_map(|size| match size {
+            tl::enums::PhotoSize::PhotoSize(size) => Some(size),
+            _ => None,
+        })
+        .max_by_key(|size| size.size)
+        .map(|size| {
+            (
+                tl::types::InputPhotoFileLocation {
+                    id: photo.id,
+                    access_hash: photo.access_hash,
+                    file_reference: photo.file_reference.clone(),
+                    thumb_size: size.r#type.clone(),
+                }
+                .into(),
+                size.size as u64,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_document_file_name() {
+        let media: tl::enums::MessageMedia = tl::types::MessageMediaDocument {
+            document: Some(
+                tl::types::Document {
+                    id: 1,
+                    access_hash: 2,
+                    file_reference: Vec::new(),
+                    date: 0,
+                    mime_type: "text/plain".into(),
+                    size: 4,
+                    thumbs: None,
+                    dc_id: 2,
+                    attributes: vec![tl::types::DocumentAttributeFilename {
+                        file_name: "notes.txt".into(),
+                    }
+                    .into()],
+                }
+                .into(),
+            ),
+            ttl_seconds: None,
+        }
+        .into();
+
+        match Media::from_raw(&media) {
+            Some(Media::Document {
+                mime_type,
+                file_name,
+                ..
+            }) => {
+                assert_eq!(mime_type, "text/plain");
+                assert_eq!(file_name, Some("notes.txt"));
+            }
+            _ => panic!("unexpected media"),
+        }
+    }
+
+    #[test]
+    fn check_empty_media() {
+        let media: tl::enums::MessageMedia = tl::types::MessageMediaEmpty {}.into();
+        assert!(Media::from_raw(&media).is_none());
+    }
+
+    #[test]
+    fn check_media_location() {
+        let media: tl::enums::MessageMedia = tl::types::MessageMediaDocument {
+            document: Some(
+                tl::types::Document {
+                    id: 1,
+                    access_hash: 2,
+                    file_reference: vec![3],
+                    date: 0,
+                    mime_type: "text/plain".into(),
+                    size: 4,
+                    thumbs: None,
+                    dc_id: 2,
+                    attributes: Vec::new(),
+                }
+                .into(),
+            ),
+            ttl_seconds: None,
+        }
+        .into();
+        match media_location(&media) {
+            Some((tl::enums::InputFileLocation::InputDocumentFileLocation(location), 4)) => {
+                assert_eq!((location.id, location.access_hash), (1, 2));
+                assert_eq!(location.file_reference, vec![3]);
+            }
+            _ => panic!("unexpected location"),
+        }
+
+        let media: tl::enums::MessageMedia = tl::types::MessageMediaEmpty {}.into();
+        assert!(media_location(&media).is_none());
+    }
+}
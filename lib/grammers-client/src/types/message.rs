@@ -0,0 +1,301 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use grammers_tl_types as tl;
+
+use super::{markup_buttons, media_location, Button, Media, PackedChat};
+
+/// Returns the bare ID of the user, group or channel the peer refers to.
+pub(crate) fn peer_id(peer: &tl::enums::Peer) -> i32 {
+    match peer {
+        tl::enums::Peer::PeerUser(user) => user.user_id,
+        tl::enums::Peer::PeerChat(chat) => chat.chat_id,
+        tl::enums::Peer::PeerChannel(channel) => channel.channel_id,
+        #[allow(unreachable_patterns)]
+        _ => 0,
+    }
+}
+
+/// Converts a Unix timestamp, as sent by Telegram, into a system time.
+pub(crate) fn timestamp(date: i32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(date.max(0) as u64)
+}
+
+/// Returns the ID of the chat a message belongs to. Private messages sent to
+/// us have our own ID as their destination, so the sender is used instead.
+pub(crate) fn chat_id(out: bool, from_id: Option<i32>, to_id: &tl::enums::Peer) -> i32 {
+    match (to_id, from_id) {
+        (tl::enums::Peer::PeerUser(_), Some(from_id)) if !out => from_id,
+        _ => peer_id(to_id),
+    }
+}
+
+/// Either a message on its own, or several messages sent together as an
+/// album. See [`group_messages`].
+///
+/// [`group_messages`]: fn.group_messages.html
+#[allow(clippy::large_enum_variant)]
+pub enum MessageGroup {
+    Single(Message),
+    /// The messages in the album, which share the same `grouped_id`, in
+    /// their original order.
+    Album(Vec<Message>),
+}
+
+/// Groups the consecutive messages that belong to the same album, keeping
+/// their order. Messages of an album that are not next to each other are
+/// not merged, and albums of a single message are returned as `Single`.
+pub fn group_messages(messages: Vec<Message>) -> Vec<MessageGroup> {
+    let mut groups = Vec::new();
+    let mut album: Vec<Message> = Vec::new();
+    for message in messages {
+        if let Some(last) = album.last() {
+            if message.grouped_id().is_some() && message.grouped_id() == last.grouped_id() {
+                album.push(message);
+                continue;
+            }
+            groups.push(finish_group(album));
+            album = Vec::new();
+        }
+
+        if message.grouped_id().is_some() {
+            album.push(message);
+        } else {
+            groups.push(MessageGroup::Single(message));
+        }
+    }
+    if !album.is_empty() {
+        groups.push(finish_group(album));
+    }
+    groups
+}
+
+fn finish_group(mut album: Vec<Message>) -> MessageGroup {
+    if album.len() == 1 {
+        MessageGroup::Single(album.pop().unwrap())
+    } else {
+        MessageGroup::Album(album)
+    }
+}
+
+/// A message sent to a private conversation, a group or a channel.
+///
+/// Service messages (such as "user joined the group") are represented by
+/// [`ServiceMessage`] instead, and deleted messages are not represented.
+///
+/// [`ServiceMessage`]: struct.ServiceMessage.html
+pub struct Message {
+    pub message: tl::types::Message,
+    /// The chat where the message was sent, if the information needed to
+    /// use it as input came along with the message.
+    pub(crate) packed_chat: Option<PackedChat>,
+}
+
+impl Message {
+    /// Returns the message if it's a normal message, or `None` if it's a
+    /// service or empty message.
+    pub(crate) fn from_raw(message: tl::enums::Message) -> Option<Self> {
+        match message {
+            tl::enums::Message::Message(message) => Some(Self {
+                message,
+                packed_chat: None,
+            }),
+            // Includes `MessageService` and `MessageEmpty`.
+            _ => None,
+        }
+    }
+
+    /// Returns the identifier of the message, which is unique within its
+    /// chat (private conversations and small groups share the same ID space).
+    pub fn id(&self) -> i32 {
+        self.message.id
+    }
+
+    /// Returns the text of the message, which may be empty if it only
+    /// contains media.
+    pub fn text(&self) -> &str {
+        &self.message.message
+    }
+
+    /// Returns the time at which the message was sent.
+    pub fn date(&self) -> SystemTime {
+        timestamp(self.message.date)
+    }
+
+    /// Returns the time at which the message was last edited, or `None` if
+    /// it was never edited.
+    pub fn edit_date(&self) -> Option<SystemTime> {
+        self.message
+            .edit_date
+            .filter(|&date| date != 0)
+            .map(timestamp)
+    }
+
+    /// Returns the ID of the user who sent the message, if any. Messages
+    /// posted in broadcast channels have no sender, even if they're signed
+    /// by the administrator who posted them.
+    pub fn sender_id(&self) -> Option<i32> {
+        if self.message.post {
+            None
+        } else {
+            self.message.from_id
+        }
+    }
+
+    /// Returns the chat where the message was sent.
+    pub fn chat(&self) -> &tl::enums::Peer {
+        &self.message.to_id
+    }
+
+    /// Returns the ID of the chat where the message was sent. For private
+    /// conversations, this is always the ID of the other user, regardless
+    /// of who sent the message.
+    ///
+    /// Users, groups and channels have separate ID spaces, so use [`chat`]
+    /// if the kind of chat matters.
+    ///
+    /// [`chat`]: #method.chat
+    pub fn chat_id(&self) -> i32 {
+        chat_id(self.message.out, self.message.from_id, &self.message.to_id)
+    }
+
+    /// Returns `true` if the message was sent by the current account.
+    pub fn outgoing(&self) -> bool {
+        self.message.out
+    }
+
+    /// Returns the media attached to the message, such as a photo or a
+    /// document, if any.
+    ///
+    /// The raw media can be passed to [`Client::download_media`] to
+    /// download its file.
+    ///
+    /// [`Client::download_media`]: ../struct.Client.html#method.download_media
+    pub fn media(&self) -> Option<Media<'_>> {
+        self.message.media.as_ref().and_then(Media::from_raw)
+    }
+
+    /// Returns the location from where the file in the message's media can
+    /// be downloaded, for those who manage their own download loop, or
+    /// `None` if the message has no downloadable file.
+    ///
+    /// Photos (and the photos of link previews) use their largest size.
+    pub fn download_location(&self) -> Option<tl::enums::InputFileLocation> {
+        self.message
+            .media
+            .as_ref()
+            .and_then(media_location)
+            .map(|(location, _)| location)
+    }
+
+    /// Returns the raw keyboard or inline buttons attached to the message,
+    /// if any.
+    pub fn reply_markup(&self) -> Option<&tl::enums::ReplyMarkup> {
+        self.message.reply_markup.as_ref()
+    }
+
+    /// Returns the rows of buttons attached to the message, which are empty
+    /// if it has none.
+    pub fn buttons(&self) -> Vec<Vec<Button<'_>>> {
+        self.reply_markup().map(markup_buttons).unwrap_or_default()
+    }
+
+    /// Returns the identifier shared by all the messages in the same album,
+    /// if this message is part of one.
+    pub fn grouped_id(&self) -> Option<i64> {
+        self.message.grouped_id
+    }
+
+    /// Returns the ID of the message this one is replying to, if any.
+    pub fn reply_to_message_id(&self) -> Option<i32> {
+        self.message.reply_to_msg_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_private_messages_belong_to_the_other_user() {
+        let to_self = tl::types::PeerUser { user_id: 1 }.into();
+        assert_eq!(chat_id(false, Some(2), &to_self), 2);
+
+        let to_other = tl::types::PeerUser { user_id: 2 }.into();
+        assert_eq!(chat_id(true, Some(1), &to_other), 2);
+
+        let to_chat = tl::types::PeerChat { chat_id: 3 }.into();
+        assert_eq!(chat_id(false, Some(2), &to_chat), 3);
+    }
+
+    fn message(id: i32, grouped_id: Option<i64>) -> Message {
+        Message {
+            message: tl::types::Message {
+                out: false,
+                mentioned: false,
+                media_unread: false,
+                silent: false,
+                post: false,
+                from_scheduled: false,
+                legacy: false,
+                edit_hide: false,
+                id,
+                from_id: None,
+                to_id: tl::types::PeerChat { chat_id: 1 }.into(),
+                fwd_from: None,
+                via_bot_id: None,
+                reply_to_msg_id: None,
+                date: 0,
+                message: String::new(),
+                media: None,
+                reply_markup: None,
+                entities: None,
+                views: None,
+                edit_date: None,
+                post_author: None,
+                grouped_id,
+                restriction_reason: None,
+            },
+            packed_chat: None,
+        }
+    }
+
+    #[test]
+    fn check_message_dates() {
+        let mut message = message(1, None);
+        message.message.date = 60;
+        assert_eq!(message.date(), UNIX_EPOCH + Duration::from_secs(60));
+        assert_eq!(message.edit_date(), None);
+
+        message.message.edit_date = Some(0);
+        assert_eq!(message.edit_date(), None);
+
+        message.message.edit_date = Some(120);
+        assert_eq!(
+            message.edit_date(),
+            Some(UNIX_EPOCH + Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn check_group_messages() {
+        let groups = group_messages(vec![
+            message(1, None),
+            message(2, Some(10)),
+            message(3, Some(10)),
+            message(4, Some(20)),
+            message(5, Some(10)),
+            message(6, Some(10)),
+            message(7, None),
+        ]);
+
+        let ids = groups
+            .iter()
+            .map(|group| match group {
+                MessageGroup::Single(message) => vec![message.id()],
+                MessageGroup::Album(messages) => messages.iter().map(Message::id).collect(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![vec![1], vec![2, 3], vec![4], vec![5, 6], vec![7]]);
+        assert!(matches!(groups[2], MessageGroup::Single(_)));
+    }
+}
@@ -1,5 +1,42 @@
+mod admin_log_event;
+mod chat_invite;
 mod dialog;
 mod entity;
+mod input_media;
+mod keyboard;
+mod limits;
+mod media;
+mod message;
+mod notify_settings;
+mod packed_chat;
+mod password_info;
+mod permissions;
+mod privacy;
+mod report_reason;
+mod service_message;
+mod update;
+mod user_status;
 
+pub use admin_log_event::{AdminLogEntry, AdminLogEvent};
+pub(crate) use chat_invite::parse_invite_hash;
+pub use chat_invite::ChatInvitePreview;
 pub use dialog::Dialog;
 pub use entity::Entity;
+pub use input_media::InputMedia;
+pub(crate) use keyboard::markup_buttons;
+pub use keyboard::{Button, ReplyKeyboard};
+pub use limits::Limits;
+pub use media::Media;
+pub(crate) use media::{media_location, photo_location};
+pub(crate) use message::{chat_id, peer_id, timestamp};
+pub use message::{group_messages, Message, MessageGroup};
+pub use notify_settings::{NotifyPeerKind, NotifySettings};
+pub use packed_chat::{PackedChat, PackedType};
+pub use password_info::PasswordInfo;
+pub use permissions::Permissions;
+pub use privacy::{PrivacyKey, PrivacyRule};
+pub use report_reason::ReportReason;
+pub use service_message::{ServiceAction, ServiceMessage};
+pub(crate) use update::PackedChats;
+pub use update::Update;
+pub use user_status::UserStatus;
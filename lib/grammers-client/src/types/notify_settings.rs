@@ -0,0 +1,73 @@
+use grammers_tl_types as tl;
+
+/// The kind of peers to which global notification settings apply.
+pub enum NotifyPeerKind {
+    /// Private conversations with users.
+    Users,
+    /// Small group chats and megagroups.
+    Chats,
+    /// Broadcast channels.
+    Broadcasts,
+}
+
+impl NotifyPeerKind {
+    pub(crate) fn to_input_notify_peer(&self) -> tl::enums::InputNotifyPeer {
+        match self {
+            Self::Users => tl::types::InputNotifyUsers {}.into(),
+            Self::Chats => tl::types::InputNotifyChats {}.into(),
+            Self::Broadcasts => tl::types::InputNotifyBroadcasts {}.into(),
+        }
+    }
+}
+
+/// A builder for notification settings.
+///
+/// Settings that are not explicitly set are left unchanged.
+#[derive(Default)]
+pub struct NotifySettings {
+    show_previews: Option<bool>,
+    silent: Option<bool>,
+    mute_until: Option<i32>,
+    sound: Option<String>,
+}
+
+impl NotifySettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the message text should be shown in the notifications.
+    pub fn show_previews(mut self, show_previews: bool) -> Self {
+        self.show_previews = Some(show_previews);
+        self
+    }
+
+    /// Whether notifications should be delivered without sound.
+    pub fn silent(mut self, silent: bool) -> Self {
+        self.silent = Some(silent);
+        self
+    }
+
+    /// Mutes notifications until the given Unix timestamp. Use `0` to unmute
+    /// and `i32::MAX` to mute forever.
+    pub fn mute_until(mut self, date: i32) -> Self {
+        self.mute_until = Some(date);
+        self
+    }
+
+    /// The name of the sound to play for notifications.
+    pub fn sound(mut self, sound: &str) -> Self {
+        self.sound = Some(sound.to_string());
+        self
+    }
+
+    pub(crate) fn to_input_settings(&self) -> tl::enums::InputPeerNotifySettings {
+        tl::types::InputPeerNotifySettings {
+            show_previews: self.show_previews,
+            silent: self.silent,
+            mute_until: self.mute_until,
+            sound: self.sound.clone(),
+        }
+        .into()
+    }
+}
@@ -0,0 +1,171 @@
+use std::io;
+
+use grammers_tl_types as tl;
+
+/// The kind of a [`PackedChat`].
+///
+/// [`PackedChat`]: struct.PackedChat.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PackedType {
+    User,
+    Chat,
+    Channel,
+}
+
+/// A compact representation of a user, group or channel, with just enough
+/// information to use it as input.
+///
+/// It can be stored (for example, in a key-value store or a URL) and used
+/// later anywhere a chat is expected without having to resolve it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PackedChat {
+    pub ty: PackedType,
+    pub id: i32,
+    /// The access hash, which small group chats don't need.
+    pub access_hash: Option<i64>,
+}
+
+/// Set in the first byte of the packed chat when an access hash follows.
+const HAS_ACCESS_HASH: u8 = 0x80;
+
+impl PackedChat {
+    /// Serializes the packed chat into 5 bytes, or 13 if it has an access
+    /// hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut kind = match self.ty {
+            PackedType::User => 1,
+            PackedType::Chat => 2,
+            PackedType::Channel => 3,
+        };
+        if self.access_hash.is_some() {
+            kind |= HAS_ACCESS_HASH;
+        }
+
+        let mut bytes = Vec::with_capacity(13);
+        bytes.push(kind);
+        bytes.extend(&self.id.to_le_bytes());
+        if let Some(access_hash) = self.access_hash {
+            bytes.extend(&access_hash.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserializes a packed chat produced by [`to_bytes`].
+    ///
+    /// [`to_bytes`]: #method.to_bytes
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid packed chat");
+
+        let (&kind, rest) = bytes.split_first().ok_or_else(invalid)?;
+        let ty = match kind & !HAS_ACCESS_HASH {
+            1 => PackedType::User,
+            2 => PackedType::Chat,
+            3 => PackedType::Channel,
+            _ => return Err(invalid()),
+        };
+        let has_access_hash = kind & HAS_ACCESS_HASH != 0;
+        if rest.len() != if has_access_hash { 12 } else { 4 } {
+            return Err(invalid());
+        }
+
+        let mut id = [0; 4];
+        id.copy_from_slice(&rest[..4]);
+        let access_hash = if has_access_hash {
+            let mut access_hash = [0; 8];
+            access_hash.copy_from_slice(&rest[4..]);
+            Some(i64::from_le_bytes(access_hash))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            ty,
+            id: i32::from_le_bytes(id),
+            access_hash,
+        })
+    }
+
+    /// Like [`to_bytes`], but encoded as lowercase hexadecimal text.
+    ///
+    /// [`to_bytes`]: #method.to_bytes
+    pub fn to_hex(&self) -> String {
+        self.to_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Deserializes a packed chat produced by [`to_hex`].
+    ///
+    /// [`to_hex`]: #method.to_hex
+    pub fn from_hex(hex: &str) -> io::Result<Self> {
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid packed chat",
+            ));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Returns the packed chat as input, using an access hash of `0` if it
+    /// is missing.
+    pub fn to_input_peer(&self) -> tl::enums::InputPeer {
+        let access_hash = self.access_hash.unwrap_or(0);
+        match self.ty {
+            PackedType::User => tl::types::InputPeerUser {
+                user_id: self.id,
+                access_hash,
+            }
+            .into(),
+            PackedType::Chat => tl::types::InputPeerChat { chat_id: self.id }.into(),
+            PackedType::Channel => tl::types::InputPeerChannel {
+                channel_id: self.id,
+                access_hash,
+            }
+            .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_packed_chat_roundtrip() {
+        let channel = PackedChat {
+            ty: PackedType::Channel,
+            id: 123,
+            access_hash: Some(-456),
+        };
+        assert_eq!(channel.to_bytes().len(), 13);
+        assert_eq!(
+            PackedChat::from_bytes(&channel.to_bytes()).unwrap(),
+            channel
+        );
+        assert_eq!(PackedChat::from_hex(&channel.to_hex()).unwrap(), channel);
+
+        let chat = PackedChat {
+            ty: PackedType::Chat,
+            id: 789,
+            access_hash: None,
+        };
+        assert_eq!(chat.to_hex(), "0215030000");
+        assert_eq!(PackedChat::from_hex("0215030000").unwrap(), chat);
+    }
+
+    #[test]
+    fn check_invalid_packed_chat() {
+        assert!(PackedChat::from_bytes(&[]).is_err());
+        assert!(PackedChat::from_bytes(&[4, 0, 0, 0, 0]).is_err());
+        assert!(PackedChat::from_bytes(&[0x81, 0, 0, 0, 0]).is_err());
+        assert!(PackedChat::from_hex("021503000").is_err());
+        assert!(PackedChat::from_hex("zz15030000").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use grammers_tl_types as tl;
+
+/// The two-factor authentication settings of the current account.
+pub struct PasswordInfo {
+    /// Whether a password is currently set.
+    pub has_password: bool,
+    /// The hint for the password, if any.
+    pub hint: Option<String>,
+    /// Whether a recovery email has been confirmed.
+    pub has_recovery: bool,
+    /// The pattern of a recovery email that is still pending confirmation,
+    /// such as `a*****@example.com`.
+    pub email_unconfirmed_pattern: Option<String>,
+}
+
+impl From<&tl::types::account::Password> for PasswordInfo {
+    fn from(password: &tl::types::account::Password) -> Self {
+        Self {
+            has_password: password.has_password,
+            hint: password.hint.clone(),
+            has_recovery: password.has_recovery,
+            email_unconfirmed_pattern: password.email_unconfirmed_pattern.clone(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use grammers_tl_types as tl;
+
+/// What the members of a group or channel are allowed to do.
+///
+/// Telegram represents these as "banned rights", where a set flag means the
+/// action is forbidden. Here every field is `true` when the action is
+/// allowed instead, which is what the [`Default`] value has for all of them.
+///
+/// [`Default`]: #impl-Default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    /// Whether the chat can be seen at all. Only meaningful for specific
+    /// users, since it's always allowed for the default permissions.
+    pub can_view_messages: bool,
+    pub can_send_messages: bool,
+    pub can_send_media: bool,
+    pub can_send_stickers: bool,
+    pub can_send_gifs: bool,
+    pub can_send_games: bool,
+    pub can_send_inline: bool,
+    pub can_embed_links: bool,
+    pub can_send_polls: bool,
+    pub can_change_info: bool,
+    pub can_invite_users: bool,
+    pub can_pin_messages: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            can_view_messages: true,
+            can_send_messages: true,
+            can_send_media: true,
+            can_send_stickers: true,
+            can_send_gifs: true,
+            can_send_games: true,
+            can_send_inline: true,
+            can_embed_links: true,
+            can_send_polls: true,
+            can_change_info: true,
+            can_invite_users: true,
+            can_pin_messages: true,
+        }
+    }
+}
+
+impl Permissions {
+    /// Converts the permissions into the banned rights Telegram expects,
+    /// which apply until the given Unix timestamp (`0` meaning forever).
+    pub(crate) fn to_banned_rights(self, until_date: i32) -> tl::enums::ChatBannedRights {
+        tl::types::ChatBannedRights {
+            view_messages: !self.can_view_messages,
+            send_messages: !self.can_send_messages,
+            send_media: !self.can_send_media,
+            send_stickers: !self.can_send_stickers,
+            send_gifs: !self.can_send_gifs,
+            send_games: !self.can_send_games,
+            send_inline: !self.can_send_inline,
+            embed_links: !self.can_embed_links,
+            send_polls: !self.can_send_polls,
+            change_info: !self.can_change_info,
+            invite_users: !self.can_invite_users,
+            pin_messages: !self.can_pin_messages,
+            until_date,
+        }
+        .into()
+    }
+}
+
+impl From<&tl::enums::ChatBannedRights> for Permissions {
+    fn from(rights: &tl::enums::ChatBannedRights) -> Self {
+        let rights = match rights {
+            tl::enums::ChatBannedRights::ChatBannedRights(rights) => rights,
+            #[allow(unreachable_patterns)]
+            _ => return Self::default(),
+        };
+        Self {
+            can_view_messages: !rights.view_messages,
+            can_send_messages: !rights.send_messages,
+            can_send_media: !rights.send_media,
+            can_send_stickers: !rights.send_stickers,
+            can_send_gifs: !rights.send_gifs,
+            can_send_games: !rights.send_games,
+            can_send_inline: !rights.send_inline,
+            can_embed_links: !rights.embed_links,
+            can_send_polls: !rights.send_polls,
+            can_change_info: !rights.change_info,
+            can_invite_users: !rights.invite_users,
+            can_pin_messages: !rights.pin_messages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_permissions_roundtrip() {
+        let permissions = Permissions {
+            can_send_media: false,
+            can_pin_messages: false,
+            ..Permissions::default()
+        };
+
+        let rights = permissions.to_banned_rights(0);
+        assert_eq!(Permissions::from(&rights), permissions);
+
+        let raw = tl::types::ChatBannedRights::from(rights);
+        assert!(raw.send_media && raw.pin_messages);
+        assert!(!raw.send_messages && !raw.view_messages);
+    }
+}
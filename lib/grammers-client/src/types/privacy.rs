@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+
+use grammers_tl_types as tl;
+
+use super::{Entity, PackedChat, PackedType};
+
+/// The piece of information or action whose privacy is configured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivacyKey {
+    /// Who can see the last time the user was online.
+    LastSeen,
+    /// Who can invite the user to groups and channels.
+    ChatInvite,
+    /// Who can call the user.
+    PhoneCall,
+    /// Who can use peer-to-peer connections in calls with the user.
+    PhoneP2P,
+    /// Who can link to the user when forwarding their messages.
+    Forwards,
+    /// Who can see the user's profile photo.
+    ProfilePhoto,
+    /// Who can see the user's phone number.
+    PhoneNumber,
+    /// Who can find the user by their phone number when adding contacts.
+    AddedByPhone,
+}
+
+impl PrivacyKey {
+    pub(crate) fn to_input_privacy_key(self) -> tl::enums::InputPrivacyKey {
+        match self {
+            Self::LastSeen => tl::types::InputPrivacyKeyStatusTimestamp {}.into(),
+            Self::ChatInvite => tl::types::InputPrivacyKeyChatInvite {}.into(),
+            Self::PhoneCall => tl::types::InputPrivacyKeyPhoneCall {}.into(),
+            Self::PhoneP2P => tl::types::InputPrivacyKeyPhoneP2P {}.into(),
+            Self::Forwards => tl::types::InputPrivacyKeyForwards {}.into(),
+            Self::ProfilePhoto => tl::types::InputPrivacyKeyProfilePhoto {}.into(),
+            Self::PhoneNumber => tl::types::InputPrivacyKeyPhoneNumber {}.into(),
+            Self::AddedByPhone => tl::types::InputPrivacyKeyAddedByPhone {}.into(),
+        }
+    }
+}
+
+/// A rule deciding who is allowed or not to see some information. Several
+/// rules are combined, with the more specific ones taking precedence.
+///
+/// Users are kept packed along with their access hash, so the rules that
+/// were fetched can be modified and set again directly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrivacyRule {
+    AllowAll,
+    AllowContacts,
+    AllowUsers(Vec<PackedChat>),
+    /// Allows the participants of the groups with these IDs.
+    AllowChatParticipants(Vec<i32>),
+    DisallowAll,
+    DisallowContacts,
+    DisallowUsers(Vec<PackedChat>),
+    /// Disallows the participants of the groups with these IDs.
+    DisallowChatParticipants(Vec<i32>),
+}
+
+/// Converts the packed users into input users. An error with kind
+/// `InvalidInput` is returned if the access hash of any of them is missing,
+/// since Telegram would not know which user it refers to.
+fn input_users(users: &[PackedChat]) -> io::Result<Vec<tl::enums::InputUser>> {
+    users
+        .iter()
+        .map(|user| match user.access_hash {
+            Some(access_hash) => Ok(tl::types::InputUser {
+                user_id: user.id,
+                access_hash,
+            }
+            .into()),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the access hash of user {} is unknown", user.id),
+            )),
+        })
+        .collect()
+}
+
+impl PrivacyRule {
+    pub(crate) fn to_input_privacy_rule(&self) -> io::Result<tl::enums::InputPrivacyRule> {
+        Ok(match self {
+            Self::AllowAll => tl::types::InputPrivacyValueAllowAll {}.into(),
+            Self::AllowContacts => tl::types::InputPrivacyValueAllowContacts {}.into(),
+            Self::AllowUsers(users) => tl::types::InputPrivacyValueAllowUsers {
+                users: input_users(users)?,
+            }
+            .into(),
+            Self::AllowChatParticipants(chats) => {
+                tl::types::InputPrivacyValueAllowChatParticipants {
+                    chats: chats.clone(),
+                }
+                .into()
+            }
+            Self::DisallowAll => tl::types::InputPrivacyValueDisallowAll {}.into(),
+            Self::DisallowContacts => tl::types::InputPrivacyValueDisallowContacts {}.into(),
+            Self::DisallowUsers(users) => tl::types::InputPrivacyValueDisallowUsers {
+                users: input_users(users)?,
+            }
+            .into(),
+            Self::DisallowChatParticipants(chats) => {
+                tl::types::InputPrivacyValueDisallowChatParticipants {
+                    chats: chats.clone(),
+                }
+                .into()
+            }
+        })
+    }
+
+    /// Converts the rules returned by Telegram, packing the users they
+    /// mention with the access hashes found in the same response.
+    pub(crate) fn from_raw(rules: tl::enums::account::PrivacyRules) -> Vec<Self> {
+        let rules = tl::types::account::PrivacyRules::from(rules);
+        let users = rules
+            .users
+            .into_iter()
+            .filter_map(|user| user.try_into().ok())
+            .map(|user: tl::types::User| (user.id, Entity::User(user).pack()))
+            .collect::<HashMap<_, _>>();
+        let pack = |ids: Vec<i32>| {
+            ids.into_iter()
+                .map(|id| {
+                    users.get(&id).copied().unwrap_or(PackedChat {
+                        ty: PackedType::User,
+                        id,
+                        access_hash: None,
+                    })
+                })
+                .collect()
+        };
+
+        rules
+            .rules
+            .into_iter()
+            .filter_map(|rule| {
+                use tl::enums::PrivacyRule as R;
+                Some(match rule {
+                    R::PrivacyValueAllowAll(_) => Self::AllowAll,
+                    R::PrivacyValueAllowContacts(_) => Self::AllowContacts,
+                    R::PrivacyValueAllowUsers(rule) => Self::AllowUsers(pack(rule.users)),
+                    R::PrivacyValueAllowChatParticipants(rule) => {
+                        Self::AllowChatParticipants(rule.chats)
+                    }
+                    R::PrivacyValueDisallowAll(_) => Self::DisallowAll,
+                    R::PrivacyValueDisallowContacts(_) => Self::DisallowContacts,
+                    R::PrivacyValueDisallowUsers(rule) => Self::DisallowUsers(pack(rule.users)),
+                    R::PrivacyValueDisallowChatParticipants(rule) => {
+                        Self::DisallowChatParticipants(rule.chats)
+                    }
+                    #[allow(unreachable_patterns)]
+                    _ => return None,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_rules_pack_known_users() {
+        let rules = tl::types::account::PrivacyRules {
+            rules: vec![
+                tl::types::PrivacyValueAllowContacts {}.into(),
+                tl::types::PrivacyValueDisallowUsers { users: vec![1, 2] }.into(),
+            ],
+            chats: Vec::new(),
+            users: vec![tl::types::UserEmpty { id: 3 }.into()],
+        };
+
+        let rules = PrivacyRule::from_raw(rules.into());
+        assert_eq!(rules[0], PrivacyRule::AllowContacts);
+        match &rules[1] {
+            PrivacyRule::DisallowUsers(users) => {
+                assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2]);
+                assert!(users.iter().all(|u| u.access_hash.is_none()));
+            }
+            _ => panic!("unexpected rule"),
+        }
+
+        // Users without an access hash can't be set again.
+        let error = rules[1].to_input_privacy_rule().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn check_rules_pack_access_hash() {
+        let user = tl::types::User {
+            is_self: false,
+            contact: true,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            id: 1,
+            access_hash: Some(11),
+            first_name: Some("Alice".to_string()),
+            last_name: None,
+            username: None,
+            phone: None,
+            photo: None,
+            status: None,
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: None,
+        };
+        let rules = tl::types::account::PrivacyRules {
+            rules: vec![tl::types::PrivacyValueAllowUsers { users: vec![1] }.into()],
+            chats: Vec::new(),
+            users: vec![user.into()],
+        };
+
+        let rules = PrivacyRule::from_raw(rules.into());
+        let expected = PackedChat {
+            ty: PackedType::User,
+            id: 1,
+            access_hash: Some(11),
+        };
+        assert_eq!(rules, vec![PrivacyRule::AllowUsers(vec![expected])]);
+
+        match rules[0].to_input_privacy_rule().unwrap() {
+            tl::enums::InputPrivacyRule::InputPrivacyValueAllowUsers(rule) => match &rule.users[..]
+            {
+                [tl::enums::InputUser::InputUser(user)] => {
+                    assert_eq!((user.user_id, user.access_hash), (1, 11))
+                }
+                _ => panic!("unexpected users"),
+            },
+            _ => panic!("unexpected rule"),
+        }
+    }
+}
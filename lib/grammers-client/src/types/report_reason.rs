@@ -0,0 +1,36 @@
+use grammers_tl_types as tl;
+
+/// The reason why a chat or user is being reported.
+pub enum ReportReason {
+    /// The chat sends spam.
+    Spam,
+    /// The chat promotes violence.
+    Violence,
+    /// The chat contains pornographic content.
+    Pornography,
+    /// The chat contains child abuse.
+    ChildAbuse,
+    /// The chat infringes copyright.
+    Copyright,
+    /// The location of a geo-group is not relevant.
+    GeoIrrelevant,
+    /// Any other reason, which should be explained in the message.
+    Other,
+}
+
+impl ReportReason {
+    pub(crate) fn to_input_report_reason(&self, message: &str) -> tl::enums::ReportReason {
+        match self {
+            Self::Spam => tl::types::InputReportReasonSpam {}.into(),
+            Self::Violence => tl::types::InputReportReasonViolence {}.into(),
+            Self::Pornography => tl::types::InputReportReasonPornography {}.into(),
+            Self::ChildAbuse => tl::types::InputReportReasonChildAbuse {}.into(),
+            Self::Copyright => tl::types::InputReportReasonCopyright {}.into(),
+            Self::GeoIrrelevant => tl::types::InputReportReasonGeoIrrelevant {}.into(),
+            Self::Other => tl::types::InputReportReasonOther {
+                text: message.to_string(),
+            }
+            .into(),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::SystemTime;
+
+use grammers_tl_types as tl;
+
+use super::{chat_id, timestamp};
+
+/// A message generated by Telegram when something happens in a chat, such
+/// as a user joining a group or its title being changed.
+pub struct ServiceMessage {
+    pub message: tl::types::MessageService,
+}
+
+/// The action that caused a service message, classified by what most
+/// applications care about.
+pub enum ServiceAction<'a> {
+    /// A basic group was created with the given title and members.
+    ChatCreated { title: &'a str, user_ids: &'a [i32] },
+    /// A channel or megagroup was created with the given title.
+    ChannelCreated { title: &'a str },
+    /// The users were added to the group (or joined it by themselves).
+    UsersAdded { user_ids: &'a [i32] },
+    /// A user joined the group through an invite link created by
+    /// `inviter_id`.
+    UserJoinedByLink { inviter_id: i32 },
+    /// A user left or was removed from the group.
+    UserRemoved { user_id: i32 },
+    /// The title was changed.
+    TitleChanged { title: &'a str },
+    /// The photo was changed.
+    PhotoChanged(&'a tl::enums::Photo),
+    /// The photo was removed.
+    PhotoDeleted,
+    /// A message was pinned. Its ID is `None` if the pinned message is not
+    /// known.
+    MessagePinned { message_id: Option<i32> },
+    /// The basic group was upgraded to the megagroup `channel_id`.
+    MigratedTo { channel_id: i32 },
+    /// The megagroup was created by upgrading the basic group `chat_id`.
+    MigratedFrom { title: &'a str, chat_id: i32 },
+    /// Any other action that does not have a more convenient variant yet.
+    Raw(&'a tl::enums::MessageAction),
+}
+
+impl ServiceMessage {
+    /// Returns the identifier of the message, which is unique within its
+    /// chat (private conversations and small groups share the same ID space).
+    pub fn id(&self) -> i32 {
+        self.message.id
+    }
+
+    /// Returns the time at which the action occurred.
+    pub fn date(&self) -> SystemTime {
+        timestamp(self.message.date)
+    }
+
+    /// Returns the ID of the user who performed the action, if any.
+    pub fn sender_id(&self) -> Option<i32> {
+        self.message.from_id
+    }
+
+    /// Returns the chat where the action occurred.
+    pub fn chat(&self) -> &tl::enums::Peer {
+        &self.message.to_id
+    }
+
+    /// Returns the ID of the chat where the action occurred, which for
+    /// private conversations is always the ID of the other user.
+    pub fn chat_id(&self) -> i32 {
+        chat_id(self.message.out, self.message.from_id, &self.message.to_id)
+    }
+
+    /// Returns the action that caused this service message.
+    pub fn action(&self) -> ServiceAction<'_> {
+        use tl::enums::MessageAction as A;
+
+        match &self.message.action {
+            A::MessageActionChatCreate(a) => ServiceAction::ChatCreated {
+                title: &a.title,
+                user_ids: &a.users,
+            },
+            A::MessageActionChannelCreate(a) => ServiceAction::ChannelCreated { title: &a.title },
+            A::MessageActionChatAddUser(a) => ServiceAction::UsersAdded { user_ids: &a.users },
+            A::MessageActionChatJoinedByLink(a) => ServiceAction::UserJoinedByLink {
+                inviter_id: a.inviter_id,
+            },
+            A::MessageActionChatDeleteUser(a) => ServiceAction::UserRemoved { user_id: a.user_id },
+            A::MessageActionChatEditTitle(a) => ServiceAction::TitleChanged { title: &a.title },
+            A::MessageActionChatEditPhoto(a) => ServiceAction::PhotoChanged(&a.photo),
+            A::MessageActionChatDeletePhoto(_) => ServiceAction::PhotoDeleted,
+            // The pinned message is the one this service message replies to.
+            A::MessageActionPinMessage(_) => ServiceAction::MessagePinned {
+                message_id: self.message.reply_to_msg_id,
+            },
+            A::MessageActionChatMigrateTo(a) => ServiceAction::MigratedTo {
+                channel_id: a.channel_id,
+            },
+            A::MessageActionChannelMigrateFrom(a) => ServiceAction::MigratedFrom {
+                title: &a.title,
+                chat_id: a.chat_id,
+            },
+            action => ServiceAction::Raw(action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service_message(
+        action: tl::enums::MessageAction,
+        reply_to_msg_id: Option<i32>,
+    ) -> ServiceMessage {
+        ServiceMessage {
+            message: tl::types::MessageService {
+                out: false,
+                mentioned: false,
+                media_unread: false,
+                silent: false,
+                post: false,
+                legacy: false,
+                id: 10,
+                from_id: Some(1),
+                to_id: tl::types::PeerChat { chat_id: 2 }.into(),
+                reply_to_msg_id,
+                date: 0,
+                action,
+            },
+        }
+    }
+
+    #[test]
+    fn check_action_classification() {
+        let message = service_message(
+            tl::types::MessageActionChatAddUser { users: vec![3, 4] }.into(),
+            None,
+        );
+        match message.action() {
+            ServiceAction::UsersAdded { user_ids } => assert_eq!(user_ids, &[3, 4]),
+            _ => panic!("wrong action"),
+        }
+
+        let message = service_message(tl::types::MessageActionPinMessage {}.into(), Some(7));
+        match message.action() {
+            ServiceAction::MessagePinned { message_id } => assert_eq!(message_id, Some(7)),
+            _ => panic!("wrong action"),
+        }
+
+        let message = service_message(tl::types::MessageActionHistoryClear {}.into(), None);
+        assert!(matches!(message.action(), ServiceAction::Raw(_)));
+    }
+}
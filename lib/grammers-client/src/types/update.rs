@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use grammers_tl_types as tl;
+
+use super::{chat_id, Message, PackedChat, PackedType, ServiceMessage, UserStatus};
+
+/// The users and channels sent along with some updates, packed so that they
+/// can be used as input, and indexed by their kind and ID.
+pub(crate) struct PackedChats(HashMap<(PackedType, i32), PackedChat>);
+
+impl PackedChats {
+    pub(crate) fn new(users: &[tl::enums::User], chats: &[tl::enums::Chat]) -> Self {
+        let users = users.iter().filter_map(|user| match user {
+            tl::enums::User::User(user) => Some((PackedType::User, user.id, user.access_hash?)),
+            _ => None,
+        });
+        let channels = chats.iter().filter_map(|chat| match chat {
+            tl::enums::Chat::Channel(channel) => {
+                Some((PackedType::Channel, channel.id, channel.access_hash?))
+            }
+            tl::enums::Chat::ChannelForbidden(channel) => {
+                Some((PackedType::Channel, channel.id, channel.access_hash))
+            }
+            _ => None,
+        });
+
+        Self(
+            users
+                .chain(channels)
+                .map(|(ty, id, access_hash)| {
+                    let chat = PackedChat {
+                        ty,
+                        id,
+                        access_hash: Some(access_hash),
+                    };
+                    ((ty, id), chat)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the packed chat where the message was sent, if known. Small
+    /// group chats don't need an access hash, so they're always known.
+    fn get(&self, message: &tl::types::Message) -> Option<PackedChat> {
+        let id = chat_id(message.out, message.from_id, &message.to_id);
+        let ty = match message.to_id {
+            tl::enums::Peer::PeerUser(_) => PackedType::User,
+            tl::enums::Peer::PeerChat(_) => {
+                return Some(PackedChat {
+                    ty: PackedType::Chat,
+                    id,
+                    access_hash: None,
+                })
+            }
+            tl::enums::Peer::PeerChannel(_) => PackedType::Channel,
+            #[allow(unreachable_patterns)]
+            _ => return None,
+        };
+        self.0.get(&(ty, id)).copied()
+    }
+}
+
+pub enum Update {
+    /// A new message was sent, either in a private conversation, a group,
+    /// or a channel.
+    NewMessage(Message),
+    /// A new service message was sent, such as when a user joins a group
+    /// or its title is changed.
+    NewServiceMessage(ServiceMessage),
+    /// A user is performing an action, such as typing, in a private
+    /// conversation (where `chat_id` is `None`) or in a group chat.
+    UserTyping {
+        user_id: i32,
+        chat_id: Option<i32>,
+        action: tl::enums::SendMessageAction,
+    },
+    /// A user went online or offline.
+    UserStatus { user_id: i32, status: UserStatus },
+    /// Any other update that does not have a more convenient variant yet.
+    Raw(tl::enums::Update),
+}
+
+impl Update {
+    /// Wraps a new message returned outside of an update, such as when
+    /// fetching the difference. Empty messages are wrapped as `Raw` updates,
+    /// without information about their `pts`.
+    pub(crate) fn from_new_message(message: tl::enums::Message) -> Self {
+        match message {
+            tl::enums::Message::Message(message) => Self::NewMessage(Message {
+                message,
+                packed_chat: None,
+            }),
+            tl::enums::Message::MessageService(message) => {
+                Self::NewServiceMessage(ServiceMessage { message })
+            }
+            message => Self::Raw(
+                tl::types::UpdateNewMessage {
+                    message,
+                    pts: 0,
+                    pts_count: 0,
+                }
+                .into(),
+            ),
+        }
+    }
+
+    /// Returns the message if this update is a new message.
+    pub fn as_new_message(&self) -> Option<&Message> {
+        match self {
+            Self::NewMessage(message) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this update is a new message sent by the current
+    /// account, which bots usually want to ignore.
+    pub fn is_outgoing(&self) -> bool {
+        match self {
+            Self::NewMessage(message) => message.outgoing(),
+            _ => false,
+        }
+    }
+
+    /// Returns the chat where this update occurred if it's a new message,
+    /// packed so that it can be used to reply.
+    ///
+    /// Users and channels can't be used as input without their access hash,
+    /// so `None` is returned if it didn't come along with the update.
+    pub fn chat(&self) -> Option<PackedChat> {
+        self.as_new_message()
+            .and_then(|message| message.packed_chat)
+    }
+
+    /// Fills the chat of new messages from the users and chats which came
+    /// along with the update.
+    pub(crate) fn with_chats(mut self, chats: &PackedChats) -> Self {
+        if let Self::NewMessage(message) = &mut self {
+            message.packed_chat = chats.get(&message.message);
+        }
+        self
+    }
+}
+
+impl From<tl::enums::Update> for Update {
+    fn from(update: tl::enums::Update) -> Self {
+        use tl::enums::Message::{Message as M, MessageService as MS};
+
+        match update {
+            tl::enums::Update::UpdateNewMessage(tl::types::UpdateNewMessage {
+                message: M(message),
+                ..
+            })
+            | tl::enums::Update::UpdateNewChannelMessage(tl::types::UpdateNewChannelMessage {
+                message: M(message),
+                ..
+            }) => Self::NewMessage(Message {
+                message,
+                packed_chat: None,
+            }),
+            tl::enums::Update::UpdateNewMessage(tl::types::UpdateNewMessage {
+                message: MS(message),
+                ..
+            })
+            | tl::enums::Update::UpdateNewChannelMessage(tl::types::UpdateNewChannelMessage {
+                message: MS(message),
+                ..
+            }) => Self::NewServiceMessage(ServiceMessage { message }),
+            tl::enums::Update::UpdateUserTyping(update) => Self::UserTyping {
+                user_id: update.user_id,
+                chat_id: None,
+                action: update.action,
+            },
+            tl::enums::Update::UpdateChatUserTyping(update) => Self::UserTyping {
+                user_id: update.user_id,
+                chat_id: Some(update.chat_id),
+                action: update.action,
+            },
+            tl::enums::Update::UpdateUserStatus(update) => Self::UserStatus {
+                user_id: update.user_id,
+                status: update.status.into(),
+            },
+            update => Self::Raw(update),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_message(out: bool, from_id: Option<i32>, to_id: tl::enums::Peer) -> Update {
+        Update::from_new_message(
+            tl::types::Message {
+                out,
+                mentioned: false,
+                media_unread: false,
+                silent: false,
+                post: false,
+                from_scheduled: false,
+                legacy: false,
+                edit_hide: false,
+                id: 1,
+                from_id,
+                to_id,
+                fwd_from: None,
+                via_bot_id: None,
+                reply_to_msg_id: None,
+                date: 0,
+                message: "Hi".to_string(),
+                media: None,
+                reply_markup: None,
+                entities: None,
+                views: None,
+                edit_date: None,
+                post_author: None,
+                grouped_id: None,
+                restriction_reason: None,
+            }
+            .into(),
+        )
+    }
+
+    fn user(id: i32, access_hash: Option<i64>) -> tl::enums::User {
+        tl::types::User {
+            is_self: false,
+            contact: false,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            id,
+            access_hash,
+            first_name: None,
+            last_name: None,
+            username: None,
+            phone: None,
+            photo: None,
+            status: None,
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn check_new_message_accessors() {
+        let update = new_message(true, Some(1), tl::types::PeerChat { chat_id: 2 }.into());
+        assert_eq!(update.as_new_message().map(Message::text), Some("Hi"));
+        assert!(update.is_outgoing());
+
+        let update = new_message(false, Some(1), tl::types::PeerChat { chat_id: 2 }.into());
+        assert!(!update.is_outgoing());
+
+        let update = Update::from(tl::enums::Update::from(tl::types::UpdateUserStatus {
+            user_id: 1,
+            status: tl::types::UserStatusEmpty {}.into(),
+        }));
+        assert!(update.as_new_message().is_none());
+        assert!(!update.is_outgoing());
+        assert!(update.chat().is_none());
+    }
+
+    #[test]
+    fn check_chat_is_packed_from_entities() {
+        let chats = PackedChats::new(&[user(1, Some(11)), user(3, None)], &[]);
+
+        // Private messages sent to us belong to the sender.
+        let update = new_message(false, Some(1), tl::types::PeerUser { user_id: 2 }.into())
+            .with_chats(&chats);
+        assert_eq!(
+            update.chat(),
+            Some(PackedChat {
+                ty: PackedType::User,
+                id: 1,
+                access_hash: Some(11),
+            })
+        );
+
+        // Small group chats need no access hash.
+        let update = new_message(false, Some(3), tl::types::PeerChat { chat_id: 4 }.into())
+            .with_chats(&chats);
+        assert_eq!(
+            update.chat(),
+            Some(PackedChat {
+                ty: PackedType::Chat,
+                id: 4,
+                access_hash: None,
+            })
+        );
+
+        // Chats without a known access hash can't be used as input.
+        let update = new_message(false, Some(3), tl::types::PeerUser { user_id: 2 }.into())
+            .with_chats(&chats);
+        assert_eq!(update.chat(), None);
+        let update = new_message(false, None, tl::types::PeerChannel { channel_id: 5 }.into())
+            .with_chats(&chats);
+        assert_eq!(update.chat(), None);
+    }
+}
@@ -0,0 +1,74 @@
+use grammers_tl_types as tl;
+
+/// The last time a user was seen online.
+///
+/// Users may choose to hide the exact time from others, in which case only
+/// an approximation is known.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UserStatus {
+    /// The status is unknown, or the user has never been online.
+    Unknown,
+    /// The user is online, and will be considered so until the `expires`
+    /// Unix timestamp, unless the status is updated before that.
+    Online { expires: i32 },
+    /// The user is offline, and was last online at the `was_online` Unix
+    /// timestamp.
+    Offline { was_online: i32 },
+    /// The user was online recently (the exact time is hidden).
+    Recently,
+    /// The user was online within the last week (the exact time is hidden).
+    LastWeek,
+    /// The user was online within the last month (the exact time is hidden).
+    LastMonth,
+}
+
+impl From<tl::enums::UserStatus> for UserStatus {
+    fn from(status: tl::enums::UserStatus) -> Self {
+        use tl::enums::UserStatus as S;
+
+        match status {
+            S::UserStatusOnline(status) => Self::Online {
+                expires: status.expires,
+            },
+            S::UserStatusOffline(status) => Self::Offline {
+                was_online: status.was_online,
+            },
+            S::UserStatusRecently(_) => Self::Recently,
+            S::UserStatusLastWeek(_) => Self::LastWeek,
+            S::UserStatusLastMonth(_) => Self::LastMonth,
+            // Includes `UserStatusEmpty`.
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_status_conversion() {
+        assert_eq!(
+            UserStatus::from(tl::enums::UserStatus::from(tl::types::UserStatusEmpty {})),
+            UserStatus::Unknown
+        );
+        assert_eq!(
+            UserStatus::from(tl::enums::UserStatus::from(tl::types::UserStatusOnline {
+                expires: 123
+            })),
+            UserStatus::Online { expires: 123 }
+        );
+        assert_eq!(
+            UserStatus::from(tl::enums::UserStatus::from(tl::types::UserStatusOffline {
+                was_online: 456
+            })),
+            UserStatus::Offline { was_online: 456 }
+        );
+        assert_eq!(
+            UserStatus::from(tl::enums::UserStatus::from(
+                tl::types::UserStatusRecently {}
+            )),
+            UserStatus::Recently
+        );
+    }
+}
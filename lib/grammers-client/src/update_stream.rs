@@ -0,0 +1,131 @@
+use std::io;
+use std::panic;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use fallible_iterator::FallibleIterator;
+
+use crate::types::Update;
+use crate::{Client, Error};
+
+/// A handle to a background thread fetching updates for a [`Client`].
+///
+/// The client is moved into the background thread for as long as the stream
+/// is alive, which periodically fetches any missed updates with
+/// [`Client::catch_up`]. Use [`close`] to stop the thread and get the client
+/// back. Dropping the stream also stops and joins the thread, but the client
+/// is dropped along with it, which closes its connection.
+///
+/// The thread only checks whether it should stop in between fetches, so
+/// closing the stream may block until an in-flight request completes or the
+/// network timeout expires. This guarantees the connection is never left in
+/// the middle of reading a response.
+///
+/// [`Client`]: struct.Client.html
+/// [`Client::catch_up`]: struct.Client.html#method.catch_up
+/// [`close`]: #method.close
+pub struct UpdateStream {
+    updates: mpsc::Receiver<Result<Update, Error>>,
+    /// Dropping this sender signals the background thread to stop.
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<Client>>,
+}
+
+impl UpdateStream {
+    pub(crate) fn spawn(mut client: Client, poll_interval: Duration) -> Result<Self, Error> {
+        let (update_tx, updates) = mpsc::channel();
+        let (stop, stop_rx) = mpsc::channel::<()>();
+
+        let handle = thread::Builder::new()
+            .name("grammers-updates".into())
+            .spawn(move || loop {
+                match client.catch_up() {
+                    Ok(batch) => {
+                        for update in batch {
+                            if update_tx.send(Ok(update)).is_err() {
+                                return client;
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        let _ = update_tx.send(Err(error));
+                        return client;
+                    }
+                }
+
+                match stop_rx.recv_timeout(poll_interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => return client,
+                }
+            })?;
+
+        Ok(Self {
+            updates,
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Stops the background thread, waits for it to finish, and returns the
+    /// client it was using, which remains connected.
+    ///
+    /// Updates that were fetched but not yet returned are discarded.
+    pub fn close(mut self) -> Client {
+        match self.stop_and_join() {
+            Some(Ok(client)) => client,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => unreachable!("the thread is only joined once"),
+        }
+    }
+
+    /// Like [`next`], but gives up waiting after `timeout`, returning `None`,
+    /// so that the update loop can be interleaved with other work.
+    ///
+    /// Timing out never loses data: updates are fetched and buffered by the
+    /// background thread as whole values, and one that arrives after the
+    /// timeout is returned by the next call. Once the background thread has
+    /// stopped, [`Error::Disconnected`] is returned (after the error that
+    /// stopped it, if any).
+    ///
+    /// [`next`]: #method.next
+    /// [`Error::Disconnected`]: enum.Error.html#variant.Disconnected
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<Update>, Error> {
+        match self.updates.recv_timeout(timeout) {
+            Ok(Ok(update)) => Ok(Some(update)),
+            Ok(Err(error)) => Err(error),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(Error::Disconnected(io::ErrorKind::NotConnected.into()))
+            }
+        }
+    }
+
+    fn stop_and_join(&mut self) -> Option<thread::Result<Client>> {
+        drop(self.stop.take());
+        self.handle.take().map(JoinHandle::join)
+    }
+}
+
+impl FallibleIterator for UpdateStream {
+    type Item = Update;
+    type Error = Error;
+
+    /// Blocks until the next update arrives. Once the background thread has
+    /// stopped, for example due to a network error (which is returned
+    /// first), `None` is returned.
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        match self.updates.recv() {
+            Ok(Ok(update)) => Ok(Some(update)),
+            Ok(Err(error)) => Err(error),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl Drop for UpdateStream {
+    fn drop(&mut self) {
+        // A panic in the background thread has nowhere to go while dropping.
+        let _ = self.stop_and_join();
+    }
+}
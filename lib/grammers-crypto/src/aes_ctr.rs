@@ -0,0 +1,64 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use openssl::symm::{Cipher, Crypter, Mode};
+
+/// A stream cipher using AES-256 in CTR mode, as required by the
+/// [obfuscated transports].
+///
+/// The same instance must be used for all the data going in one direction,
+/// since its state advances with every byte that goes through it.
+///
+/// [obfuscated transports]: https://core.telegram.org/mtproto/mtproto-transports#transport-obfuscation
+pub struct AesCtr {
+    crypter: Crypter,
+}
+
+impl AesCtr {
+    /// Creates a new cipher with the given key and initial counter.
+    pub fn new(key: &[u8; 32], iv: &[u8; 16]) -> Self {
+        // The key and IV sizes always match what the cipher expects.
+        let crypter = Crypter::new(Cipher::aes_256_ctr(), Mode::Encrypt, key, Some(iv)).unwrap();
+        Self { crypter }
+    }
+
+    /// Encrypts or decrypts (the operation is the same) the data in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        // CTR never outputs more than its input, but OpenSSL requires room
+        // for an extra block.
+        let mut output = vec![0; data.len() + 16];
+        let len = self.crypter.update(data, &mut output).unwrap();
+        data.copy_from_slice(&output[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_split_apply_matches_whole() {
+        let key = [1; 32];
+        let iv = [2; 16];
+        let plaintext = (0..100).collect::<Vec<u8>>();
+
+        let mut whole = plaintext.clone();
+        AesCtr::new(&key, &iv).apply(&mut whole);
+
+        let mut split = plaintext.clone();
+        let mut cipher = AesCtr::new(&key, &iv);
+        let (left, right) = split.split_at_mut(37);
+        cipher.apply(left);
+        cipher.apply(right);
+
+        assert_eq!(whole, split);
+        assert_ne!(whole, plaintext);
+
+        AesCtr::new(&key, &iv).apply(&mut whole);
+        assert_eq!(whole, plaintext);
+    }
+}
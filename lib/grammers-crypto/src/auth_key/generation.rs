@@ -262,8 +262,8 @@ fn do_step2(
 ) -> Result<(Vec<u8>, Step2), AuthKeyGenError> {
     // Step 2. Validate the PQ response. Return `(p, q)` if it's valid.
     let Step1 { nonce } = data;
-    let tl::enums::ResPQ::ResPQ(res_pq) =
-        <tl::functions::ReqPqMulti as RPC>::Return::from_bytes(&response)?;
+    let res_pq: tl::types::ResPQ =
+        <tl::functions::ReqPqMulti as RPC>::Return::from_bytes(&response)?.into();
 
     check_nonce(&res_pq.nonce, &nonce)?;
 
@@ -407,6 +407,8 @@ fn do_step3(
             return Err(AuthKeyGenError::DHParamsFail);
         }
         tl::enums::ServerDHParams::ServerDHParamsOk(x) => x,
+        #[allow(unreachable_patterns)]
+        _ => return Err(unexpected_response()),
     };
 
     check_nonce(&server_dh_params.nonce, &nonce)?;
@@ -434,7 +436,7 @@ fn do_step3(
     // where the padding starts).
     let mut plain_text_cursor = io::Cursor::new(&plain_text_answer[20..]);
     let server_dh_inner = match tl::enums::ServerDHInnerData::deserialize(&mut plain_text_cursor) {
-        Ok(tl::enums::ServerDHInnerData::ServerDHInnerData(x)) => x,
+        Ok(x) => tl::types::ServerDHInnerData::from(x),
         Err(error) => return Err(AuthKeyGenError::InvalidDHInnerData { error }),
     };
 
@@ -571,6 +573,8 @@ pub fn create_key(data: Step3, response: Vec<u8>) -> Result<(AuthKey, i32), Auth
             new_nonce_hash: x.new_nonce_hash3,
             nonce_number: 3,
         },
+        #[allow(unreachable_patterns)]
+        _ => return Err(unexpected_response()),
     };
 
     check_nonce(&dh_gen.nonce, &nonce)?;
@@ -579,7 +583,7 @@ pub fn create_key(data: Step3, response: Vec<u8>) -> Result<(AuthKey, i32), Auth
     let auth_key = {
         let mut buffer = [0; 256];
         buffer.copy_from_slice(&gab.to_bytes_be());
-        AuthKey::from_bytes(buffer)
+        AuthKey::from(buffer)
     };
 
     let new_nonce_hash = auth_key.calc_new_nonce_hash(&new_nonce, dh_gen.nonce_number);
@@ -593,6 +597,14 @@ pub fn create_key(data: Step3, response: Vec<u8>) -> Result<(AuthKey, i32), Auth
     }
 }
 
+/// The error for a response with a constructor unknown at compile time,
+/// which can only occur if the types are generated as non-exhaustive.
+fn unexpected_response() -> AuthKeyGenError {
+    AuthKeyGenError::InvalidResponse {
+        error: io::Error::new(io::ErrorKind::InvalidData, "unexpected constructor"),
+    }
+}
+
 /// Helper function to avoid the boilerplate of checking for invalid nonce.
 fn check_nonce(got: &[u8; 16], expected: &[u8; 16]) -> Result<(), AuthKeyGenError> {
     if got == expected {
@@ -833,7 +845,7 @@ mod tests {
         let response = step3_response.to_vec();
 
         let (auth_key, time_offset) = create_key(data, response)?;
-        assert_eq!(auth_key, AuthKey::from_bytes(expected_auth_key));
+        assert_eq!(auth_key, AuthKey::from(expected_auth_key));
         assert_eq!(time_offset, 0);
 
         Ok(())
@@ -9,6 +9,7 @@ pub mod generation;
 
 use openssl::sha::sha1;
 use std::fmt;
+use std::io;
 
 #[derive(Clone)]
 pub struct AuthKey {
@@ -31,25 +32,27 @@ impl PartialEq for AuthKey {
 /// [authorization key]: https://core.telegram.org/mtproto/auth_key
 /// [`generation`]: generation.html
 impl AuthKey {
-    /// Creates a new authorization key from the given binary data.
-    pub fn from_bytes(data: [u8; 256]) -> Self {
-        let sha = sha1(&data);
-        let aux_hash = {
-            let mut buffer = [0; 8];
-            buffer.copy_from_slice(&sha[0..8]);
-            buffer
-        };
-        let key_id = {
-            let mut buffer = [0; 8];
-            buffer.copy_from_slice(&sha[12..12 + 8]);
-            buffer
-        };
-
-        Self {
-            data,
-            aux_hash,
-            key_id,
+    /// Creates a new authorization key from the given binary data, such as
+    /// the one previously returned by [`to_bytes`].
+    ///
+    /// The key ID is recomputed from the data. An error is returned if the
+    /// data is not exactly 256 bytes long.
+    ///
+    /// [`to_bytes`]: #method.to_bytes
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        if data.len() != 256 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "authorization key must be 256 bytes long, got {}",
+                    data.len()
+                ),
+            ));
         }
+
+        let mut buffer = [0; 256];
+        buffer.copy_from_slice(data);
+        Ok(Self::from(buffer))
     }
 
     /// Converts the authorization key to a sequence of bytes, which can
@@ -58,6 +61,16 @@ impl AuthKey {
         self.data
     }
 
+    /// Returns the identifier of this authorization key, which is sent in
+    /// the header of every encrypted message.
+    ///
+    /// It's derived from the SHA1 hash of the key, so it can be used to tell
+    /// keys apart without revealing them (for example, when logging), or to
+    /// detect a stored key that doesn't match the one the server knows.
+    pub fn key_id(&self) -> i64 {
+        i64::from_le_bytes(self.key_id)
+    }
+
     /// Calculates the new nonce hash based on the current attributes.
     pub fn calc_new_nonce_hash(&self, new_nonce: &[u8; 32], number: u8) -> [u8; 16] {
         let data = {
@@ -74,10 +87,33 @@ impl AuthKey {
     }
 }
 
+impl From<[u8; 256]> for AuthKey {
+    /// Creates a new authorization key from the given binary data.
+    fn from(data: [u8; 256]) -> Self {
+        let sha = sha1(&data);
+        let aux_hash = {
+            let mut buffer = [0; 8];
+            buffer.copy_from_slice(&sha[0..8]);
+            buffer
+        };
+        let key_id = {
+            let mut buffer = [0; 8];
+            buffer.copy_from_slice(&sha[12..12 + 8]);
+            buffer
+        };
+
+        Self {
+            data,
+            aux_hash,
+            key_id,
+        }
+    }
+}
+
 impl fmt::Debug for AuthKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AuthKey")
-            .field("key_id", &u64::from_le_bytes(self.key_id))
+            .field("key_id", &self.key_id())
             .finish()
     }
 }
@@ -93,7 +129,7 @@ mod tests {
             .enumerate()
             .for_each(|(i, x)| *x = i as u8);
 
-        AuthKey::from_bytes(buffer)
+        AuthKey::from(buffer)
     }
 
     fn get_test_new_nonce() -> [u8; 32] {
@@ -120,6 +156,26 @@ mod tests {
         let expected = [50, 209, 88, 110, 164, 87, 223, 200];
 
         assert_eq!(auth_key.key_id, expected);
+        assert_eq!(auth_key.key_id(), i64::from_le_bytes(expected));
+    }
+
+    #[test]
+    fn auth_key_bytes_roundtrip() {
+        let auth_key = get_test_auth_key();
+        let loaded = AuthKey::from_bytes(&auth_key.to_bytes()).unwrap();
+
+        assert_eq!(loaded.data[..], auth_key.data[..]);
+        assert_eq!(loaded.aux_hash, auth_key.aux_hash);
+        assert_eq!(loaded.key_id, auth_key.key_id);
+    }
+
+    #[test]
+    fn auth_key_from_bytes_bad_len() {
+        let data = get_test_auth_key().to_bytes();
+
+        assert!(AuthKey::from_bytes(&data[..255]).is_err());
+        assert!(AuthKey::from_bytes(&[data, data].concat()[..257]).is_err());
+        assert!(AuthKey::from_bytes(&[]).is_err());
     }
 
     #[test]
@@ -0,0 +1,72 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Encoding of binary data as [Base64], as needed by the WebSocket handshake
+//! and the login tokens shared through URLs.
+//!
+//! [Base64]: https://tools.ietf.org/html/rfc4648#section-4
+const STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn encode_with(data: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut result = String::with_capacity((data.len() / 3 + 1) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else if pad {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Encodes the data as standard Base64, with padding.
+pub fn encode(data: &[u8]) -> String {
+    encode_with(data, STANDARD_ALPHABET, true)
+}
+
+/// Encodes the data as the [URL-safe] variant of Base64, without padding.
+///
+/// [URL-safe]: https://tools.ietf.org/html/rfc4648#section-5
+pub fn encode_url(data: &[u8]) -> String {
+    encode_with(data, URL_SAFE_ALPHABET, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn check_encode_url() {
+        assert_eq!(encode_url(b""), "");
+        assert_eq!(encode_url(b"f"), "Zg");
+        assert_eq!(encode_url(b"fo"), "Zm8");
+        assert_eq!(encode_url(b"foo"), "Zm9v");
+        assert_eq!(encode_url(b"foob"), "Zm9vYg");
+        assert_eq!(encode_url(&[0xfb, 0xff]), "-_8");
+    }
+}
@@ -5,9 +5,14 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+mod aes_ctr;
 pub mod auth_key;
+pub mod base64;
 mod factorize;
+pub mod passport;
 mod rsa;
+pub mod two_factor_auth;
+pub use aes_ctr::AesCtr;
 pub use auth_key::AuthKey;
 use getrandom::getrandom;
 use openssl::aes::{aes_ige, AesKey};
@@ -163,7 +168,8 @@ pub enum DecryptionError {
     /// The server replied with the ID of a different authorization key.
     AuthKeyMismatch,
 
-    /// The key of the message did not match our expectations.
+    /// The key of the message did not match the one calculated from the
+    /// decrypted data, which means the message was corrupted or tampered.
     MessageKeyMismatch,
 }
 
@@ -171,8 +177,11 @@ impl Error for DecryptionError {}
 
 impl fmt::Display for DecryptionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO better display
-        write!(f, "{:?}", self)
+        match self {
+            Self::InvalidBuffer => write!(f, "invalid ciphertext buffer length"),
+            Self::AuthKeyMismatch => write!(f, "server authkey mismatches with ours"),
+            Self::MessageKeyMismatch => write!(f, "message integrity check failed"),
+        }
     }
 }
 
@@ -270,6 +279,16 @@ pub fn generate_key_data_from_nonce(
     (key, iv)
 }
 
+/// Calculates the SHA-256 hash of the data, as used by Telegram to verify
+/// the integrity of downloaded files.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(data);
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
 /// Encrypt data using AES-IGE.
 pub fn encrypt_ige(plaintext: &[u8], key: &[u8; 32], iv: &[u8; 32]) -> Vec<u8> {
     let mut padded: Vec<u8>;
@@ -325,7 +344,7 @@ mod tests {
             .enumerate()
             .for_each(|(i, x)| *x = i as u8);
 
-        AuthKey::from_bytes(buffer)
+        AuthKey::from(buffer)
     }
 
     fn get_test_msg_key() -> [u8; 16] {
@@ -401,9 +420,8 @@ mod tests {
         );
     }
 
-    #[test]
-    fn decrypt_server_data_v2() {
-        let ciphertext = vec![
+    fn get_test_server_ciphertext() -> Vec<u8> {
+        vec![
             122, 113, 131, 194, 193, 14, 79, 77, 249, 69, 250, 154, 154, 189, 53, 231, 195, 132,
             11, 97, 240, 69, 48, 79, 57, 103, 76, 25, 192, 226, 9, 120, 79, 80, 246, 34, 106, 7,
             53, 41, 214, 117, 201, 44, 191, 11, 250, 140, 153, 167, 155, 63, 57, 199, 42, 93, 154,
@@ -413,8 +431,11 @@ mod tests {
             169, 134, 18, 174, 254, 148, 44, 93, 146, 18, 26, 203, 141, 176, 45, 204, 206, 182,
             109, 15, 135, 32, 172, 18, 160, 109, 176, 88, 43, 253, 149, 91, 227, 79, 54, 81, 24,
             227, 186, 184, 205, 8, 12, 230, 180, 91, 40, 234, 197, 109, 205, 42, 41, 55, 78,
-        ];
-        let auth_key = AuthKey::from_bytes([
+        ]
+    }
+
+    fn get_test_server_auth_key() -> AuthKey {
+        AuthKey::from([
             93, 46, 125, 101, 244, 158, 194, 139, 208, 41, 168, 135, 97, 234, 39, 184, 164, 199,
             159, 18, 34, 101, 37, 68, 62, 125, 124, 89, 110, 243, 48, 53, 48, 219, 33, 7, 232, 154,
             169, 151, 199, 160, 22, 74, 182, 148, 24, 122, 222, 255, 21, 107, 214, 239, 113, 24,
@@ -429,7 +450,13 @@ mod tests {
             29, 16, 94, 193, 23, 51, 111, 92, 118, 198, 177, 135, 3, 125, 75, 66, 112, 206, 233,
             204, 33, 7, 29, 151, 233, 188, 162, 32, 198, 215, 176, 27, 153, 140, 242, 229, 205,
             185, 165, 14, 205, 161, 133, 42, 54, 230, 53, 105, 12, 142,
-        ]);
+        ])
+    }
+
+    #[test]
+    fn decrypt_server_data_v2() {
+        let ciphertext = get_test_server_ciphertext();
+        let auth_key = get_test_server_auth_key();
         let expected = vec![
             252, 130, 106, 2, 36, 139, 40, 253, 96, 242, 196, 130, 36, 67, 173, 104, 1, 240, 193,
             194, 145, 139, 48, 94, 2, 0, 0, 0, 88, 0, 0, 0, 220, 248, 241, 115, 2, 0, 0, 0, 1, 168,
@@ -443,6 +470,31 @@ mod tests {
         assert_eq!(decrypt_data_v2(&ciphertext, &auth_key).unwrap(), expected);
     }
 
+    #[test]
+    fn decrypt_corrupted_data_v2() {
+        let mut ciphertext = get_test_server_ciphertext();
+        let auth_key = get_test_server_auth_key();
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+
+        match decrypt_data_v2(&ciphertext, &auth_key) {
+            Err(DecryptionError::MessageKeyMismatch) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn decrypt_tampered_msg_key_v2() {
+        let mut ciphertext = get_test_server_ciphertext();
+        let auth_key = get_test_server_auth_key();
+        ciphertext[8] ^= 1;
+
+        match decrypt_data_v2(&ciphertext, &auth_key) {
+            Err(DecryptionError::MessageKeyMismatch) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
     #[test]
     fn key_from_nonce() {
         let server_nonce = {
@@ -502,4 +554,14 @@ mod tests {
         ];
         assert_eq!(decrypt_ige(&ciphertext, &key, &iv), expected);
     }
+
+    #[test]
+    fn verify_sha256() {
+        let expected = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(sha256(b"abc"), expected);
+    }
 }
@@ -0,0 +1,145 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decryption of the data shared with bots through [Telegram Passport].
+//!
+//! The credentials sent along with the values are encrypted with a secret
+//! that only the bot's private key can decrypt. Once decrypted, the
+//! credentials contain the secret and hash needed to decrypt every value.
+//!
+//! [Telegram Passport]: https://core.telegram.org/passport
+use openssl::rsa::{Padding, Rsa};
+use openssl::sha::{sha256, sha512};
+use openssl::symm::{Cipher, Crypter, Mode};
+use std::io;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Decrypts the secret of the encrypted credentials using the bot's private
+/// key in PEM format, with RSA-OAEP.
+pub fn decrypt_credentials_secret(private_key_pem: &[u8], secret: &[u8]) -> io::Result<Vec<u8>> {
+    let rsa = Rsa::private_key_from_pem(private_key_pem)?;
+    let mut buffer = vec![0; rsa.size() as usize];
+    let len = rsa.private_decrypt(secret, &mut buffer, Padding::PKCS1_OAEP)?;
+    buffer.truncate(len);
+    Ok(buffer)
+}
+
+/// Computes the `(key, iv)` pair used to encrypt the data from its secret
+/// and hash.
+fn calc_key(secret: &[u8], data_hash: &[u8]) -> ([u8; 32], [u8; 16]) {
+    let mut buffer = Vec::with_capacity(secret.len() + data_hash.len());
+    buffer.extend(secret);
+    buffer.extend(data_hash);
+    let secret_hash = sha512(&buffer);
+
+    let mut key = [0; 32];
+    let mut iv = [0; 16];
+    key.copy_from_slice(&secret_hash[..32]);
+    iv.copy_from_slice(&secret_hash[32..48]);
+    (key, iv)
+}
+
+/// Decrypts data (either the credentials or one of the values) with its
+/// secret, verifying that it matches the hash and removing its padding.
+pub fn decrypt_secure_data(data: &[u8], data_hash: &[u8], secret: &[u8]) -> io::Result<Vec<u8>> {
+    if data.is_empty() || data.len() % 16 != 0 {
+        return Err(invalid(
+            "the encrypted data is not a multiple of the block size",
+        ));
+    }
+
+    let (key, iv) = calc_key(secret, data_hash);
+    let mut crypter = Crypter::new(Cipher::aes_256_cbc(), Mode::Decrypt, &key, Some(&iv))?;
+    crypter.pad(false);
+
+    let mut decrypted = vec![0; data.len() + 16];
+    let mut len = crypter.update(data, &mut decrypted)?;
+    len += crypter.finalize(&mut decrypted[len..])?;
+    decrypted.truncate(len);
+
+    if sha256(&decrypted)[..] != *data_hash {
+        return Err(invalid("the decrypted data does not match its hash"));
+    }
+
+    // The first byte of the data indicates how many bytes of padding it has,
+    // which is always between 32 and 255.
+    let padding = decrypted[0] as usize;
+    if padding < 32 || padding > decrypted.len() {
+        return Err(invalid("the decrypted data has invalid padding"));
+    }
+
+    Ok(decrypted.split_off(padding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Inverse of `decrypt_secure_data`, which is what clients do when they
+    // share their data.
+    fn encrypt_secure_data(data: &[u8], secret: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let padding = 32 + (16 - data.len() % 16) % 16;
+
+        let mut padded = vec![0x55; padding];
+        padded[0] = padding as u8;
+        padded.extend(data);
+        let data_hash = sha256(&padded).to_vec();
+
+        let (key, iv) = calc_key(secret, &data_hash);
+        let mut crypter =
+            Crypter::new(Cipher::aes_256_cbc(), Mode::Encrypt, &key, Some(&iv)).unwrap();
+        crypter.pad(false);
+
+        let mut encrypted = vec![0; padded.len() + 16];
+        let mut len = crypter.update(&padded, &mut encrypted).unwrap();
+        len += crypter.finalize(&mut encrypted[len..]).unwrap();
+        encrypted.truncate(len);
+        (encrypted, data_hash)
+    }
+
+    #[test]
+    fn decrypt_secure_data_roundtrip() {
+        let secret = [0x42; 32];
+        let data = br#"{"first_name":"Jo","last_name":"Doe"}"#;
+
+        let (encrypted, data_hash) = encrypt_secure_data(data, &secret);
+        let decrypted = decrypt_secure_data(&encrypted, &data_hash, &secret).unwrap();
+        assert_eq!(decrypted, &data[..]);
+    }
+
+    #[test]
+    fn decrypt_secure_data_bad_hash() {
+        let secret = [0x42; 32];
+        let (encrypted, mut data_hash) = encrypt_secure_data(b"data", &secret);
+        data_hash[0] ^= 1;
+
+        assert!(decrypt_secure_data(&encrypted, &data_hash, &secret).is_err());
+        assert!(decrypt_secure_data(&encrypted[1..], &data_hash, &secret).is_err());
+    }
+
+    #[test]
+    fn decrypt_credentials_secret_oaep() {
+        let rsa = Rsa::generate(2048).unwrap();
+        let pem = rsa.private_key_to_pem().unwrap();
+        let secret = [0x24; 32];
+
+        let mut encrypted = vec![0; rsa.size() as usize];
+        let len = rsa
+            .public_encrypt(&secret, &mut encrypted, Padding::PKCS1_OAEP)
+            .unwrap();
+        encrypted.truncate(len);
+
+        assert_eq!(
+            decrypt_credentials_secret(&pem, &encrypted).unwrap(),
+            &secret[..]
+        );
+    }
+}
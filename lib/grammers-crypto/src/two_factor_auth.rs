@@ -0,0 +1,259 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Implementation of the [Secure Remote Password] protocol used by Telegram
+//! to prove knowledge of the two-factor authentication password without
+//! ever sending it.
+//!
+//! All the functions here operate on the parameters of the
+//! `passwordKdfAlgoSHA256SHA256PBKDF2HMACSHA512iter100000SHA256ModPow`
+//! algorithm, which is the only one currently in use.
+//!
+//! [Secure Remote Password]: https://core.telegram.org/api/srp
+use getrandom::getrandom;
+use num::bigint::BigUint;
+use num::One;
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use sha2::{Digest, Sha256};
+use std::io;
+
+/// The size, in bytes, of every number used by the protocol.
+const SIZE: usize = 256;
+
+/// The parameters of the password key derivation, as given by Telegram.
+pub struct PasswordAlgo<'a> {
+    pub salt1: &'a [u8],
+    pub salt2: &'a [u8],
+    pub g: i32,
+    pub p: &'a [u8],
+}
+
+/// The result of proving knowledge of the password, which is sent to
+/// Telegram along with the `srp_id` it provided.
+pub struct PasswordCheck {
+    pub a: Vec<u8>,
+    pub m1: Vec<u8>,
+}
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.input(part);
+    }
+    hasher.result().to_vec()
+}
+
+/// `SH(data, salt) := H(salt | data | salt)`
+fn salted_hash(data: &[u8], salt: &[u8]) -> Vec<u8> {
+    sha256(&[salt, data, salt])
+}
+
+/// `PH1(password, salt1, salt2) := SH(SH(password, salt1), salt2)`
+fn primary_hash(password: &[u8], salt1: &[u8], salt2: &[u8]) -> Vec<u8> {
+    salted_hash(&salted_hash(password, salt1), salt2)
+}
+
+/// `PH2(password, salt1, salt2) := SH(pbkdf2(sha512, PH1(...), salt1, 100000), salt2)`
+fn password_hash(password: &[u8], salt1: &[u8], salt2: &[u8]) -> io::Result<Vec<u8>> {
+    let mut key = [0; 64];
+    pbkdf2_hmac(
+        &primary_hash(password, salt1, salt2),
+        salt1,
+        100_000,
+        MessageDigest::sha512(),
+        &mut key,
+    )?;
+    Ok(salted_hash(&key, salt2))
+}
+
+/// Left-pads the big-endian representation of the number with zeros.
+fn pad(number: &BigUint) -> Vec<u8> {
+    let bytes = number.to_bytes_be();
+    let mut buffer = vec![0; SIZE.saturating_sub(bytes.len())];
+    buffer.extend(bytes);
+    buffer
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Computes the verifier `v = g^x mod p` of a new password, which is what
+/// Telegram stores (as `new_password_hash`) when the password is changed.
+pub fn compute_password_hash(algo: &PasswordAlgo, password: &[u8]) -> io::Result<Vec<u8>> {
+    let g = BigUint::from(algo.g as u32);
+    let p = BigUint::from_bytes_be(algo.p);
+    let x = BigUint::from_bytes_be(&password_hash(password, algo.salt1, algo.salt2)?);
+    Ok(pad(&g.modpow(&x, &p)))
+}
+
+/// Appends 32 random bytes to the `salt1` suggested by Telegram, as required
+/// when setting a new password.
+pub fn extend_salt(salt1: &[u8]) -> io::Result<Vec<u8>> {
+    let mut random = [0; 32];
+    getrandom(&mut random).map_err(|_| invalid("failed to generate a secure random salt"))?;
+
+    let mut salt = salt1.to_vec();
+    salt.extend(&random);
+    Ok(salt)
+}
+
+/// Proves knowledge of the current password, given the parameters and the
+/// server's public value `g_b` (`srp_B`) returned by `account.getPassword`.
+pub fn check_password(
+    algo: &PasswordAlgo,
+    g_b: &[u8],
+    password: &[u8],
+) -> io::Result<PasswordCheck> {
+    let mut random = [0; SIZE];
+    getrandom(&mut random).map_err(|_| invalid("failed to generate a secure random number"))?;
+    do_check_password(algo, g_b, password, &random)
+}
+
+// Inner body of `check_password`, separated for testing purposes.
+fn do_check_password(
+    algo: &PasswordAlgo,
+    g_b: &[u8],
+    password: &[u8],
+    random: &[u8; SIZE],
+) -> io::Result<PasswordCheck> {
+    let g = BigUint::from(algo.g as u32);
+    let p = BigUint::from_bytes_be(algo.p);
+    let g_b = BigUint::from_bytes_be(g_b);
+
+    let one = BigUint::one();
+    if g_b <= one || g_b >= &p - &one {
+        return Err(invalid("the server's g_b is out of range"));
+    }
+
+    // k := H(p | g)
+    let k = BigUint::from_bytes_be(&sha256(&[&pad(&p), &pad(&g)]));
+
+    let x = BigUint::from_bytes_be(&password_hash(password, algo.salt1, algo.salt2)?);
+    let v = g.modpow(&x, &p);
+
+    let a = BigUint::from_bytes_be(random);
+    let g_a = g.modpow(&a, &p);
+
+    // u := H(g_a | g_b)
+    let u = BigUint::from_bytes_be(&sha256(&[&pad(&g_a), &pad(&g_b)]));
+
+    // t := (g_b - k * v) mod p, which must be positive
+    let k_v = (k * v) % &p;
+    let t = if g_b >= k_v {
+        &g_b - &k_v
+    } else {
+        &g_b + &p - &k_v
+    };
+
+    // s_a := t^(a + u * x) mod p
+    let s_a = t.modpow(&(&a + &u * &x), &p);
+    let k_a = sha256(&[&pad(&s_a)]);
+
+    // M1 := H(H(p) xor H(g) | H(salt1) | H(salt2) | g_a | g_b | k_a)
+    let h_p = sha256(&[&pad(&p)]);
+    let h_g = sha256(&[&pad(&g)]);
+    let h_p_xor_h_g = h_p.iter().zip(h_g).map(|(p, g)| p ^ g).collect::<Vec<_>>();
+    let m1 = sha256(&[
+        &h_p_xor_h_g,
+        &sha256(&[algo.salt1]),
+        &sha256(&[algo.salt2]),
+        &pad(&g_a),
+        &pad(&g_b),
+        &k_a,
+    ]);
+
+    Ok(PasswordCheck { a: pad(&g_a), m1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_prime() -> Vec<u8> {
+        // Any odd modulus of the right size works to check the algebra.
+        let mut buffer = vec![0xc7; SIZE];
+        buffer[SIZE - 1] = 0x5b;
+        buffer
+    }
+
+    #[test]
+    fn check_password_matches_server() {
+        let p = get_test_prime();
+        let algo = PasswordAlgo {
+            salt1: b"salt1",
+            salt2: b"salt2",
+            g: 3,
+            p: &p,
+        };
+        let password = b"hunter2";
+
+        // What the server knows: the verifier, and its random value b.
+        let g = BigUint::from(3u32);
+        let p = BigUint::from_bytes_be(&p);
+        let v = BigUint::from_bytes_be(&compute_password_hash(&algo, password).unwrap());
+        let b = BigUint::from_bytes_be(&[0x42; SIZE]);
+        let k = BigUint::from_bytes_be(&sha256(&[&pad(&p), &pad(&g)]));
+        let g_b = (k * &v + g.modpow(&b, &p)) % &p;
+
+        let check = do_check_password(&algo, &pad(&g_b), password, &[0x24; SIZE]).unwrap();
+
+        // s_b := (g_a * v^u)^b mod p, which must match the client's s_a.
+        let g_a = BigUint::from_bytes_be(&check.a);
+        let u = BigUint::from_bytes_be(&sha256(&[&pad(&g_a), &pad(&g_b)]));
+        let s_b = (g_a * v.modpow(&u, &p) % &p).modpow(&b, &p);
+        let k_b = sha256(&[&pad(&s_b)]);
+
+        let h_p = sha256(&[&pad(&p)]);
+        let h_g = sha256(&[&pad(&g)]);
+        let h_p_xor_h_g = h_p.iter().zip(h_g).map(|(p, g)| p ^ g).collect::<Vec<_>>();
+        let expected = sha256(&[
+            &h_p_xor_h_g,
+            &sha256(&[b"salt1"]),
+            &sha256(&[b"salt2"]),
+            &check.a,
+            &pad(&g_b),
+            &k_b,
+        ]);
+
+        assert_eq!(check.m1, expected);
+    }
+
+    #[test]
+    fn check_wrong_password_mismatches() {
+        let p = get_test_prime();
+        let algo = PasswordAlgo {
+            salt1: b"salt1",
+            salt2: b"salt2",
+            g: 3,
+            p: &p,
+        };
+        let g_b = pad(&BigUint::from(12345u32));
+        let random = [0x24; SIZE];
+
+        let right = do_check_password(&algo, &g_b, b"hunter2", &random).unwrap();
+        let wrong = do_check_password(&algo, &g_b, b"hunter3", &random).unwrap();
+        assert_eq!(right.a, wrong.a);
+        assert_ne!(right.m1, wrong.m1);
+    }
+
+    #[test]
+    fn check_g_b_out_of_range() {
+        let p = get_test_prime();
+        let algo = PasswordAlgo {
+            salt1: b"salt1",
+            salt2: b"salt2",
+            g: 3,
+            p: &p,
+        };
+
+        assert!(do_check_password(&algo, &[1], b"hunter2", &[0x24; SIZE]).is_err());
+        assert!(do_check_password(&algo, &p, b"hunter2", &[0x24; SIZE]).is_err());
+    }
+}
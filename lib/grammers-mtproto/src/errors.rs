@@ -12,6 +12,7 @@ use std::error::Error;
 use std::fmt;
 use std::io;
 
+pub use grammers_crypto::DecryptionError;
 use grammers_tl_types as tl;
 
 /// The error type for enqueueing requests.
@@ -23,6 +24,10 @@ pub enum EnqueueError {
 
     /// Well-formed data must be padded to 4 bytes.
     IncorrectPadding,
+
+    /// The provided message ID is not divisible by 4, or is not greater
+    /// than the last one used. Telegram would reject the message.
+    InvalidMsgId { got: i64, last: i64 },
 }
 
 impl Error for EnqueueError {}
@@ -32,6 +37,11 @@ impl fmt::Display for EnqueueError {
         match *self {
             Self::PayloadTooLarge => write!(f, "the payload is too large and cannot be sent"),
             Self::IncorrectPadding => write!(f, "the data is not padded correctly"),
+            Self::InvalidMsgId { got, last } => write!(
+                f,
+                "the message id {} is not valid after the last one, {}",
+                got, last
+            ),
         }
     }
 }
@@ -25,12 +25,17 @@ use std::io::{self, Write};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use getrandom::getrandom;
-use grammers_crypto::{decrypt_data_v2, encrypt_data_v2, AuthKey};
+pub use grammers_crypto::AuthKey;
+use grammers_crypto::{decrypt_data_v2, encrypt_data_v2};
 use grammers_tl_types::{self as tl, Deserializable, Identifiable, Serializable};
 
 /// The default compression threshold to be used.
 pub const DEFAULT_COMPRESSION_THRESHOLD: Option<usize> = Some(512);
 
+/// How many of the last received message identifiers are remembered, to
+/// tell whether an answer the server says it sent has been received.
+const RECEIVED_MSG_IDS_LIMIT: usize = 200;
+
 /// A builder to configure [`MTProto`] instances.
 ///
 /// Use the [`MTProto::build`] method to create builder instances.
@@ -78,6 +83,9 @@ pub struct MTProto {
     /// The time offset from the server's time, in seconds.
     time_offset: i32,
 
+    /// Whether the time offset has been learnt from a server message yet.
+    time_offset_synced: bool,
+
     /// The current salt to be used when encrypting payload.
     salt: i64,
 
@@ -96,6 +104,10 @@ pub struct MTProto {
     /// Identifiers that need to be acknowledged to the server.
     pending_ack: Vec<i64>,
 
+    /// Identifiers of the last messages received from the server, oldest
+    /// first.
+    received_msg_ids: VecDeque<i64>,
+
     /// If present, the threshold in bytes at which a message will be
     /// considered large enough to attempt compressing it. Otherwise,
     /// outgoing messages will never be compressed.
@@ -103,6 +115,10 @@ pub struct MTProto {
 
     /// A queue of responses ready to be used.
     response_queue: VecDeque<(MsgId, Result<Vec<u8>, RequestError>)>,
+
+    /// Identifiers of the `rpc_drop_answer` requests whose result has not
+    /// been received yet.
+    pending_drops: Vec<i64>,
 }
 
 /// A Message Identifier.
@@ -168,14 +184,17 @@ impl MTProto {
         Self {
             auth_key: None,
             time_offset: 0,
+            time_offset_synced: false,
             salt: 0,
             client_id,
             sequence: 0,
             last_msg_id: 0,
             message_queue: VecDeque::new(),
             pending_ack: vec![],
+            received_msg_ids: VecDeque::new(),
             compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
             response_queue: VecDeque::new(),
+            pending_drops: vec![],
         }
     }
 
@@ -201,6 +220,22 @@ impl MTProto {
         self.time_offset = time_offset;
     }
 
+    /// Returns the current time offset from the server's time, in seconds.
+    ///
+    /// The offset is learnt from the first message the server sends, and
+    /// corrected whenever it reports our message identifiers are wrong.
+    pub fn time_offset(&self) -> i32 {
+        self.time_offset
+    }
+
+    /// Changes the current time offset from the server's time, in seconds.
+    ///
+    /// This is useful to restore a previously-known offset, so that the
+    /// first messages sent after reconnecting have a correct identifier.
+    pub fn set_time_offset(&mut self, time_offset: i32) {
+        self.time_offset = time_offset;
+    }
+
     /// Correct our time offset based on a known valid message ID.
     fn correct_time_offset(&mut self, msg_id: i64) {
         let now = SystemTime::now()
@@ -325,7 +360,44 @@ impl MTProto {
     /// been sent yet, the message ID will become stale and Telegram will
     /// reject it. The caller is then expected to re-enqueue their request
     /// and send a new encrypted message.
-    pub fn enqueue_request(&mut self, mut body: Vec<u8>) -> Result<MsgId, EnqueueError> {
+    pub fn enqueue_request(&mut self, body: Vec<u8>) -> Result<MsgId, EnqueueError> {
+        let body = self.prepare_request_body(body)?;
+        Ok(self.enqueue_body(body, true))
+    }
+
+    /// Like [`enqueue_request`], but uses the given `msg_id` instead of
+    /// generating a new one, which is useful to reproduce the exact same
+    /// messages (for example, when testing or replaying a session).
+    ///
+    /// The `msg_id` must be divisible by 4 and greater than the last one
+    /// used, since Telegram rejects messages that don't follow these rules.
+    ///
+    /// [`enqueue_request`]: #method.enqueue_request
+    pub fn enqueue_request_with_msg_id(
+        &mut self,
+        body: Vec<u8>,
+        msg_id: i64,
+    ) -> Result<MsgId, EnqueueError> {
+        if msg_id % 4 != 0 || msg_id <= self.last_msg_id {
+            return Err(EnqueueError::InvalidMsgId {
+                got: msg_id,
+                last: self.last_msg_id,
+            });
+        }
+
+        let body = self.prepare_request_body(body)?;
+        self.last_msg_id = msg_id;
+        let seq_no = self.get_seq_no(true);
+        self.message_queue.push_back(manual_tl::Message {
+            msg_id,
+            seq_no,
+            body,
+        });
+        Ok(MsgId(msg_id))
+    }
+
+    /// Validates the body of a request, compressing it if it's worth it.
+    fn prepare_request_body(&self, mut body: Vec<u8>) -> Result<Vec<u8>, EnqueueError> {
         if body.len() + manual_tl::Message::SIZE_OVERHEAD
             > manual_tl::MessageContainer::MAXIMUM_SIZE
         {
@@ -346,7 +418,26 @@ impl MTProto {
             }
         }
 
-        Ok(self.enqueue_body(body, true))
+        Ok(body)
+    }
+
+    /// Enqueues a request asking the server to not send the response to a
+    /// previously-sent request, such as one that is no longer awaited.
+    ///
+    /// The server replies to it with `rpc_answer_dropped` (or one of its
+    /// variants), which is handled internally and never returned by
+    /// [`poll_response`]. Should the response to the original request arrive
+    /// anyway, it will be returned as usual.
+    ///
+    /// [`poll_response`]: #method.poll_response
+    pub fn drop_answer(&mut self, msg_id: MsgId) -> MsgId {
+        let body = tl::functions::RpcDropAnswer {
+            req_msg_id: msg_id.0,
+        }
+        .to_bytes();
+        let drop_msg_id = self.enqueue_body(body, true);
+        self.pending_drops.push(drop_msg_id.0);
+        drop_msg_id
     }
 
     fn enqueue_body(&mut self, body: Vec<u8>, content_related: bool) -> MsgId {
@@ -486,6 +577,17 @@ impl MTProto {
 
         utils::check_message_buffer(ciphertext)?;
 
+        let auth_key_id = i64::deserialize(&mut io::Cursor::new(ciphertext))?;
+        if auth_key_id != auth_key.key_id() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DeserializeError::BadAuthKey {
+                    got: auth_key_id,
+                    expected: auth_key.key_id(),
+                },
+            ));
+        }
+
         let plaintext = decrypt_data_v2(ciphertext, auth_key)?;
         let mut buffer = io::Cursor::new(plaintext);
 
@@ -510,6 +612,17 @@ impl MTProto {
 
     fn process_message(&mut self, message: manual_tl::Message) -> io::Result<()> {
         self.pending_ack.push(message.msg_id);
+        if self.received_msg_ids.len() == RECEIVED_MSG_IDS_LIMIT {
+            self.received_msg_ids.pop_front();
+        }
+        self.received_msg_ids.push_back(message.msg_id);
+
+        // The identifiers of server messages contain the server's time,
+        // so the first one can be used to align our own identifiers.
+        if !self.time_offset_synced {
+            self.time_offset_synced = true;
+            self.correct_time_offset(message.msg_id);
+        }
 
         // Determine what to do based on the inner body's constructor
         match message.constructor_id()? {
@@ -545,13 +658,18 @@ impl MTProto {
         let manual_tl::RpcResult { req_msg_id, result } = rpc_result;
         let msg_id = MsgId(req_msg_id);
 
+        // The result of `rpc_drop_answer` is only useful to know whether the
+        // answer was dropped, which doesn't change what we have to do, so
+        // it's ignored whatever it is (even an error).
+        if self.take_pending_drop(req_msg_id) {
+            return Ok(());
+        }
+
         match inner_constructor {
             tl::types::RpcError::CONSTRUCTOR_ID => {
-                match tl::enums::RpcError::from_bytes(&result)? {
-                    tl::enums::RpcError::RpcError(error) => self
-                        .response_queue
-                        .push_back((msg_id, Err(RequestError::RPCError(error.into())))),
-                }
+                let error = tl::types::RpcError::from(tl::enums::RpcError::from_bytes(&result)?);
+                self.response_queue
+                    .push_back((msg_id, Err(RequestError::RPCError(error.into()))));
             }
             manual_tl::GzipPacked::CONSTRUCTOR_ID => {
                 // Telegram shouldn't send compressed errors (the overhead
@@ -570,6 +688,18 @@ impl MTProto {
         Ok(())
     }
 
+    /// Stops waiting for the result of the `rpc_drop_answer` request sent
+    /// with the given identifier, returning `true` if it was being awaited.
+    fn take_pending_drop(&mut self, msg_id: i64) -> bool {
+        match self.pending_drops.iter().position(|id| *id == msg_id) {
+            Some(pos) => {
+                self.pending_drops.swap_remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Processes the inner messages of a container with many of them:
     ///
     /// ```tl
@@ -608,7 +738,7 @@ impl MTProto {
     /// pong#347773c5 msg_id:long ping_id:long = Pong;
     /// ```
     fn handle_pong(&mut self, message: &manual_tl::Message) -> io::Result<()> {
-        let tl::enums::Pong::Pong(pong) = tl::enums::Pong::from_bytes(&message.body)?;
+        let pong = tl::types::Pong::from(tl::enums::Pong::from_bytes(&message.body)?);
 
         self.response_queue
             .push_back((MsgId(pong.msg_id), Ok(message.body.clone())));
@@ -635,6 +765,8 @@ impl MTProto {
         let bad_msg = match bad_msg {
             tl::enums::BadMsgNotification::BadMsgNotification(x) => x,
             tl::enums::BadMsgNotification::BadServerSalt(x) => {
+                // Rejected drop requests are not sent again.
+                self.take_pending_drop(x.bad_msg_id);
                 self.response_queue.push_back((
                     MsgId(x.bad_msg_id),
                     Err(RequestError::BadMessage { code: x.error_code }),
@@ -642,8 +774,11 @@ impl MTProto {
                 self.salt = x.new_server_salt;
                 return Ok(());
             }
+            #[allow(unreachable_patterns)]
+            _ => return Ok(()),
         };
 
+        self.take_pending_drop(bad_msg.bad_msg_id);
         self.response_queue.push_back((
             MsgId(bad_msg.bad_msg_id),
             Err(RequestError::BadMessage {
@@ -688,17 +823,29 @@ impl MTProto {
     /// msg_new_detailed_info#809db6df answer_msg_id:long
     /// bytes:int status:int = MsgDetailedInfo;
     /// ```
+    ///
+    /// The server sends these to tell about an answer it sent, which only
+    /// has to be acknowledged if it was received. Otherwise, the answer may
+    /// have been lost, so it's requested again instead. Either way, the
+    /// server won't need to tell about the answer anymore.
     fn handle_detailed_info(&mut self, message: &manual_tl::Message) -> io::Result<()> {
-        // TODO https://github.com/telegramdesktop/tdesktop/blob/8f82880b938e06b7a2a27685ef9301edb12b4648/Telegram/SourceFiles/mtproto/connection.cpp#L1790-L1820
-        // TODO https://github.com/telegramdesktop/tdesktop/blob/8f82880b938e06b7a2a27685ef9301edb12b4648/Telegram/SourceFiles/mtproto/connection.cpp#L1822-L1845
-        let msg_detailed = tl::enums::MsgDetailedInfo::from_bytes(&message.body)?;
-        match msg_detailed {
-            tl::enums::MsgDetailedInfo::MsgDetailedInfo(x) => {
-                self.pending_ack.push(x.answer_msg_id);
-            }
-            tl::enums::MsgDetailedInfo::MsgNewDetailedInfo(x) => {
-                self.pending_ack.push(x.answer_msg_id);
-            }
+        let answer_msg_id = match tl::enums::MsgDetailedInfo::from_bytes(&message.body)? {
+            tl::enums::MsgDetailedInfo::MsgDetailedInfo(x) => x.answer_msg_id,
+            tl::enums::MsgDetailedInfo::MsgNewDetailedInfo(x) => x.answer_msg_id,
+            #[allow(unreachable_patterns)]
+            _ => return Ok(()),
+        };
+
+        if self.received_msg_ids.contains(&answer_msg_id) {
+            self.pending_ack.push(answer_msg_id);
+        } else {
+            self.enqueue_body(
+                tl::enums::MsgResendReq::MsgResendReq(tl::types::MsgResendReq {
+                    msg_ids: vec![answer_msg_id],
+                })
+                .to_bytes(),
+                false,
+            );
         }
         Ok(())
     }
@@ -710,12 +857,9 @@ impl MTProto {
     /// server_salt:long = NewSession;
     /// ```
     fn handle_new_session_created(&mut self, message: &manual_tl::Message) -> io::Result<()> {
-        let new_session = tl::enums::NewSession::from_bytes(&message.body)?;
-        match new_session {
-            tl::enums::NewSession::NewSessionCreated(x) => {
-                self.salt = x.server_salt;
-            }
-        }
+        let new_session =
+            tl::types::NewSessionCreated::from(tl::enums::NewSession::from_bytes(&message.body)?);
+        self.salt = new_session.server_salt;
         Ok(())
     }
 
@@ -752,8 +896,8 @@ impl MTProto {
     /// salts:vector<future_salt> = FutureSalts;
     /// ```
     fn handle_future_salts(&mut self, message: &manual_tl::Message) -> io::Result<()> {
-        let tl::enums::FutureSalts::FutureSalts(salts) =
-            tl::enums::FutureSalts::from_bytes(&message.body)?;
+        let salts =
+            tl::types::FutureSalts::from(tl::enums::FutureSalts::from_bytes(&message.body)?);
 
         self.response_queue
             .push_back((MsgId(salts.req_msg_id), Ok(message.body.clone())));
@@ -1016,4 +1160,192 @@ mod tests {
             assert!(buffer.windows(4).any(|w| w == GZIP_PACKED_HEADER));
         }
     }
+
+    #[test]
+    fn ensure_provided_msg_id_is_used_and_validated() {
+        let mut mtproto = MTProto::build().compression_threshold(None).finish();
+        let body = vec![b'H', b'e', b'y', b'!'];
+
+        let msg_id = mtproto
+            .enqueue_request_with_msg_id(body.clone(), 1 << 32)
+            .unwrap();
+        assert_eq!(msg_id, MsgId(1 << 32));
+        let buffer = mtproto.pop_queued_messages().unwrap();
+        // buffer[0..16] is the salt and client_id.
+        assert_eq!(buffer[16..24], (1i64 << 32).to_le_bytes());
+
+        // Not greater than the last one.
+        assert!(matches!(
+            mtproto.enqueue_request_with_msg_id(body.clone(), 1 << 32),
+            Err(EnqueueError::InvalidMsgId { .. })
+        ));
+        // Not divisible by 4.
+        assert!(matches!(
+            mtproto.enqueue_request_with_msg_id(body.clone(), (1 << 32) + 2),
+            Err(EnqueueError::InvalidMsgId { .. })
+        ));
+
+        // Generated IDs continue after the provided one.
+        let msg_id = mtproto.enqueue_request(body).unwrap();
+        assert!(msg_id.0 > 1 << 32);
+    }
+
+    #[test]
+    fn ensure_drop_answer_result_is_consumed() {
+        let mut mtproto = MTProto::build().compression_threshold(None).finish();
+
+        let msg_id = mtproto
+            .enqueue_request(vec![b'H', b'e', b'y', b'!'])
+            .unwrap();
+        let drop_msg_id = mtproto.drop_answer(msg_id);
+        let buffer = mtproto.pop_queued_messages().unwrap();
+        let drop_body = tl::functions::RpcDropAnswer {
+            req_msg_id: msg_id.0,
+        }
+        .to_bytes();
+        assert!(buffer.windows(drop_body.len()).any(|w| w == &drop_body[..]));
+
+        let rpc_result = |req_msg_id: i64, result: Vec<u8>| {
+            let mut body = manual_tl::RpcResult::CONSTRUCTOR_ID.to_bytes();
+            body.extend(req_msg_id.to_bytes());
+            body.extend(result);
+            manual_tl::Message {
+                msg_id: 1,
+                seq_no: 1,
+                body,
+            }
+        };
+
+        let dropped = tl::enums::RpcDropAnswer::RpcAnswerDropped(tl::types::RpcAnswerDropped {
+            msg_id: msg_id.0,
+            seq_no: 1,
+            bytes: 4,
+        });
+        mtproto
+            .process_message(rpc_result(drop_msg_id.0, dropped.to_bytes()))
+            .unwrap();
+        assert!(mtproto.poll_response().is_none());
+        assert!(mtproto.pending_drops.is_empty());
+
+        // Other results are still returned, even to the dropped request.
+        mtproto
+            .process_message(rpc_result(msg_id.0, vec![b'B', b'y', b'e', b'!']))
+            .unwrap();
+        match mtproto.poll_response() {
+            Some((id, Ok(data))) => {
+                assert_eq!(id, msg_id);
+                assert_eq!(data, b"Bye!");
+            }
+            _ => panic!("the response to the dropped request was not returned"),
+        }
+    }
+
+    #[test]
+    fn ensure_drop_answer_error_is_consumed() {
+        let mut mtproto = MTProto::build().compression_threshold(None).finish();
+
+        let msg_id = mtproto
+            .enqueue_request(vec![b'H', b'e', b'y', b'!'])
+            .unwrap();
+        let drop_msg_id = mtproto.drop_answer(msg_id);
+
+        let error = tl::enums::RpcError::RpcError(tl::types::RpcError {
+            error_code: 400,
+            error_message: "MSG_ID_INVALID".into(),
+        });
+        let mut body = manual_tl::RpcResult::CONSTRUCTOR_ID.to_bytes();
+        body.extend(drop_msg_id.0.to_bytes());
+        body.extend(error.to_bytes());
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 1,
+                seq_no: 1,
+                body,
+            })
+            .unwrap();
+        assert!(mtproto.poll_response().is_none());
+        assert!(mtproto.pending_drops.is_empty());
+    }
+
+    #[test]
+    fn ensure_detailed_info_does_not_derail_responses() {
+        let mut mtproto = MTProto::build().compression_threshold(None).finish();
+        let msg_id = mtproto
+            .enqueue_request(vec![b'H', b'e', b'y', b'!'])
+            .unwrap();
+        mtproto.pop_queued_messages().unwrap();
+
+        let mut body = manual_tl::RpcResult::CONSTRUCTOR_ID.to_bytes();
+        body.extend(msg_id.0.to_bytes());
+        body.extend(b"Bye!");
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 100,
+                seq_no: 1,
+                body,
+            })
+            .unwrap();
+
+        // The answer was received, so it's only acknowledged.
+        let detailed_info = |answer_msg_id| manual_tl::Message {
+            msg_id: 200,
+            seq_no: 2,
+            body: tl::enums::MsgDetailedInfo::MsgDetailedInfo(tl::types::MsgDetailedInfo {
+                msg_id: msg_id.0,
+                answer_msg_id,
+                bytes: 4,
+                status: 0,
+            })
+            .to_bytes(),
+        };
+        mtproto.process_message(detailed_info(100)).unwrap();
+        assert!(mtproto.pending_ack.contains(&100));
+        assert!(mtproto.message_queue.is_empty());
+
+        match mtproto.poll_response() {
+            Some((id, Ok(response))) => {
+                assert_eq!(id, msg_id);
+                assert_eq!(response, b"Bye!");
+            }
+            _ => panic!("the response should have been returned"),
+        }
+        assert!(mtproto.poll_response().is_none());
+
+        // The answer was not received, so it's requested again.
+        mtproto.process_message(detailed_info(300)).unwrap();
+        assert!(!mtproto.pending_ack.contains(&300));
+        let resend = mtproto.message_queue.pop_back().unwrap();
+        assert_eq!(
+            resend.body,
+            tl::enums::MsgResendReq::MsgResendReq(tl::types::MsgResendReq { msg_ids: vec![300] })
+                .to_bytes()
+        );
+        assert!(mtproto.poll_response().is_none());
+    }
+
+    #[test]
+    fn ensure_time_offset_is_learnt_from_first_message() {
+        let mut mtproto = MTProto::new();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let pong = |msg_id: i64| manual_tl::Message {
+            msg_id,
+            seq_no: 0,
+            body: tl::enums::Pong::Pong(tl::types::Pong {
+                msg_id: 0,
+                ping_id: 0,
+            })
+            .to_bytes(),
+        };
+
+        mtproto.process_message(pong((now + 100) << 32)).unwrap();
+        assert!((99..=100).contains(&mtproto.time_offset()));
+
+        // Only the first message should be used to learn the offset.
+        mtproto.process_message(pong((now - 100) << 32)).unwrap();
+        assert!((99..=100).contains(&mtproto.time_offset()));
+    }
 }
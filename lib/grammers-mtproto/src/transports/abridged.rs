@@ -45,7 +45,11 @@ impl Transport for TransportAbridged {
         Ok(())
     }
 
-    fn receive_into<R: Read>(&mut self, channel: &mut R, buffer: &mut Vec<u8>) -> Result<()> {
+    fn receive_into<C: Read + Write>(
+        &mut self,
+        channel: &mut C,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
         let len = {
             let mut buf = [0; 1];
             channel.read_exact(&mut buf)?;
@@ -12,10 +12,12 @@
 mod abridged;
 mod full;
 mod intermediate;
+mod websocket;
 
 pub use abridged::TransportAbridged;
 pub use full::TransportFull;
 pub use intermediate::TransportIntermediate;
+pub use websocket::TransportWebSocket;
 
 use std::error::Error;
 use std::fmt;
@@ -66,11 +68,18 @@ pub trait Transport {
     fn send<W: Write>(&mut self, channel: &mut W, payload: &[u8]) -> Result<()>;
 
     /// Receive a packet into an existing buffer.
-    fn receive_into<R: Read>(&mut self, channel: &mut R, buffer: &mut Vec<u8>) -> Result<()>;
+    ///
+    /// The channel is also written to by transports which must answer
+    /// control messages while receiving, such as the WebSocket pings.
+    fn receive_into<C: Read + Write>(
+        &mut self,
+        channel: &mut C,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()>;
 
     /// Create a new buffer to hold an incoming message,
     /// and then receive one into it.
-    fn receive<R: Read>(&mut self, channel: &mut R) -> Result<Vec<u8>> {
+    fn receive<C: Read + Write>(&mut self, channel: &mut C) -> Result<Vec<u8>> {
         let mut buffer = Vec::new();
         match self.receive_into(channel, &mut buffer) {
             Ok(_) => Ok(buffer),
@@ -0,0 +1,463 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::transports::{LengthTooLong, Transport};
+use getrandom::getrandom;
+use grammers_crypto::{base64, AesCtr};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// The path of the WebSocket endpoint in Telegram's web servers.
+const PATH: &str = "/apiws";
+
+/// The tag identifying the intermediate transport in the obfuscated header.
+const INTERMEDIATE_TAG: [u8; 4] = [0xee, 0xee, 0xee, 0xee];
+
+/// The GUID used to compute the `Sec-WebSocket-Accept` value, as defined
+/// by [RFC 6455].
+///
+/// [RFC 6455]: https://tools.ietf.org/html/rfc6455#section-1.3
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC11B65";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// The MTProto transport used by web clients, which is the only one
+/// available where raw TCP connections can't be made. This is an
+/// implementation of the [obfuscated] [intermediate transport], with every
+/// chunk of data sent inside a binary [WebSocket] frame.
+///
+/// The stream must be connected to one of the `*.web.telegram.org` servers
+/// (normally through TLS, using port 443), and [`handshake`] must be called
+/// on it before sending any data.
+///
+/// * Overhead: medium.
+/// * Minimum envelope length: 10 bytes.
+/// * Maximum envelope length: 22 bytes.
+///
+/// [obfuscated]: https://core.telegram.org/mtproto/mtproto-transports#transport-obfuscation
+/// [intermediate transport]: https://core.telegram.org/mtproto/mtproto-transports#intermediate
+/// [WebSocket]: https://tools.ietf.org/html/rfc6455
+/// [`handshake`]: #method.handshake
+#[derive(Default)]
+pub struct TransportWebSocket {
+    /// The ciphers used to encrypt outgoing and decrypt incoming data, set
+    /// once the obfuscated header has been sent.
+    ciphers: Option<(AesCtr, AesCtr)>,
+    /// Data received and decrypted which is not part of a packet yet.
+    incoming: Vec<u8>,
+}
+
+/// Generates the 64 bytes which start the obfuscated transport, making sure
+/// they can't be mistaken for the start of a different protocol.
+fn generate_init() -> Result<[u8; 64]> {
+    let mut init = [0; 64];
+    loop {
+        getrandom(&mut init).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "failed to generate a secure random header",
+            )
+        })?;
+        if init[0] != 0xef
+            && !matches!(
+                &init[..4],
+                b"HEAD" | b"POST" | b"GET " | b"OPTI" | [0xdd, 0xdd, 0xdd, 0xdd]
+            )
+            && init[..4] != INTERMEDIATE_TAG
+            && init[..4] != [0x16, 0x03, 0x01, 0x02]
+            && init[4..8] != [0; 4]
+        {
+            break;
+        }
+    }
+    init[56..60].copy_from_slice(&INTERMEDIATE_TAG);
+    Ok(init)
+}
+
+/// Returns the `(encrypt, decrypt)` ciphers defined by the header.
+fn ciphers_from_init(init: &[u8; 64]) -> (AesCtr, AesCtr) {
+    let mut reversed = [0; 48];
+    reversed.copy_from_slice(&init[8..56]);
+    reversed.reverse();
+
+    let cipher = |data: &[u8]| {
+        let mut key = [0; 32];
+        let mut iv = [0; 16];
+        key.copy_from_slice(&data[..32]);
+        iv.copy_from_slice(&data[32..48]);
+        AesCtr::new(&key, &iv)
+    };
+    (cipher(&init[8..56]), cipher(&reversed))
+}
+
+/// Writes the data in a single, masked frame with the given opcode.
+fn write_frame<W: Write>(channel: &mut W, opcode: u8, mut data: Vec<u8>) -> Result<()> {
+    let mut mask = [0; 4];
+    getrandom(&mut mask)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "failed to generate a random mask"))?;
+
+    let mut header = Vec::with_capacity(14);
+    header.push(0x80 | opcode);
+    match data.len() {
+        len if len < 126 => header.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            header.push(0x80 | 126);
+            header.extend(&(len as u16).to_be_bytes());
+        }
+        len => {
+            header.push(0x80 | 127);
+            header.extend(&(len as u64).to_be_bytes());
+        }
+    }
+    header.extend(&mask);
+
+    data.iter_mut()
+        .zip(mask.iter().cycle())
+        .for_each(|(b, m)| *b ^= m);
+
+    channel.write_all(&header)?;
+    channel.write_all(&data)
+}
+
+/// Reads a single frame, returning its opcode and unmasked payload.
+fn read_frame<R: Read>(channel: &mut R) -> Result<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    channel.read_exact(&mut header)?;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            channel.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            channel.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > TransportWebSocket::MAXIMUM_DATA as u64 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            LengthTooLong { len: len as u32 },
+        ));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        channel.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    channel.read_exact(&mut payload)?;
+    if masked {
+        payload
+            .iter_mut()
+            .zip(mask.iter().cycle())
+            .for_each(|(b, m)| *b ^= m);
+    }
+    Ok((opcode, payload))
+}
+
+impl TransportWebSocket {
+    /// Creates a new instance of a `TransportWebSocket`.
+    pub fn new() -> Self {
+        Self {
+            ciphers: None,
+            incoming: Vec::new(),
+        }
+    }
+
+    /// Upgrades the connection to the given host (such as
+    /// `venus.web.telegram.org`) to the WebSocket protocol.
+    ///
+    /// This must be done before the transport is used over the stream.
+    pub fn handshake<S: Read + Write>(stream: &mut S, host: &str) -> Result<()> {
+        let mut nonce = [0; 16];
+        getrandom(&mut nonce)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "failed to generate a random key"))?;
+        let key = base64::encode(&nonce);
+
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\n\
+             Sec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Protocol: binary\r\n\
+             \r\n",
+            PATH, host, key
+        )?;
+        stream.flush()?;
+
+        // The response has no body, so read up to the empty line.
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte)?;
+            response.push(byte[0]);
+            if response.len() > 8 * 1024 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "the websocket handshake response is too long",
+                ));
+            }
+        }
+        let response = String::from_utf8_lossy(&response);
+
+        let mut lines = response.split("\r\n");
+        let status = lines.next().unwrap_or_default();
+        if status.split(' ').nth(1) != Some("101") {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("the websocket handshake failed: {}", status),
+            ));
+        }
+
+        let expected = base64::encode(&sha1::Sha1::from(key + WEBSOCKET_GUID).digest().bytes());
+        let accepted = lines.any(|line| {
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next().unwrap_or_default().trim();
+            let value = parts.next().unwrap_or_default().trim();
+            name.eq_ignore_ascii_case("sec-websocket-accept") && value == expected
+        });
+        if !accepted {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the websocket handshake response is not valid",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Transport for TransportWebSocket {
+    fn send<W: Write>(&mut self, channel: &mut W, payload: &[u8]) -> Result<()> {
+        let mut data = Vec::with_capacity(64 + 4 + payload.len());
+        if self.ciphers.is_none() {
+            // The header goes in the clear, except for the protocol tag and
+            // the bytes after it, which are replaced by their encryption.
+            let init = generate_init()?;
+            let mut ciphers = ciphers_from_init(&init);
+            let mut encrypted = init;
+            ciphers.0.apply(&mut encrypted);
+
+            data.extend(&init[..56]);
+            data.extend(&encrypted[56..]);
+            self.ciphers = Some(ciphers);
+        }
+
+        let start = data.len();
+        data.extend(&(payload.len() as u32).to_le_bytes());
+        data.extend(payload);
+        if let Some((encrypt, _)) = self.ciphers.as_mut() {
+            encrypt.apply(&mut data[start..]);
+        }
+
+        write_frame(channel, OPCODE_BINARY, data)
+    }
+
+    fn receive_into<C: Read + Write>(
+        &mut self,
+        channel: &mut C,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
+        loop {
+            if self.incoming.len() >= 4 {
+                let mut len = [0; 4];
+                len.copy_from_slice(&self.incoming[..4]);
+                let len = u32::from_le_bytes(len);
+                if len > Self::MAXIMUM_DATA {
+                    return Err(Error::new(ErrorKind::InvalidInput, LengthTooLong { len }));
+                }
+
+                let end = 4 + len as usize;
+                if self.incoming.len() >= end {
+                    buffer.clear();
+                    buffer.extend(&self.incoming[4..end]);
+                    self.incoming.drain(..end);
+                    return Ok(());
+                }
+            }
+
+            let (opcode, mut payload) = read_frame(channel)?;
+            match opcode {
+                OPCODE_BINARY | OPCODE_CONTINUATION => {
+                    let decrypt = match self.ciphers.as_mut() {
+                        Some((_, decrypt)) => decrypt,
+                        None => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "received data before sending any",
+                            ))
+                        }
+                    };
+                    decrypt.apply(&mut payload);
+                    self.incoming.extend(payload);
+                }
+                OPCODE_CLOSE => {
+                    return Err(Error::new(
+                        ErrorKind::ConnectionAborted,
+                        "the server closed the websocket",
+                    ))
+                }
+                OPCODE_PING => {
+                    // The pong must carry the same data as the ping.
+                    write_frame(channel, OPCODE_PONG, payload)?;
+                    channel.flush()?;
+                }
+                OPCODE_PONG => {}
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unexpected websocket frame opcode: {:x}", opcode),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays the role of the server, reading the header and the first packet
+    /// sent by the client, and returns the cipher used to send responses.
+    fn accept(mut sent: &[u8]) -> (Vec<u8>, AesCtr) {
+        let (opcode, data) = read_frame(&mut sent).unwrap();
+        assert_eq!(opcode, OPCODE_BINARY);
+        assert!(sent.is_empty());
+
+        let mut init = [0; 64];
+        init.copy_from_slice(&data[..64]);
+        // From the server's point of view, the ciphers are swapped.
+        let (mut decrypt, encrypt) = ciphers_from_init(&init);
+
+        let mut data = data;
+        decrypt.apply(&mut data);
+        assert_eq!(&data[56..60], &INTERMEDIATE_TAG);
+        (data[64..].to_vec(), encrypt)
+    }
+
+    fn server_frame(opcode: u8, data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x80 | opcode, data.len() as u8];
+        frame.extend(data);
+        frame
+    }
+
+    /// The connection to the server, which reads the frames it sends and
+    /// collects the frames written to it.
+    struct Channel<'a> {
+        received: &'a [u8],
+        sent: Vec<u8>,
+    }
+
+    impl<'a> Channel<'a> {
+        fn new(received: &'a [u8]) -> Self {
+            Self {
+                received,
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Channel<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.received.read(buf)
+        }
+    }
+
+    impl Write for Channel<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn check_send() {
+        let mut transport = TransportWebSocket::new();
+        let mut sent = Vec::new();
+        transport.send(&mut sent, &[1, 2, 3, 4]).unwrap();
+
+        let (packet, _) = accept(&sent);
+        assert_eq!(packet, vec![4, 0, 0, 0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn check_receive_across_frames() {
+        let mut transport = TransportWebSocket::new();
+        let mut sent = Vec::new();
+        transport.send(&mut sent, &[0; 4]).unwrap();
+        let (_, mut encrypt) = accept(&sent);
+
+        let mut first = vec![8, 0, 0, 0, 1, 2, 3];
+        let mut second = vec![4, 5, 6, 7, 8, 4, 0, 0, 0, 9, 9, 9, 9];
+        encrypt.apply(&mut first);
+        encrypt.apply(&mut second);
+
+        let mut received = server_frame(OPCODE_BINARY, &first);
+        received.extend(server_frame(OPCODE_BINARY, &second));
+        let mut channel = Channel::new(&received);
+
+        let mut buffer = Vec::new();
+        transport.receive_into(&mut channel, &mut buffer).unwrap();
+        assert_eq!(buffer, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        transport.receive_into(&mut channel, &mut buffer).unwrap();
+        assert_eq!(buffer, vec![9, 9, 9, 9]);
+    }
+
+    #[test]
+    fn check_receive_answers_ping() {
+        let mut transport = TransportWebSocket::new();
+        let mut sent = Vec::new();
+        transport.send(&mut sent, &[0; 4]).unwrap();
+        let (_, mut encrypt) = accept(&sent);
+
+        let mut packet = vec![4, 0, 0, 0, 1, 2, 3, 4];
+        encrypt.apply(&mut packet);
+
+        let mut received = server_frame(OPCODE_PING, b"ping");
+        received.extend(server_frame(OPCODE_BINARY, &packet));
+        let mut channel = Channel::new(&received);
+
+        let mut buffer = Vec::new();
+        transport.receive_into(&mut channel, &mut buffer).unwrap();
+        assert_eq!(buffer, vec![1, 2, 3, 4]);
+
+        let mut pong = &channel.sent[..];
+        assert_eq!(
+            read_frame(&mut pong).unwrap(),
+            (OPCODE_PONG, b"ping".to_vec())
+        );
+        assert!(pong.is_empty());
+    }
+
+    #[test]
+    fn check_receive_close() {
+        let mut transport = TransportWebSocket::new();
+        let mut sent = Vec::new();
+        transport.send(&mut sent, &[0; 4]).unwrap();
+
+        let mut channel = Channel::new(&[0x80 | OPCODE_CLOSE, 0]);
+        let error = transport
+            .receive_into(&mut channel, &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionAborted);
+    }
+}
@@ -8,13 +8,14 @@
 use grammers_crypto::{auth_key, AuthKey};
 use grammers_mtproto::errors::{RPCError, RequestError};
 use grammers_mtproto::transports::{Transport, TransportFull};
-use grammers_mtproto::MTProto;
 pub use grammers_mtproto::DEFAULT_COMPRESSION_THRESHOLD;
+use grammers_mtproto::{MTProto, MsgId};
 use grammers_tl_types::{Deserializable, RPC};
+use log::{debug, trace};
 
-use std::io;
-use std::net::{TcpStream, ToSocketAddrs};
-use std::time::Duration;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant, SystemTime};
 
 pub const DEFAULT_TIMEOUT: Option<Duration> = Some(Duration::from_secs(10));
 
@@ -36,12 +37,77 @@ pub struct MTSenderBuilder {
 /// A Mobile Transport sender, using the [Mobile Transport Protocol]
 /// underneath.
 ///
+/// The sender normally communicates over a `TcpStream`, but any other
+/// stream may be used through [`from_stream`], such as an in-memory one to
+/// test code against recorded responses. Similarly, the [full transport] is
+/// used unless a different one is given to
+/// [`MTSenderBuilder::with_transport`].
+///
 /// [Mobile Transport Protocol]: https://core.telegram.org/mtproto
-pub struct MTSender {
+/// [`from_stream`]: #method.from_stream
+/// [full transport]: https://core.telegram.org/mtproto/mtproto-transports#full
+/// [`MTSenderBuilder::with_transport`]: struct.MTSenderBuilder.html#method.with_transport
+pub struct MTSender<S = TcpStream, T = TransportFull> {
     protocol: MTProto,
-    stream: TcpStream,
-    // TODO let the user change the type of transport used
-    transport: TransportFull,
+    stream: S,
+    /// The network timeout configured when building the sender.
+    timeout: Option<Duration>,
+    transport: T,
+    /// Packets waiting to be written to the stream in a single call by
+    /// `flush`, so that their envelopes don't cause separate writes.
+    write_buffer: Vec<u8>,
+    /// Whether a read timed out in the middle of a packet. The rest of it
+    /// is still due to arrive where the next packet would be expected, so
+    /// the stream can't be read from anymore.
+    desynchronized: bool,
+}
+
+/// Streams whose reads can be made to time out, which is needed to wait
+/// for a response for a limited time.
+pub trait ReadTimeout {
+    /// Sets the timeout for the reads made from now on, or removes it if
+    /// `None`. Reads which time out fail with an error of kind `TimedOut`
+    /// or `WouldBlock`.
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl ReadTimeout for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Returns `true` if the error is caused by a read timing out, which Unix
+/// reports as `WouldBlock`.
+fn is_read_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// A stream which remembers whether any data was read through it.
+struct TrackingReader<'a, R> {
+    inner: &'a mut R,
+    read_any: bool,
+}
+
+impl<R: Read> Read for TrackingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read_any |= n != 0;
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for TrackingReader<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 impl MTSenderBuilder {
@@ -79,6 +145,47 @@ impl MTSenderBuilder {
     pub fn connect<A: ToSocketAddrs>(self, addr: A) -> io::Result<MTSender> {
         MTSender::with_builder(self, addr)
     }
+
+    /// Finishes the builder and returns the `MTSender` instance, which will
+    /// send and receive the raw bytes of the transport through `stream`
+    /// instead of a network connection.
+    ///
+    /// The configured timeout is not applied to the stream.
+    pub fn with_stream<S: Read + Write>(self, stream: S) -> MTSender<S> {
+        self.with_transport(stream, TransportFull::new())
+    }
+
+    /// Like [`with_stream`], but packs the data sent through the stream
+    /// with the given transport. For example, [`TransportWebSocket`] must
+    /// be used over the streams connected to Telegram's web servers.
+    ///
+    /// [`with_stream`]: #method.with_stream
+    /// [`TransportWebSocket`]: ../grammers_mtproto/transports/struct.TransportWebSocket.html
+    pub fn with_transport<S: Read + Write, T: Transport>(
+        self,
+        stream: S,
+        transport: T,
+    ) -> MTSender<S, T> {
+        MTSender {
+            protocol: self.build_protocol(),
+            stream,
+            timeout: self.timeout,
+            transport,
+            write_buffer: Vec::new(),
+            desynchronized: false,
+        }
+    }
+
+    /// Creates the `MTProto` instance with the configuration applied.
+    fn build_protocol(&self) -> MTProto {
+        let mut protocol = MTProto::build().compression_threshold(self.compression_threshold);
+
+        if let Some(auth_key) = &self.auth_key {
+            protocol = protocol.auth_key(auth_key.clone());
+        }
+
+        protocol.finish()
+    }
 }
 
 impl MTSender {
@@ -95,26 +202,127 @@ impl MTSender {
     /// Constructs an instance using a finished builder.
     fn with_builder<A: ToSocketAddrs>(builder: MTSenderBuilder, addr: A) -> io::Result<Self> {
         let stream = TcpStream::connect(addr)?;
+        debug!("connected to {}", stream.peer_addr()?);
         stream.set_read_timeout(builder.timeout)?;
+        Ok(builder.with_stream(stream))
+    }
 
-        let mut protocol = MTProto::build().compression_threshold(builder.compression_threshold);
+    /// Returns the socket address of the server this sender is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Returns the local socket address this sender is connected from.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+}
 
-        if let Some(auth_key) = builder.auth_key {
-            protocol = protocol.auth_key(auth_key);
+impl<S: Read + Write + ReadTimeout, T: Transport> MTSender<S, T> {
+    /// Like [`invoke`], but only waits for the response to this request for
+    /// at most the given duration.
+    ///
+    /// The duration may be longer than the network timeout, for example, to
+    /// wait for requests which take long to complete, such as uploads. Reads
+    /// which time out while nothing has been received yet are retried until
+    /// the deadline is reached.
+    ///
+    /// If no response arrives in time, an error with kind
+    /// [`io::ErrorKind::TimedOut`] is returned. Should the response arrive
+    /// later, it will be discarded rather than mistaken for the response of
+    /// a different request. However, if the deadline is reached while a
+    /// packet is only partially received, the sender becomes
+    /// [desynchronized] and a new connection is needed.
+    ///
+    /// [`invoke`]: #method.invoke
+    /// [desynchronized]: #method.is_desynchronized
+    /// [`io::ErrorKind::TimedOut`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.TimedOut
+    pub fn invoke_with_timeout<R: RPC>(
+        &mut self,
+        request: &R,
+        timeout: Duration,
+    ) -> RequestResult<R::Return> {
+        let result = self.invoke_until(request, Instant::now() + timeout);
+        // The read timeout was changed to honor the deadline, so restore it.
+        self.stream.set_read_timeout(self.timeout)?;
+        result
+    }
+
+    /// Invokes a request, waiting for its response until the deadline.
+    ///
+    /// If the deadline is reached, the server is asked to drop the response
+    /// to the request, since it's no longer awaited.
+    fn invoke_until<R: RPC>(&mut self, request: &R, deadline: Instant) -> RequestResult<R::Return> {
+        let mut msg_id = self.enqueue(request)?;
+        let result = self.wait_until(request, &mut msg_id, deadline);
+        if let Err(ref e) = result {
+            if e.kind() == io::ErrorKind::TimedOut {
+                // The drop request is sent along with the next request.
+                debug!("dropping answer to {:?} after timing out", msg_id);
+                self.protocol.drop_answer(msg_id);
+            }
         }
+        result
+    }
 
-        Ok(Self {
-            protocol: protocol.finish(),
-            stream,
-            transport: TransportFull::new(),
-        })
+    /// Waits for the response to the request sent as `msg_id` until the
+    /// deadline.
+    fn wait_until<R: RPC>(
+        &mut self,
+        request: &R,
+        msg_id: &mut MsgId,
+        deadline: Instant,
+    ) -> RequestResult<R::Return> {
+        loop {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| *remaining > Duration::from_secs(0))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::TimedOut))?;
+
+            let read_timeout = match self.timeout {
+                Some(timeout) if timeout < remaining => timeout,
+                _ => remaining,
+            };
+            self.stream.set_read_timeout(Some(read_timeout))?;
+
+            match self.step(request, msg_id) {
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => {}
+                Err(e) if is_read_timeout(&e) => {
+                    // The rest of a partially-read packet can't be awaited.
+                    // Otherwise, nothing was read, and the response may
+                    // still arrive before the deadline.
+                    if self.desynchronized {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
+}
 
+impl<S: Read + Write> MTSender<S> {
+    /// Creates a new instance with default settings which sends and receives
+    /// the raw bytes of the transport through `stream`.
+    ///
+    /// This can be used to tunnel the connection (for example, through a
+    /// proxy or TLS). Use [`MTSenderBuilder::with_stream`] to configure the
+    /// instance.
+    ///
+    /// [`MTSenderBuilder::with_stream`]: struct.MTSenderBuilder.html#method.with_stream
+    pub fn from_stream(stream: S) -> Self {
+        MTSenderBuilder::new().with_stream(stream)
+    }
+}
+
+impl<S: Read + Write, T: Transport> MTSender<S, T> {
     /// Performs the handshake necessary to generate a new authorization
     /// key that can be used to safely transmit data to and from the server.
     ///
     /// See also: https://core.telegram.org/mtproto/auth_key.
     pub fn generate_auth_key(&mut self) -> io::Result<AuthKey> {
+        debug!("generating new authorization key");
         let (request, data) = auth_key::generation::step1()?;
         let response = self.invoke_plain_request(&request)?;
 
@@ -125,6 +333,7 @@ impl MTSender {
         let response = self.invoke_plain_request(&request)?;
 
         let (auth_key, time_offset) = auth_key::generation::create_key(data, response)?;
+        debug!("generated authorization key {}", auth_key.key_id());
         self.protocol.set_auth_key(auth_key.clone(), time_offset);
 
         Ok(auth_key)
@@ -132,14 +341,63 @@ impl MTSender {
 
     /// Changes the authorization key data for a different one.
     pub fn set_auth_key(&mut self, data: [u8; 256]) {
-        self.protocol.set_auth_key(AuthKey::from_bytes(data), 0);
+        let auth_key = AuthKey::from(data);
+        debug!("using authorization key {}", auth_key.key_id());
+        self.protocol.set_auth_key(auth_key, 0);
+    }
+
+    /// Returns the difference between the server's time and our local time,
+    /// in seconds.
+    ///
+    /// This offset is learnt from the messages the server sends, and may be
+    /// persisted to be restored later with [`set_time_offset`].
+    ///
+    /// [`set_time_offset`]: #method.set_time_offset
+    pub fn time_offset(&self) -> i32 {
+        self.protocol.time_offset()
+    }
+
+    /// Changes the difference between the server's time and our local time,
+    /// in seconds, so that the first messages sent are correctly aligned.
+    pub fn set_time_offset(&mut self, time_offset: i32) {
+        self.protocol.set_time_offset(time_offset);
+    }
+
+    /// Returns the current time according to the server, which is the local
+    /// time adjusted by the known [`time_offset`].
+    ///
+    /// This should be preferred over the local time when computing absolute
+    /// dates for the server, such as when scheduling messages.
+    ///
+    /// [`time_offset`]: #method.time_offset
+    pub fn server_time(&self) -> SystemTime {
+        let now = SystemTime::now();
+        let offset = self.time_offset();
+        if offset >= 0 {
+            now + Duration::from_secs(offset as u64)
+        } else {
+            now - Duration::from_secs(-(offset as i64) as u64)
+        }
+    }
+
+    /// Returns `true` if a read timed out in the middle of a packet (for
+    /// example, because the network timeout is too short for its size).
+    ///
+    /// The rest of the packet would be mistaken for the next one, so every
+    /// request made from then on fails with an error of kind
+    /// `ConnectionAborted`, and the sender should be replaced by one with a
+    /// new connection.
+    pub fn is_desynchronized(&self) -> bool {
+        self.desynchronized
     }
 
     /// Invoke a serialized request in plaintext.
     fn invoke_plain_request(&mut self, request: &[u8]) -> io::Result<Vec<u8>> {
+        self.check_synchronized()?;
         // Send
         let payload = self.protocol.serialize_plain_message(request);
-        self.transport.send(&mut self.stream, &payload)?;
+        self.transport.send(&mut self.write_buffer, &payload)?;
+        self.flush()?;
 
         // Receive
         let response = self.receive_message()?;
@@ -157,45 +415,147 @@ impl MTSender {
     /// request itself was understood by the server, but it could not be
     /// executed. This is represented by the innermost result.
     pub fn invoke<R: RPC>(&mut self, request: &R) -> RequestResult<R::Return> {
-        let mut msg_id = self.protocol.enqueue_request(request.to_bytes())?;
+        let mut msg_id = self.enqueue(request)?;
+        loop {
+            if let Some(result) = self.step(request, &mut msg_id)? {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Like [`invoke`], but the request is sent with the given message ID
+    /// instead of a generated one, so that the exact same traffic can be
+    /// reproduced (for example, in tests or when replaying a session).
+    ///
+    /// The message ID must be divisible by 4 and greater than any previous
+    /// one, or an error with kind `InvalidData` is returned. If the request
+    /// has to be sent again, a new message ID is generated for it.
+    ///
+    /// [`invoke`]: #method.invoke
+    pub fn invoke_with_msg_id<R: RPC>(
+        &mut self,
+        request: &R,
+        msg_id: i64,
+    ) -> RequestResult<R::Return> {
+        let body = request.to_bytes();
+        let name = request.constructor_name();
+        let mut msg_id = self.protocol.enqueue_request_with_msg_id(body, msg_id)?;
+        debug!("enqueued {} as {:?}", name, msg_id);
         loop {
-            // The protocol may generate more outgoing requests, so we need
-            // to constantly check for those until we receive a response.
-            while let Some(payload) = self.protocol.serialize_encrypted_messages()? {
-                self.transport.send(&mut self.stream, &payload)?;
+            if let Some(result) = self.step(request, &mut msg_id)? {
+                return Ok(result);
             }
+        }
+    }
 
-            // Process all messages we receive.
-            let response = self.receive_message()?;
-            self.protocol.process_encrypted_response(&response)?;
-
-            // See if there are responses to our request.
-            while let Some((response_id, data)) = self.protocol.poll_response() {
-                if response_id == msg_id {
-                    match data {
-                        Ok(x) => {
-                            return Ok(Ok(R::Return::from_bytes(&x)?));
-                        }
-                        Err(RequestError::RPCError(error)) => {
-                            return Ok(Err(error));
-                        }
-                        Err(RequestError::BadMessage { .. }) => {
-                            // Need to retransmit
-                            msg_id = self.protocol.enqueue_request(request.to_bytes())?;
-                        }
+    /// Serializes and enqueues a request, returning the message ID it will
+    /// be sent with.
+    fn enqueue<R: RPC>(&mut self, request: &R) -> io::Result<MsgId> {
+        let body = request.to_bytes();
+        let name = request.constructor_name();
+        let msg_id = self.protocol.enqueue_request(body)?;
+        debug!("enqueued {} as {:?}", name, msg_id);
+        Ok(msg_id)
+    }
+
+    /// Sends all pending messages, receives and processes a single message,
+    /// and returns the result of the request if its response was in it.
+    ///
+    /// If the request has to be sent again, `msg_id` is updated.
+    fn step<R: RPC>(
+        &mut self,
+        request: &R,
+        msg_id: &mut MsgId,
+    ) -> io::Result<Option<Result<R::Return, RPCError>>> {
+        self.check_synchronized()?;
+
+        // The protocol may generate more outgoing requests, so we need
+        // to constantly check for those until we receive a response.
+        while let Some(payload) = self.protocol.serialize_encrypted_messages()? {
+            trace!("sending {} bytes", payload.len());
+            self.transport.send(&mut self.write_buffer, &payload)?;
+        }
+        self.flush()?;
+
+        // Process all messages we receive.
+        let response = self.receive_message()?;
+        self.protocol.process_encrypted_response(&response)?;
+
+        // See if there are responses to our request. Responses to any
+        // other message (such as one which previously timed out) are
+        // no longer awaited and get dropped here.
+        while let Some((response_id, data)) = self.protocol.poll_response() {
+            if response_id == *msg_id {
+                match data {
+                    Ok(x) => {
+                        debug!("received response to {:?}", msg_id);
+                        return Ok(Some(Ok(R::Return::from_bytes(&x)?)));
+                    }
+                    Err(RequestError::RPCError(error)) => {
+                        debug!("received error to {:?}: {}", msg_id, error);
+                        return Ok(Some(Err(error)));
+                    }
+                    Err(RequestError::BadMessage { .. }) => {
+                        // Need to retransmit
+                        debug!("retransmitting request sent as {:?}", msg_id);
+                        *msg_id = self.enqueue(request)?;
                     }
                 }
+            } else {
+                debug!(
+                    "dropping response to {:?} which is not awaited",
+                    response_id
+                );
             }
         }
+
+        Ok(None)
+    }
+
+    /// Writes all the buffered packets to the stream at once, and flushes it
+    /// so that they're sent without delay.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.write_buffer.is_empty() {
+            let result = self.stream.write_all(&self.write_buffer);
+            // The packets can't be partially resent, so they're discarded
+            // even on error, and the requests will have to be sent again.
+            self.write_buffer.clear();
+            result?;
+        }
+        self.stream.flush()
+    }
+
+    /// Fails if the stream can't be read from anymore.
+    fn check_synchronized(&self) -> io::Result<()> {
+        if self.desynchronized {
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "a previous read timed out in the middle of a packet",
+            ))
+        } else {
+            Ok(())
+        }
     }
 
     /// Receives a single message from the server
     fn receive_message(&mut self) -> io::Result<Vec<u8>> {
-        self.transport
-            .receive(&mut self.stream)
-            .map_err(|e| match e.kind() {
-                io::ErrorKind::UnexpectedEof => io::Error::new(io::ErrorKind::ConnectionReset, e),
-                _ => e,
-            })
+        let mut reader = TrackingReader {
+            inner: &mut self.stream,
+            read_any: false,
+        };
+        let result = self.transport.receive(&mut reader);
+        let read_any = reader.read_any;
+
+        let message = result.map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(io::ErrorKind::ConnectionReset, e),
+            _ if read_any && is_read_timeout(&e) => {
+                debug!("read timed out in the middle of a packet");
+                self.desynchronized = true;
+                e
+            }
+            _ => e,
+        })?;
+        trace!("received {} bytes", message.len());
+        Ok(message)
     }
 }
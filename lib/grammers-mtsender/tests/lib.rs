@@ -10,10 +10,481 @@ pub const TELEGRAM_TEST_DC_2: &str = "149.154.167.40:443";
 /// The default datacenter to connect to for testing.
 pub const TELEGRAM_DEFAULT_TEST_DC: &str = TELEGRAM_TEST_DC_2;
 
-use grammers_mtsender::MTSender;
+use grammers_crypto::AuthKey;
+use grammers_mtproto::transports::{Transport, TransportFull};
+use grammers_mtsender::{MTSender, ReadTimeout};
+use grammers_tl_types::{self as tl, Deserializable, Serializable};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An in-memory stream which reads from canned data and captures all writes.
+struct MockStream {
+    incoming: io::Cursor<Vec<u8>>,
+    outgoing: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MockStream {
+    /// Creates a stream that will read the given packets, framed with the
+    /// full transport, and returns it along with a handle to what's written.
+    fn new(packets: &[&[u8]]) -> (Self, Rc<RefCell<Vec<u8>>>) {
+        let mut transport = TransportFull::new();
+        let mut incoming = Vec::new();
+        for packet in packets {
+            transport.send(&mut incoming, packet).unwrap();
+        }
+
+        let outgoing = Rc::new(RefCell::new(Vec::new()));
+        let stream = Self {
+            incoming: io::Cursor::new(incoming),
+            outgoing: Rc::clone(&outgoing),
+        };
+        (stream, outgoing)
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.incoming.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A stream which counts how many times it's written to.
+struct CountingStream {
+    inner: MockStream,
+    writes: Rc<Cell<usize>>,
+}
+
+impl Read for CountingStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for CountingStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes.set(self.writes.get() + 1);
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A stream which delivers the data in the given chunks, with a read timing
+/// out wherever there's a `None`, and discards all writes.
+struct SlowStream {
+    chunks: VecDeque<Option<Vec<u8>>>,
+    read_timeout: Option<Duration>,
+}
+
+impl SlowStream {
+    fn new(chunks: Vec<Option<Vec<u8>>>) -> Self {
+        Self {
+            chunks: chunks.into(),
+            read_timeout: None,
+        }
+    }
+}
+
+impl Read for SlowStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.chunks.pop_front() {
+            Some(Some(mut chunk)) => {
+                let n = chunk.len().min(buf.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+                if n < chunk.len() {
+                    self.chunks.push_front(Some(chunk.split_off(n)));
+                }
+                Ok(n)
+            }
+            Some(None) => {
+                thread::sleep(self.read_timeout.unwrap_or_default());
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl Write for SlowStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ReadTimeout for SlowStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout = timeout;
+        Ok(())
+    }
+}
+
+/// Computes the AES key and IV used to encrypt a message, where `x` is 0 for
+/// messages sent by the client and 8 for those sent by the server.
+fn calc_key(auth_key: &[u8; 256], msg_key: &[u8], x: usize) -> ([u8; 32], [u8; 32]) {
+    let sha256_a = grammers_crypto::sha256(&[msg_key, &auth_key[x..x + 36]].concat());
+    let sha256_b = grammers_crypto::sha256(&[&auth_key[40 + x..76 + x], msg_key].concat());
+
+    let mut key = [0; 32];
+    key[..8].copy_from_slice(&sha256_a[..8]);
+    key[8..24].copy_from_slice(&sha256_b[8..24]);
+    key[24..].copy_from_slice(&sha256_a[24..]);
+
+    let mut iv = [0; 32];
+    iv[..8].copy_from_slice(&sha256_b[..8]);
+    iv[8..24].copy_from_slice(&sha256_a[8..24]);
+    iv[24..].copy_from_slice(&sha256_b[24..]);
+    (key, iv)
+}
+
+/// What a `FakeServer` does every time it's read from.
+enum Event {
+    /// The read times out, once the read timeout elapses.
+    Timeout,
+    /// The server answers the ping with the given ID.
+    Pong(i64),
+}
+
+/// A stream which plays the role of the server, decrypting the requests
+/// written to it and answering them as scripted.
+struct FakeServer {
+    auth_key: [u8; 256],
+    events: VecDeque<Event>,
+    read_timeout: Option<Duration>,
+    written: Vec<u8>,
+    incoming: io::Cursor<Vec<u8>>,
+    sent_count: i64,
+}
+
+impl FakeServer {
+    fn new(auth_key: [u8; 256], events: Vec<Event>) -> Self {
+        Self {
+            auth_key,
+            events: events.into(),
+            read_timeout: None,
+            written: Vec::new(),
+            incoming: io::Cursor::new(Vec::new()),
+            sent_count: 0,
+        }
+    }
+
+    /// Returns the session ID and message ID with which the ping with the
+    /// given ID was sent.
+    fn find_ping(&self, ping_id: i64) -> (i64, i64) {
+        let ping = tl::functions::Ping { ping_id }.to_bytes();
+        let mut written = io::Cursor::new(self.written.clone());
+        let mut transport = TransportFull::new();
+        while let Ok(packet) = transport.receive(&mut written) {
+            let (key, iv) = calc_key(&self.auth_key, &packet[8..24], 0);
+            let plaintext = grammers_crypto::decrypt_ige(&packet[24..], &key, &iv);
+            let session_id = i64::from_bytes(&plaintext[8..16]).unwrap();
+
+            let mut messages = vec![&plaintext[16..]];
+            while let Some(message) = messages.pop() {
+                let msg_id = i64::from_bytes(&message[..8]).unwrap();
+                let len = i32::from_bytes(&message[12..16]).unwrap() as usize;
+                let body = &message[16..16 + len];
+                if body == &ping[..] {
+                    return (session_id, msg_id);
+                }
+                // msg_container#73f1f8dc messages:vector<%Message>
+                if body[..4] == 0x73f1f8dcu32.to_le_bytes() {
+                    let mut inner = &body[8..];
+                    while !inner.is_empty() {
+                        let len = i32::from_bytes(&inner[12..16]).unwrap() as usize;
+                        messages.push(inner);
+                        inner = &inner[16 + len..];
+                    }
+                }
+            }
+        }
+        panic!("ping {} was not sent", ping_id);
+    }
+
+    /// Returns the encrypted packet with the answer to the given ping.
+    fn pong(&mut self, ping_id: i64) -> Vec<u8> {
+        let (session_id, ping_msg_id) = self.find_ping(ping_id);
+        let pong = tl::enums::Pong::Pong(tl::types::Pong {
+            msg_id: ping_msg_id,
+            ping_id,
+        })
+        .to_bytes();
+
+        // Server message IDs contain the time they were sent at.
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        self.sent_count += 1;
+        let msg_id = ((now.as_secs() as i64) << 32) | (self.sent_count * 4 + 1);
+
+        let mut plaintext = Vec::new();
+        plaintext.extend(&0i64.to_le_bytes());
+        plaintext.extend(&session_id.to_le_bytes());
+        plaintext.extend(&msg_id.to_le_bytes());
+        plaintext.extend(&1i32.to_le_bytes());
+        plaintext.extend(&(pong.len() as i32).to_le_bytes());
+        plaintext.extend(&pong);
+        plaintext.extend(vec![0; 32 - plaintext.len() % 16]);
+
+        let msg_key_large =
+            grammers_crypto::sha256(&[&self.auth_key[96..128], &plaintext[..]].concat());
+        let msg_key = &msg_key_large[8..24];
+        let (key, iv) = calc_key(&self.auth_key, msg_key, 8);
+
+        let mut response = Vec::new();
+        response.extend(&AuthKey::from(self.auth_key).key_id().to_le_bytes());
+        response.extend(msg_key);
+        response.extend(grammers_crypto::encrypt_ige(&plaintext, &key, &iv));
+
+        let mut packet = Vec::new();
+        TransportFull::new().send(&mut packet, &response).unwrap();
+        packet
+    }
+}
+
+impl Read for FakeServer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.incoming.position() as usize == self.incoming.get_ref().len() {
+            match self.events.pop_front() {
+                Some(Event::Timeout) => {
+                    thread::sleep(self.read_timeout.unwrap_or_default());
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                Some(Event::Pong(ping_id)) => {
+                    self.incoming = io::Cursor::new(self.pong(ping_id));
+                }
+                None => return Ok(0),
+            }
+        }
+        self.incoming.read(buf)
+    }
+}
+
+impl Write for FakeServer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ReadTimeout for FakeServer {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout = timeout;
+        Ok(())
+    }
+}
+
+/// Returns a packet encrypted with the wrong key, framed with the full
+/// transport, which fails the integrity check once received whole.
+fn corrupted_packet(auth_key: [u8; 256]) -> Vec<u8> {
+    let mut response = Vec::new();
+    response.extend(&AuthKey::from(auth_key).key_id().to_le_bytes());
+    response.extend(&[0; 16]);
+    response.extend(&[0; 32]);
+
+    let mut packet = Vec::new();
+    TransportFull::new().send(&mut packet, &response).unwrap();
+    packet
+}
 
 #[test]
 fn test_auth_key_generation() {
     let mut sender = MTSender::connect(TELEGRAM_DEFAULT_TEST_DC).unwrap();
     assert!(sender.generate_auth_key().is_ok());
 }
+
+#[test]
+fn test_sent_data_is_captured_by_stream() {
+    let (stream, outgoing) = MockStream::new(&[]);
+    let mut sender = MTSender::build().with_stream(stream);
+
+    // There is no response, so the stream reaches its end.
+    let error = sender.generate_auth_key().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+
+    let packet = TransportFull::new()
+        .receive(&mut io::Cursor::new(outgoing.borrow().clone()))
+        .unwrap();
+
+    // auth_key_id, msg_id, length, and `req_pq_multi` with its nonce.
+    assert_eq!(packet.len(), 8 + 8 + 4 + 4 + 16);
+    assert_eq!(&packet[0..8], &[0; 8]);
+    assert_eq!(&packet[16..20], &20u32.to_le_bytes());
+    assert_eq!(&packet[20..24], &0xbe7e8ef1u32.to_le_bytes());
+}
+
+#[test]
+fn test_packet_is_written_at_once() {
+    let (inner, outgoing) = MockStream::new(&[]);
+    let writes = Rc::new(Cell::new(0));
+    let stream = CountingStream {
+        inner,
+        writes: Rc::clone(&writes),
+    };
+    let mut sender = MTSender::from_stream(stream);
+    assert!(sender.generate_auth_key().is_err());
+
+    // The transport's envelope and the payload are written together.
+    assert_eq!(writes.get(), 1);
+    assert!(!outgoing.borrow().is_empty());
+}
+
+#[test]
+fn test_received_data_is_read_from_stream() {
+    // A plain message must have an `auth_key_id` of zero.
+    let mut response = Vec::new();
+    response.extend(&1i64.to_le_bytes());
+    response.extend(&0i64.to_le_bytes());
+    response.extend(&4u32.to_le_bytes());
+    response.extend(&[0; 4]);
+
+    let (stream, _outgoing) = MockStream::new(&[&response]);
+    let mut sender = MTSender::build().with_stream(stream);
+
+    let error = sender.generate_auth_key().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_corrupted_message_fails_integrity_check() {
+    let auth_key = [1; 256];
+
+    // A message encrypted with our key, but whose contents don't match the
+    // message key (as if it had been corrupted along the way).
+    let mut response = Vec::new();
+    response.extend(&AuthKey::from(auth_key).key_id().to_le_bytes());
+    response.extend(&[0; 16]);
+    response.extend(&[0; 32]);
+
+    let (stream, _outgoing) = MockStream::new(&[&response]);
+    let mut sender = MTSender::build().with_stream(stream);
+    sender.set_auth_key(auth_key);
+
+    let error = sender
+        .invoke(&tl::functions::Ping { ping_id: 0 })
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "message integrity check failed");
+}
+
+#[test]
+fn test_late_response_is_received_whole() {
+    let auth_key = [1; 256];
+    let stream = SlowStream::new(vec![None, Some(corrupted_packet(auth_key))]);
+    let mut sender = MTSender::build().with_stream(stream);
+    sender.set_auth_key(auth_key);
+
+    let error = sender
+        .invoke_with_timeout(
+            &tl::functions::Ping { ping_id: 0 },
+            Duration::from_millis(20),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert!(!sender.is_desynchronized());
+
+    // The late packet is read from its start, and not mistaken for anything else.
+    let error = sender
+        .invoke(&tl::functions::Ping { ping_id: 1 })
+        .unwrap_err();
+    assert_eq!(error.to_string(), "message integrity check failed");
+}
+
+#[test]
+fn test_timeout_in_middle_of_packet_desynchronizes() {
+    let auth_key = [1; 256];
+    let mut packet = corrupted_packet(auth_key);
+    let rest = packet.split_off(10);
+    let stream = SlowStream::new(vec![Some(packet), None, Some(rest)]);
+    let mut sender = MTSender::build().with_stream(stream);
+    sender.set_auth_key(auth_key);
+
+    let error = sender
+        .invoke_with_timeout(&tl::functions::Ping { ping_id: 0 }, Duration::from_secs(1))
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert!(sender.is_desynchronized());
+
+    // The rest of the packet is not parsed as if it were a new one.
+    let error = sender
+        .invoke(&tl::functions::Ping { ping_id: 1 })
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionAborted);
+}
+
+#[test]
+fn test_response_after_network_timeout_is_awaited() {
+    let auth_key = [1; 256];
+    let stream = FakeServer::new(
+        auth_key,
+        vec![Event::Timeout, Event::Timeout, Event::Pong(0)],
+    );
+    let mut sender = MTSender::build()
+        .timeout(Some(Duration::from_millis(10)))
+        .with_stream(stream);
+    sender.set_auth_key(auth_key);
+
+    // The deadline is well past the network timeout, so both reads timing
+    // out must not make the request fail.
+    let pong: tl::types::Pong = sender
+        .invoke_with_timeout(&tl::functions::Ping { ping_id: 0 }, Duration::from_secs(5))
+        .unwrap()
+        .unwrap()
+        .into();
+    assert_eq!(pong.ping_id, 0);
+}
+
+#[test]
+fn test_response_after_deadline_is_dropped() {
+    let auth_key = [1; 256];
+    let stream = FakeServer::new(
+        auth_key,
+        vec![
+            Event::Timeout,
+            Event::Timeout,
+            Event::Pong(0),
+            Event::Pong(1),
+        ],
+    );
+    let mut sender = MTSender::build()
+        .timeout(Some(Duration::from_millis(20)))
+        .with_stream(stream);
+    sender.set_auth_key(auth_key);
+
+    let error = sender
+        .invoke_with_timeout(
+            &tl::functions::Ping { ping_id: 0 },
+            Duration::from_millis(30),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert!(!sender.is_desynchronized());
+
+    // The late answer to the first ping is not returned for the second.
+    let pong: tl::types::Pong = sender
+        .invoke(&tl::functions::Ping { ping_id: 1 })
+        .unwrap()
+        .unwrap()
+        .into();
+    assert_eq!(pong.ping_id, 1);
+}
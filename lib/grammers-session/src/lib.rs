@@ -16,9 +16,15 @@
 //! [`Session`]: trait.session.html
 
 mod memory_session;
+#[cfg(any(test, feature = "testing"))]
+mod mock_session;
 mod session;
 mod text_session;
+mod update_state;
 
 pub use memory_session::MemorySession;
+#[cfg(any(test, feature = "testing"))]
+pub use mock_session::{MockSession, SessionWrite};
 pub use session::Session;
 pub use text_session::TextSession;
+pub use update_state::UpdateState;
@@ -5,25 +5,58 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use crate::Session;
+use crate::{Session, UpdateState};
+
+struct SessionData {
+    user_dc: Option<(i32, SocketAddr)>,
+    auth_key_data: HashMap<i32, [u8; 256]>,
+    time_offset: Option<i32>,
+    update_state: Option<UpdateState>,
+}
 
 /// A basic session implementation, kept only in-memory.
+///
+/// The sessions returned by [`clone_box`] share the same data, so changes
+/// made through any of them are seen by all.
+///
+/// [`clone_box`]: trait.Session.html#method.clone_box
 pub struct MemorySession {
-    user_dc: Option<(i32, SocketAddr)>,
-    auth_key_data: Option<[u8; 256]>,
+    data: Arc<Mutex<SessionData>>,
 }
 
 impl MemorySession {
     /// Create a new session instance.
     pub fn new() -> Self {
         Self {
-            user_dc: None,
-            auth_key_data: None,
+            data: Arc::new(Mutex::new(SessionData {
+                user_dc: None,
+                auth_key_data: HashMap::new(),
+                time_offset: None,
+                update_state: None,
+            })),
         }
     }
+
+    /// Return a new handle to the same data.
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            data: Arc::clone(&self.data),
+        }
+    }
+
+    /// Return the authorization keys of every datacenter.
+    pub(crate) fn auth_keys(&self) -> HashMap<i32, [u8; 256]> {
+        self.data().auth_key_data.clone()
+    }
+
+    fn data(&self) -> MutexGuard<'_, SessionData> {
+        self.data.lock().unwrap()
+    }
 }
 
 impl Default for MemorySession {
@@ -34,22 +67,42 @@ impl Default for MemorySession {
 
 impl Session for MemorySession {
     fn set_user_datacenter(&mut self, dc_id: i32, dc_addr: &SocketAddr) {
-        self.user_dc = Some((dc_id, *dc_addr));
+        self.data().user_dc = Some((dc_id, *dc_addr));
     }
 
-    fn set_auth_key_data(&mut self, _dc_id: i32, data: &[u8; 256]) {
-        self.auth_key_data = Some(*data);
+    fn set_auth_key_data(&mut self, dc_id: i32, data: &[u8; 256]) {
+        self.data().auth_key_data.insert(dc_id, *data);
     }
 
     fn get_user_datacenter(&self) -> Option<(i32, SocketAddr)> {
-        self.user_dc
+        self.data().user_dc
+    }
+
+    fn get_auth_key_data(&self, dc_id: i32) -> Option<[u8; 256]> {
+        self.data().auth_key_data.get(&dc_id).copied()
     }
 
-    fn get_auth_key_data(&self, _dc_id: i32) -> Option<[u8; 256]> {
-        self.auth_key_data
+    fn set_time_offset(&mut self, offset: i32) {
+        self.data().time_offset = Some(offset);
+    }
+
+    fn get_time_offset(&self) -> Option<i32> {
+        self.data().time_offset
+    }
+
+    fn set_update_state(&mut self, state: UpdateState) {
+        self.data().update_state = Some(state);
+    }
+
+    fn get_update_state(&self) -> Option<UpdateState> {
+        self.data().update_state.clone()
     }
 
     fn save(&mut self) -> io::Result<()> {
         Ok(())
     }
+
+    fn clone_box(&self) -> Box<dyn Session> {
+        Box::new(self.share())
+    }
 }
@@ -0,0 +1,169 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::{MemorySession, Session, UpdateState};
+
+/// A write made to a [`MockSession`].
+///
+/// [`MockSession`]: struct.MockSession.html
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionWrite {
+    UserDatacenter { dc_id: i32, dc_addr: SocketAddr },
+    AuthKeyData { dc_id: i32, data: [u8; 256] },
+    TimeOffset(i32),
+    UpdateState(UpdateState),
+    Save,
+}
+
+/// An in-memory session which records every write made to it, in order.
+///
+/// The session is usually moved into the code being tested, so the log
+/// can be obtained beforehand with [`log`], and inspected afterwards.
+///
+/// [`log`]: #method.log
+pub struct MockSession {
+    session: MemorySession,
+    log: Arc<Mutex<Vec<SessionWrite>>>,
+}
+
+impl MockSession {
+    /// Create a new, empty session instance.
+    pub fn new() -> Self {
+        Self {
+            session: MemorySession::new(),
+            log: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Return a handle to the log of writes, which remains valid even after
+    /// the session is moved or dropped.
+    pub fn log(&self) -> Arc<Mutex<Vec<SessionWrite>>> {
+        Arc::clone(&self.log)
+    }
+
+    fn record(&self, write: SessionWrite) {
+        self.log.lock().unwrap().push(write);
+    }
+}
+
+impl Default for MockSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session for MockSession {
+    fn set_user_datacenter(&mut self, dc_id: i32, dc_addr: &SocketAddr) {
+        self.record(SessionWrite::UserDatacenter {
+            dc_id,
+            dc_addr: *dc_addr,
+        });
+        self.session.set_user_datacenter(dc_id, dc_addr);
+    }
+
+    fn set_auth_key_data(&mut self, dc_id: i32, data: &[u8; 256]) {
+        self.record(SessionWrite::AuthKeyData { dc_id, data: *data });
+        self.session.set_auth_key_data(dc_id, data);
+    }
+
+    fn get_user_datacenter(&self) -> Option<(i32, SocketAddr)> {
+        self.session.get_user_datacenter()
+    }
+
+    fn get_auth_key_data(&self, dc_id: i32) -> Option<[u8; 256]> {
+        self.session.get_auth_key_data(dc_id)
+    }
+
+    fn set_time_offset(&mut self, offset: i32) {
+        self.record(SessionWrite::TimeOffset(offset));
+        self.session.set_time_offset(offset);
+    }
+
+    fn get_time_offset(&self) -> Option<i32> {
+        self.session.get_time_offset()
+    }
+
+    fn set_update_state(&mut self, state: UpdateState) {
+        self.record(SessionWrite::UpdateState(state.clone()));
+        self.session.set_update_state(state);
+    }
+
+    fn get_update_state(&self) -> Option<UpdateState> {
+        self.session.get_update_state()
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        self.record(SessionWrite::Save);
+        self.session.save()
+    }
+
+    fn clone_box(&self) -> Box<dyn Session> {
+        Box::new(Self {
+            session: self.session.share(),
+            log: Arc::clone(&self.log),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_records_migration() {
+        let dc_addr: SocketAddr = "149.154.167.40:443".parse().unwrap();
+        let mut session = MockSession::new();
+        let log = session.log();
+
+        // Migrating to a different datacenter stores both its address and the
+        // key generated for it before saving.
+        session.set_user_datacenter(4, &dc_addr);
+        session.set_auth_key_data(4, &[7; 256]);
+        session.save().unwrap();
+        drop(session);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                SessionWrite::UserDatacenter { dc_id: 4, dc_addr },
+                SessionWrite::AuthKeyData {
+                    dc_id: 4,
+                    data: [7; 256]
+                },
+                SessionWrite::Save,
+            ]
+        );
+    }
+
+    #[test]
+    fn check_clone_shares_data_and_log() {
+        let mut session = MockSession::new();
+        let log = session.log();
+        let mut clone = session.clone_box();
+
+        clone.set_auth_key_data(2, &[1; 256]);
+        assert_eq!(session.get_auth_key_data(2), Some([1; 256]));
+
+        session.set_time_offset(-3);
+        assert_eq!(clone.get_time_offset(), Some(-3));
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                SessionWrite::AuthKeyData {
+                    dc_id: 2,
+                    data: [1; 256]
+                },
+                SessionWrite::TimeOffset(-3),
+            ]
+        );
+    }
+}
@@ -8,6 +8,8 @@
 use std::io;
 use std::net::SocketAddr;
 
+use crate::{MemorySession, UpdateState};
+
 /// The trait used by session implementations.
 ///
 /// The creation and loading of sessions is left up to each concrete
@@ -15,19 +17,79 @@ use std::net::SocketAddr;
 ///
 /// However, all of them should store the required information to allow
 /// saving themselves whenever the client requests to do so.
-pub trait Session {
+///
+/// Sessions must be `Send`, so that the client owning them can be moved to
+/// a different thread.
+pub trait Session: Send {
     /// Save the user's main datacenter to the session.
     fn set_user_datacenter(&mut self, dc_id: i32, dc_addr: &SocketAddr);
 
     /// Save the authorization key data to the session.
+    ///
+    /// Every datacenter has its own key, and keys for datacenters other than
+    /// the user's are needed too (for example, to download files stored in
+    /// them), so all of them should be kept.
     fn set_auth_key_data(&mut self, dc_id: i32, data: &[u8; 256]);
 
     /// Return the user's main datacenter, if any.
     fn get_user_datacenter(&self) -> Option<(i32, SocketAddr)>;
 
-    /// Return the authorization key, if any.
+    /// Return the authorization key for the given datacenter, if any.
     fn get_auth_key_data(&self, dc_id: i32) -> Option<[u8; 256]>;
 
+    /// Save the difference between the server's time and local time,
+    /// in seconds.
+    ///
+    /// The default implementation does nothing, so sessions that don't
+    /// override it will have to correct the offset again on every run.
+    fn set_time_offset(&mut self, _offset: i32) {}
+
+    /// Return the last known difference between the server's time and
+    /// local time, in seconds, if any.
+    fn get_time_offset(&self) -> Option<i32> {
+        None
+    }
+
+    /// Save the state needed to fetch the updates missed while offline.
+    ///
+    /// The default implementation does nothing, so sessions that don't
+    /// override it won't be able to catch up on missed updates.
+    fn set_update_state(&mut self, _state: UpdateState) {}
+
+    /// Return the last saved update state, if any.
+    fn get_update_state(&self) -> Option<UpdateState> {
+        None
+    }
+
     /// Persist the data to disk.
     fn save(&mut self) -> io::Result<()>;
+
+    /// Return a new handle to this session, which can be used, for example,
+    /// from a different thread.
+    ///
+    /// The default implementation returns a [`MemorySession`] with a copy
+    /// of the current data (including the authorization keys of the five
+    /// production datacenters), so changes made to either of them are not
+    /// seen by the other, and those made to the copy are never persisted.
+    /// Sessions should override it to share their underlying storage.
+    ///
+    /// [`MemorySession`]: struct.MemorySession.html
+    fn clone_box(&self) -> Box<dyn Session> {
+        let mut session = MemorySession::new();
+        if let Some((dc_id, dc_addr)) = self.get_user_datacenter() {
+            session.set_user_datacenter(dc_id, &dc_addr);
+        }
+        for dc_id in 1..=5 {
+            if let Some(data) = self.get_auth_key_data(dc_id) {
+                session.set_auth_key_data(dc_id, &data);
+            }
+        }
+        if let Some(offset) = self.get_time_offset() {
+            session.set_time_offset(offset);
+        }
+        if let Some(state) = self.get_update_state() {
+            session.set_update_state(state);
+        }
+        Box::new(session)
+    }
 }
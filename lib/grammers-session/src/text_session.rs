@@ -5,20 +5,29 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
 use std::io::{self, BufRead, BufReader, Seek, Write};
 use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
-use crate::Session;
+use crate::{MemorySession, Session, UpdateState};
 
 const CURRENT_VERSION: u32 = 1;
 
 /// A basic session implementation, backed by a text file.
+///
+/// The sessions returned by [`clone_box`] share the same data and file, so
+/// changes made through any of them are seen by all, and saved by any.
+///
+/// [`clone_box`]: trait.Session.html#method.clone_box
 pub struct TextSession {
-    file: File,
-    user_dc: Option<(i32, SocketAddr)>,
-    auth_key_data: Option<[u8; 256]>,
+    file: Arc<Mutex<File>>,
+    /// The data of the session, including the authorization key of every
+    /// datacenter (not just the user's, since the others are used, for
+    /// example, to download files).
+    session: MemorySession,
 }
 
 fn parse_hex(byte: &str) -> Option<u8> {
@@ -59,9 +68,8 @@ impl TextSession {
     /// Create a new session instance.
     pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         Ok(Self {
-            file: File::create(path)?,
-            user_dc: None,
-            auth_key_data: None,
+            file: Arc::new(Mutex::new(File::create(path)?)),
+            session: MemorySession::new(),
         })
     }
 
@@ -117,57 +125,274 @@ impl TextSession {
             None
         };
 
-        // auth_key_data
-        let auth_key_data = if let Some(Ok(line)) = lines.next() {
-            key_from_hex(&line)
+        // auth_key_data of the user's datacenter
+        let mut auth_key_data = HashMap::new();
+        if let Some(Ok(line)) = lines.next() {
+            if let (Some((dc_id, _)), Some(key)) = (user_dc, key_from_hex(&line)) {
+                auth_key_data.insert(dc_id, key);
+            }
+        }
+
+        // time_offset (missing in sessions saved by older versions)
+        let time_offset = if let Some(Ok(line)) = lines.next() {
+            line.parse().ok()
         } else {
             None
         };
 
+        // update_state, as space-separated "pts qts date seq"
+        let update_state = if let Some(Ok(line)) = lines.next() {
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse())
+                .collect::<Result<Vec<i32>, _>>();
+
+            match values.as_ref().map(|v| v.as_slice()) {
+                Ok([pts, qts, date, seq]) => Some(UpdateState {
+                    pts: *pts,
+                    qts: *qts,
+                    date: *date,
+                    seq: *seq,
+                    channels: HashMap::new(),
+                }),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        // update_state.channels, as space-separated "channel_id:pts" pairs
+        let update_state = match (update_state, lines.next()) {
+            (Some(mut state), Some(Ok(line))) => {
+                state.channels = line
+                    .split_whitespace()
+                    .filter_map(|pair| {
+                        let mut parts = pair.split(':');
+                        let channel_id = parts.next()?.parse().ok()?;
+                        let pts = parts.next()?.parse().ok()?;
+                        Some((channel_id, pts))
+                    })
+                    .collect();
+                Some(state)
+            }
+            (state, _) => state,
+        };
+
+        // auth_key_data of other datacenters, as space-separated "dc_id:key"
+        // pairs (missing in sessions saved by older versions)
+        if let Some(Ok(line)) = lines.next() {
+            auth_key_data.extend(line.split_whitespace().filter_map(|pair| {
+                let mut parts = pair.split(':');
+                let dc_id = parts.next()?.parse().ok()?;
+                let key = key_from_hex(parts.next()?)?;
+                Some((dc_id, key))
+            }));
+        }
+
         drop(lines);
+        let mut session = MemorySession::new();
+        if let Some((dc_id, dc_addr)) = user_dc {
+            session.set_user_datacenter(dc_id, &dc_addr);
+        }
+        for (dc_id, data) in auth_key_data.iter() {
+            session.set_auth_key_data(*dc_id, data);
+        }
+        if let Some(offset) = time_offset {
+            session.set_time_offset(offset);
+        }
+        if let Some(state) = update_state {
+            session.set_update_state(state);
+        }
+
         Ok(Self {
-            file: File::open(path)?,
-            user_dc,
-            auth_key_data,
+            file: Arc::new(Mutex::new(OpenOptions::new().write(true).open(path)?)),
+            session,
         })
     }
 }
 
 impl Session for TextSession {
     fn set_user_datacenter(&mut self, dc_id: i32, dc_addr: &SocketAddr) {
-        self.user_dc = Some((dc_id, *dc_addr));
+        self.session.set_user_datacenter(dc_id, dc_addr);
     }
 
-    fn set_auth_key_data(&mut self, _dc_id: i32, data: &[u8; 256]) {
-        self.auth_key_data = Some(*data);
+    fn set_auth_key_data(&mut self, dc_id: i32, data: &[u8; 256]) {
+        self.session.set_auth_key_data(dc_id, data);
     }
 
     fn get_user_datacenter(&self) -> Option<(i32, SocketAddr)> {
-        self.user_dc
+        self.session.get_user_datacenter()
+    }
+
+    fn get_auth_key_data(&self, dc_id: i32) -> Option<[u8; 256]> {
+        self.session.get_auth_key_data(dc_id)
+    }
+
+    fn set_time_offset(&mut self, offset: i32) {
+        self.session.set_time_offset(offset);
     }
 
-    fn get_auth_key_data(&self, _dc_id: i32) -> Option<[u8; 256]> {
-        self.auth_key_data
+    fn get_time_offset(&self) -> Option<i32> {
+        self.session.get_time_offset()
+    }
+
+    fn set_update_state(&mut self, state: UpdateState) {
+        self.session.set_update_state(state);
+    }
+
+    fn get_update_state(&self) -> Option<UpdateState> {
+        self.session.get_update_state()
     }
 
     fn save(&mut self) -> io::Result<()> {
-        self.file.seek(io::SeekFrom::Start(0))?;
-        writeln!(self.file, "{}", CURRENT_VERSION)?;
+        let user_dc = self.session.get_user_datacenter();
+        let auth_key_data = self.session.auth_keys();
+        let time_offset = self.session.get_time_offset();
+        let update_state = self.session.get_update_state();
 
-        if let Some((dc_id, dc_addr)) = self.user_dc {
-            writeln!(self.file, "{}", dc_id)?;
-            writeln!(self.file, "{}", dc_addr)?;
+        let mut file = self.file.lock().unwrap();
+        file.seek(io::SeekFrom::Start(0))?;
+        file.set_len(0)?;
+        writeln!(file, "{}", CURRENT_VERSION)?;
+
+        if let Some((dc_id, dc_addr)) = user_dc {
+            writeln!(file, "{}", dc_id)?;
+            writeln!(file, "{}", dc_addr)?;
         } else {
-            writeln!(self.file)?;
-            writeln!(self.file)?;
+            writeln!(file)?;
+            writeln!(file)?;
         }
 
-        if let Some(data) = self.auth_key_data {
-            writeln!(self.file, "{}", hex_from_key(&data))?;
+        let user_dc_id = user_dc.map(|(dc_id, _)| dc_id);
+        if let Some(data) = user_dc_id.and_then(|dc_id| auth_key_data.get(&dc_id)) {
+            writeln!(file, "{}", hex_from_key(data))?;
         } else {
-            writeln!(self.file)?;
+            writeln!(file)?;
         }
-        self.file.sync_data()?;
+
+        if let Some(offset) = time_offset {
+            writeln!(file, "{}", offset)?;
+        } else {
+            writeln!(file)?;
+        }
+
+        if let Some(state) = &update_state {
+            writeln!(
+                file,
+                "{} {} {} {}",
+                state.pts, state.qts, state.date, state.seq
+            )?;
+            for (i, (channel_id, pts)) in state.channels.iter().enumerate() {
+                if i != 0 {
+                    write!(file, " ")?;
+                }
+                write!(file, "{}:{}", channel_id, pts)?;
+            }
+            writeln!(file)?;
+        } else {
+            writeln!(file)?;
+            writeln!(file)?;
+        }
+
+        let other_keys = auth_key_data
+            .iter()
+            .filter(|(dc_id, _)| Some(**dc_id) != user_dc_id);
+        for (i, (dc_id, data)) in other_keys.enumerate() {
+            if i != 0 {
+                write!(file, " ")?;
+            }
+            write!(file, "{}:{}", dc_id, hex_from_key(data))?;
+        }
+        writeln!(file)?;
+        file.sync_data()?;
         Ok(())
     }
+
+    fn clone_box(&self) -> Box<dyn Session> {
+        Box::new(Self {
+            file: Arc::clone(&self.file),
+            session: self.session.share(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Returns a path in the temporary directory unique to the given test.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "grammers-session-{}-{}.session",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let path = temp_path("round-trip");
+        let dc_addr: SocketAddr = "149.154.167.51:443".parse().unwrap();
+        let mut state = UpdateState {
+            pts: 10,
+            qts: 20,
+            date: 1_577_836_800,
+            seq: 30,
+            ..UpdateState::default()
+        };
+        state.channels.insert(1234, 56);
+        state.channels.insert(7890, 12);
+
+        let mut session = TextSession::create(&path).unwrap();
+        session.set_user_datacenter(2, &dc_addr);
+        session.set_auth_key_data(2, &[2; 256]);
+        session.set_auth_key_data(4, &[4; 256]);
+        session.set_auth_key_data(5, &[5; 256]);
+        session.set_time_offset(-42);
+        session.set_update_state(state.clone());
+        session.save().unwrap();
+        drop(session);
+
+        let session = TextSession::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(session.get_user_datacenter(), Some((2, dc_addr)));
+        assert_eq!(session.get_auth_key_data(2), Some([2; 256]));
+        assert_eq!(session.get_auth_key_data(4), Some([4; 256]));
+        assert_eq!(session.get_auth_key_data(5), Some([5; 256]));
+        assert_eq!(session.get_time_offset(), Some(-42));
+        assert_eq!(session.get_update_state(), Some(state));
+    }
+
+    #[test]
+    fn check_round_trip_empty() {
+        let path = temp_path("round-trip-empty");
+        TextSession::create(&path).unwrap().save().unwrap();
+
+        let session = TextSession::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(session.get_user_datacenter(), None);
+        assert_eq!(session.get_time_offset(), None);
+        assert_eq!(session.get_update_state(), None);
+    }
+
+    #[test]
+    fn check_clone_saves_shared_data() {
+        let path = temp_path("clone");
+        let mut session = TextSession::create(&path).unwrap();
+        let mut clone = session.clone_box();
+
+        session.set_time_offset(7);
+        clone.save().unwrap();
+        drop(session);
+        drop(clone);
+
+        let session = TextSession::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(session.get_time_offset(), Some(7));
+    }
 }
@@ -0,0 +1,31 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use std::collections::HashMap;
+
+/// The state needed to fetch the updates missed while offline, as returned
+/// by Telegram.
+///
+/// See also: https://core.telegram.org/api/updates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UpdateState {
+    /// The number of events that occurred in the account's text box.
+    pub pts: i32,
+
+    /// The number of events that occurred in the account's secret chats.
+    pub qts: i32,
+
+    /// The date of the last update received.
+    pub date: i32,
+
+    /// The number of update sequences received.
+    pub seq: i32,
+
+    /// The `pts` of each channel, indexed by their ID, which are tracked
+    /// separately from the common `pts`.
+    pub channels: HashMap<i32, i32>,
+}
@@ -9,7 +9,8 @@
 //! Errors that can occur during the parsing of [Type Language] definitions.
 //!
 //! [Type Language]: https://core.telegram.org/mtproto/TL
-use std::num::ParseIntError;
+use alloc::string::String;
+use core::num::ParseIntError;
 
 /// The error type for the parsing operation of [`Definition`]s.
 ///
@@ -56,7 +57,8 @@ pub enum ParamParseError {
     /// use, such as `{X:Type}`, but it is not a parameter in itself.
     TypeDef { name: String },
 
-    /// The parameter refers to some unknown definition.
+    /// The parameter refers to some unknown definition, such as a generic
+    /// type or a flags parameter that was not defined before it.
     MissingDef,
 
     /// The parser does not know how to parse the parameter.
@@ -17,6 +17,16 @@
 //! [Type Language]: https://core.telegram.org/mtproto/TL
 //! [`parse_tl_file`]: fn.parse_tl_file.html
 //! [`Definition`]: tl/struct.Definition.html
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it, the crate is
+//! `no_std` (but still requires `alloc`).
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[macro_use]
+extern crate alloc;
+
 pub mod errors;
 pub mod tl;
 mod tl_iterator;
@@ -5,8 +5,10 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::fmt;
-use std::str::FromStr;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
 
 use crate::errors::{ParamParseError, ParseError};
 use crate::tl::{Category, Flag, Parameter, ParameterType, Type};
@@ -63,7 +65,12 @@ impl fmt::Display for Definition {
         for param in self.params.iter() {
             write!(f, " {}", param)?;
         }
-        write!(f, " = {}", self.ty)?;
+        if self.ty.generic_ref {
+            // The result of generic definitions is written without the `!`.
+            write!(f, " = {}", self.ty.name)?;
+        } else {
+            write!(f, " = {}", self.ty)?;
+        }
         Ok(())
     }
 }
@@ -225,6 +232,7 @@ impl FromStr for Definition {
 mod tests {
     use super::*;
     use crate::tl::Flag;
+    use alloc::boxed::Box;
 
     #[test]
     fn parse_empty_def() {
@@ -425,11 +433,50 @@ mod tests {
             Definition::from_str(def),
             Err(ParseError::InvalidParam(ParamParseError::MissingDef))
         );
+
+        let def = "a x:flags.2?int = B";
+        assert_eq!(
+            Definition::from_str(def),
+            Err(ParseError::InvalidParam(ParamParseError::MissingDef))
+        );
+    }
+
+    #[test]
+    fn parse_flags_defined_late() {
+        // The flags must be defined before the parameters that use them.
+        let def = "a x:flags.2?int flags:# = B";
+        assert_eq!(
+            Definition::from_str(def),
+            Err(ParseError::InvalidParam(ParamParseError::MissingDef))
+        );
+    }
+
+    #[test]
+    fn parse_multiple_flags() {
+        let def = "a#1 flags:# x:flags.0?int flags2:# y:flags2.1?true = B";
+        let def = Definition::from_str(def).unwrap();
+        assert_eq!(def.params.len(), 4);
+        assert_eq!(def.params[0].ty, ParameterType::Flags);
+        assert_eq!(def.params[2].ty, ParameterType::Flags);
+
+        let flag_of = |param: &Parameter| match &param.ty {
+            ParameterType::Normal {
+                flag: Some(flag), ..
+            } => (flag.name.clone(), flag.index),
+            _ => panic!("parameter is not conditional"),
+        };
+        assert_eq!(flag_of(&def.params[1]), ("flags".into(), 0));
+        assert_eq!(flag_of(&def.params[3]), ("flags2".into(), 1));
+
+        let def = "a#1 flags:# x:flags.0?int flags2:# y:flags2.1?true = B";
+        assert_eq!(Definition::from_str(def).unwrap().to_string(), def);
     }
 
     #[test]
     fn test_to_string() {
         let def = "ns1.name#123 {X:Type} flags:# pname:flags.10?ns2.Vector<!X> = ns3.Type";
         assert_eq!(Definition::from_str(def).unwrap().to_string(), def);
+        let def = "invokeAfterMsg#cb9f372d {X:Type} msg_id:long query:!X = X";
+        assert_eq!(Definition::from_str(def).unwrap().to_string(), def);
     }
 }
@@ -5,8 +5,9 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::fmt;
-use std::str::FromStr;
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
 
 use crate::errors::ParamParseError;
 
@@ -14,6 +15,9 @@ use crate::errors::ParamParseError;
 #[derive(Debug, PartialEq)]
 pub struct Flag {
     /// The name of the parameter containing the flags in its bits.
+    ///
+    /// A definition may have more than one flags parameter (such as
+    /// `flags` and `flags2`), and this name tells which one is used.
     pub name: String,
 
     /// The bit index used by this flag inside the flags parameter.
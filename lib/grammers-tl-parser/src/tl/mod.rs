@@ -23,3 +23,5 @@ pub use flag::Flag;
 pub use parameter::Parameter;
 pub use parameter_type::ParameterType;
 pub use ty::Type;
+
+pub use crate::utils::infer_id;
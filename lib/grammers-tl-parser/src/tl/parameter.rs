@@ -5,8 +5,9 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::fmt;
-use std::str::FromStr;
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
 
 use crate::errors::ParamParseError;
 use crate::tl::ParameterType;
@@ -81,6 +82,7 @@ impl FromStr for Parameter {
 mod tests {
     use super::*;
     use crate::tl::{Flag, Type};
+    use alloc::boxed::Box;
 
     #[test]
     fn parse_empty_param() {
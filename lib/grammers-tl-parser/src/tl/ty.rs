@@ -5,8 +5,11 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::fmt;
-use std::str::FromStr;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
 
 use crate::errors::ParamParseError;
 
@@ -37,6 +40,9 @@ impl fmt::Display for Type {
         if self.generic_ref {
             write!(f, "!")?;
         }
+        if self.bare && self.name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            write!(f, "%")?;
+        }
         write!(f, "{}", self.name)?;
         if let Some(generic_arg) = &self.generic_arg {
             write!(f, "<{}>", generic_arg)?;
@@ -78,6 +84,13 @@ impl FromStr for Type {
             (ty, false)
         };
 
+        // Parse `%Type`, the bare version of a boxed type
+        let (ty, force_bare) = if let Some(ty) = ty.strip_prefix('%') {
+            (ty, true)
+        } else {
+            (ty, false)
+        };
+
         // Parse `type<generic_arg>`
         let (ty, generic_arg) = if let Some(pos) = ty.find('<') {
             if !ty.ends_with('>') {
@@ -101,7 +114,7 @@ impl FromStr for Type {
         let name = namespace.pop().unwrap();
 
         // Safe to unwrap because we just checked is not empty
-        let bare = name.chars().next().unwrap().is_ascii_lowercase();
+        let bare = force_bare || name.chars().next().unwrap().is_ascii_lowercase();
 
         Ok(Self {
             namespace,
@@ -192,6 +205,28 @@ mod tests {
         });
     }
 
+    #[test]
+    fn check_percent_bare() {
+        assert_eq!(
+            Type::from_str("%Vector<int>"),
+            Ok(Type {
+                namespace: vec![],
+                name: "Vector".into(),
+                bare: true,
+                generic_ref: false,
+                generic_arg: Some(Box::new("int".parse().unwrap())),
+            })
+        );
+        assert_eq!(
+            Type::from_str("%Vector<int>").unwrap().to_string(),
+            "%Vector<int>"
+        );
+        assert_eq!(
+            Type::from_str("Vector<int>").unwrap().to_string(),
+            "Vector<int>"
+        );
+    }
+
     #[test]
     fn check_generic_ref() {
         assert!(match Type::from_str("f") {
@@ -5,6 +5,8 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+use alloc::string::String;
+
 use crate::errors::ParseError;
 use crate::tl::{Category, Definition};
 use crate::utils::remove_tl_comments;
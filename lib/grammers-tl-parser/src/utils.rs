@@ -8,6 +8,8 @@
 
 //! This module contains several free-standing utility functions.
 
+use alloc::string::String;
+
 use crc32fast::Hasher;
 
 /// Removes all single-line comments from the contents.
@@ -31,11 +33,37 @@ pub(crate) fn remove_tl_comments(contents: &str) -> String {
     result
 }
 
-/// Infers the identifier for a definition.
-pub(crate) fn infer_id(definition: &str) -> u32 {
+/// Infers the constructor identifier of a [Type Language] definition, which
+/// is the CRC32 checksum of its textual representation.
+///
+/// An explicit `#id` present in the definition is ignored, so the result
+/// can be compared against it to detect corrupted definitions.
+///
+/// # Examples
+///
+/// ```
+/// use grammers_tl_parser::tl::infer_id;
+///
+/// assert_eq!(infer_id("boolTrue = Bool"), 0x997275b5);
+/// assert_eq!(infer_id("boolTrue#997275b5 = Bool"), 0x997275b5);
+/// ```
+///
+/// [Type Language]: https://core.telegram.org/mtproto/TL
+pub fn infer_id(definition: &str) -> u32 {
+    let definition = definition.trim().trim_end_matches(';').trim_end();
+
+    // Remove the explicit `#id` following the name, if any.
+    let name_end = definition
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(definition.len());
+    let definition = match definition[..name_end].find('#') {
+        Some(pos) => format!("{}{}", &definition[..pos], &definition[name_end..]),
+        None => definition.into(),
+    };
+
     let mut representation = definition
-        .replace(":bytes ", ": string")
-        .replace("?bytes ", "? string")
+        .replace(":bytes ", ":string ")
+        .replace("?bytes ", "?string ")
         .replace("<", " ")
         .replace(">", "")
         .replace("{", "")
@@ -92,6 +120,10 @@ mod tests {
         let def = "rpc_answer_dropped msg_id:long seq_no:int bytes:int = RpcDropAnswer";
         assert_eq!(infer_id(def), 0xa43ad8b7);
 
+        // Note the parameter of type `bytes`
+        let def = "resPQ nonce:int128 server_nonce:int128 pq:bytes server_public_key_fingerprints:Vector<long> = ResPQ";
+        assert_eq!(infer_id(def), 0x05162463);
+
         // Note the use of angle brackets
         let def = "msgs_ack msg_ids:Vector<long> = MsgsAck";
         assert_eq!(infer_id(def), 0x62d6b459);
@@ -104,4 +136,17 @@ mod tests {
         let def = "inputMessagesFilterPhoneCalls flags:# missed:flags.0?true = MessagesFilter";
         assert_eq!(infer_id(def), 0x80c99768);
     }
+
+    #[test]
+    fn check_infer_id_ignores_explicit_id() {
+        let def = "boolFalse#bc799737 = Bool";
+        assert_eq!(infer_id(def), 0xbc799737);
+
+        let def = "msgs_ack#62d6b459 msg_ids:Vector<long> = MsgsAck;";
+        assert_eq!(infer_id(def), 0x62d6b459);
+
+        // A wrong explicit ID doesn't change the inferred one.
+        let def = "msgs_ack#12345678 msg_ids:Vector<long> = MsgsAck";
+        assert_eq!(infer_id(def), 0x62d6b459);
+    }
 }
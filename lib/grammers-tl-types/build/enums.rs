@@ -31,6 +31,9 @@ fn write_enum<W: Write>(
     if cfg!(feature = "impl-debug") {
         writeln!(file, "{}#[derive(Debug)]", indent)?;
     }
+    if cfg!(feature = "non-exhaustive-enums") {
+        writeln!(file, "{}#[non_exhaustive]", indent)?;
+    }
 
     writeln!(file, "{}pub enum {} {{", indent, rusty_class_name(name))?;
     for d in type_defs.iter() {
@@ -54,7 +57,7 @@ fn write_enum<W: Write>(
 ///
 /// ```
 /// impl crate::Serializable for Name {
-///     fn serialize<B: std::io::Write>(&self, buf: &mut B) -> std::io::Result<()> {
+///     fn serialize<B: crate::io::Write>(&self, buf: &mut B) -> crate::io::Result<()> {
 ///         use crate::Identifiable;
 ///         match self {
 ///             Self::Variant(x) => {
@@ -80,7 +83,7 @@ fn write_serializable<W: Write>(
     )?;
     writeln!(
         file,
-        "{}    fn serialize<B: std::io::Write>(&self, buf: &mut B) -> std::io::Result<()> {{",
+        "{}    fn serialize<B: crate::io::Write>(&self, buf: &mut B) -> crate::io::Result<()> {{",
         indent
     )?;
 
@@ -116,11 +119,11 @@ fn write_serializable<W: Write>(
 ///
 /// ```
 /// impl crate::Deserializable for Name {
-///     fn deserialize<B: std::io::Read>(buf: &mut B) -> std::io::Result<Self> {
+///     fn deserialize<B: crate::io::Read>(buf: &mut B) -> crate::io::Result<Self> {
 ///         use crate::Identifiable;
 ///         Ok(match u32::deserialize(buf)? {
 ///             crate::types::Name::CONSTRUCTOR_ID => Self::Variant(crate::types::Name::deserialize(buf)?),
-///             _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ...)),
+///             _ => return Err(crate::io::Error::new(crate::io::ErrorKind::InvalidData, ...)),
 ///         })
 ///     }
 /// }
@@ -140,7 +143,7 @@ fn write_deserializable<W: Write>(
     )?;
     writeln!(
         file,
-        "{}    fn deserialize<B: std::io::Read>(buf: &mut B) -> std::io::Result<Self> {{",
+        "{}    fn deserialize<B: crate::io::Read>(buf: &mut B) -> crate::io::Result<Self> {{",
         indent
     )?;
     writeln!(file, "{}        use crate::Identifiable;", indent)?;
@@ -170,7 +173,7 @@ fn write_deserializable<W: Write>(
     }
     writeln!(
         file,
-        "{}            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, \
+        "{}            _ => return Err(crate::io::Error::new(crate::io::ErrorKind::InvalidData, \
          crate::errors::UnexpectedConstructor {{ id }})),",
         indent
     )?;
@@ -230,6 +233,56 @@ fn write_impl_from<W: Write>(
     Ok(())
 }
 
+/// Defines the `impl Default` corresponding to the type definitions, if any
+/// of them can be used as the default value:
+///
+/// ```
+/// impl Default for Name {
+///     fn default() -> Self {
+///         Self::NameEmpty(Default::default())
+///     }
+/// }
+/// ```
+fn write_impl_default<W: Write>(
+    file: &mut W,
+    indent: &str,
+    name: &str,
+    type_defs: &[&Definition],
+    metadata: &Metadata,
+) -> io::Result<()> {
+    let def = match metadata.default_def(&type_defs[0].ty) {
+        Some(def) => def,
+        None => return Ok(()),
+    };
+
+    writeln!(
+        file,
+        "{}impl Default for {} {{",
+        indent,
+        rusty_class_name(name)
+    )?;
+    writeln!(file, "{}    fn default() -> Self {{", indent)?;
+    writeln!(
+        file,
+        "{}        Self::{variant}({box_}Default::default(){paren})",
+        indent,
+        variant = rusty_class_name(&def.name),
+        box_ = if metadata.is_recursive_def(def) {
+            "Box::new("
+        } else {
+            ""
+        },
+        paren = if metadata.is_recursive_def(def) {
+            ")"
+        } else {
+            ""
+        },
+    )?;
+    writeln!(file, "{}    }}", indent)?;
+    writeln!(file, "{}}}", indent)?;
+    Ok(())
+}
+
 /// Writes an entire definition as Rust code (`enum` and `impl`).
 fn write_definition<W: Write>(
     file: &mut W,
@@ -245,6 +298,9 @@ fn write_definition<W: Write>(
     if cfg!(feature = "impl-from-type") {
         write_impl_from(file, indent, name, type_defs, metadata)?;
     }
+    if cfg!(feature = "impl-default") {
+        write_impl_default(file, indent, name, type_defs, metadata)?;
+    }
     Ok(())
 }
 
@@ -282,8 +338,16 @@ pub(crate) fn write_enums_mod<W: Write>(
             "    "
         };
 
+        // The crate may be `no_std`, so these are not always in the prelude.
+        writeln!(file, "{}#[allow(unused_imports)]", indent)?;
+        writeln!(
+            file,
+            "{}use alloc::{{boxed::Box, string::String, vec::Vec}};",
+            indent
+        )?;
+
         for name in grouped[key].iter() {
-            let type_defs: Vec<&Definition> = definitions
+            let mut type_defs: Vec<&Definition> = definitions
                 .into_iter()
                 .filter(|d| {
                     d.category == Category::Types
@@ -292,6 +356,10 @@ pub(crate) fn write_enums_mod<W: Write>(
                 })
                 .collect();
 
+            // Sort the variants so that the output does not depend on the
+            // order in which the definitions appear in the schema.
+            type_defs.sort_by_key(|d| &d.name);
+
             assert!(!type_defs.is_empty(), "type defs should not be empty");
             write_definition(&mut file, indent, name, &type_defs, metadata)?;
         }
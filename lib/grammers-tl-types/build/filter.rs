@@ -0,0 +1,99 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to restrict the definitions that get generated.
+
+use grammers_tl_parser::tl::{Category, Definition, ParameterType, Type};
+use std::collections::HashMap;
+
+/// Identifies a type or a definition by its namespace and name.
+type Key<'a> = (&'a [String], &'a str);
+
+/// Keep only the definitions belonging to the given namespaces, along with
+/// every definition they depend on (directly or transitively), so that the
+/// generated code can still be compiled.
+///
+/// Functions in the global namespace (such as `initConnection`) are always
+/// kept, since they're required to set up the connection. So are the
+/// constructors of `Bool`, which the library converts to and from `bool`.
+pub(crate) fn filter_namespaces(
+    definitions: Vec<Definition>,
+    namespaces: &[&str],
+) -> Vec<Definition> {
+    let mut by_type = HashMap::<Key, Vec<usize>>::new();
+    let mut by_name = HashMap::<Key, usize>::new();
+    for (i, d) in definitions.iter().enumerate() {
+        if d.category == Category::Types {
+            by_type
+                .entry((&d.ty.namespace[..], &d.ty.name[..]))
+                .or_default()
+                .push(i);
+            by_name.insert((&d.namespace[..], &d.name[..]), i);
+        }
+    }
+
+    let mut keep = vec![false; definitions.len()];
+    let mut pending = definitions
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| match d.namespace.first() {
+            Some(ns) => namespaces.contains(&&ns[..]),
+            None => d.category == Category::Functions || d.ty.name == "Bool",
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    while let Some(i) = pending.pop() {
+        if keep[i] {
+            continue;
+        }
+        keep[i] = true;
+
+        // Every constructor of a type is needed to generate its `enum`,
+        // including those of the type the definition itself belongs to.
+        let d = &definitions[i];
+        let mut refs = vec![&d.ty];
+        for param in d.params.iter() {
+            if let ParameterType::Normal { ty, .. } = &param.ty {
+                refs.push(ty);
+            }
+        }
+
+        while let Some(ty) = refs.pop() {
+            if let Some(arg) = &ty.generic_arg {
+                refs.push(arg);
+            }
+            if ty.generic_ref {
+                continue;
+            }
+            pending.extend(resolve(ty, &by_type, &by_name));
+        }
+    }
+
+    definitions
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(d, keep)| if keep { Some(d) } else { None })
+        .collect()
+}
+
+/// Find the indices of the definitions a type refers to. Bare types refer
+/// to a single constructor, while boxed types refer to all of them.
+///
+/// Built-in types, such as `int`, are not definitions and won't be found.
+fn resolve(
+    ty: &Type,
+    by_type: &HashMap<Key, Vec<usize>>,
+    by_name: &HashMap<Key, usize>,
+) -> Vec<usize> {
+    let key = (&ty.namespace[..], &ty.name[..]);
+    match by_type.get(&key) {
+        Some(indices) => indices.clone(),
+        None => by_name.get(&key).into_iter().copied().collect(),
+    }
+}
@@ -9,13 +9,16 @@
 //! Code to load definitions from files.
 
 use grammers_tl_parser::parse_tl_file;
-use grammers_tl_parser::tl::Definition;
+use grammers_tl_parser::tl::{infer_id, Definition};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read};
 
 /// Load the type language definitions from a certain file.
 /// Parse errors will be printed to `stderr`, and only the
 /// valid results will be returned.
+///
+/// Definitions whose explicit identifier differs from the inferred one
+/// are also reported, since it may be a sign of a corrupted schema.
 pub(crate) fn load_tl(file: &str) -> io::Result<Vec<Definition>> {
     let mut file = File::open(file)?;
     let mut contents = String::new();
@@ -23,7 +26,13 @@ pub(crate) fn load_tl(file: &str) -> io::Result<Vec<Definition>> {
     Ok(parse_tl_file(&contents)
         .into_iter()
         .filter_map(|d| match d {
-            Ok(d) => Some(d),
+            Ok(d) => {
+                let inferred = infer_id(&d.to_string());
+                if inferred != d.id {
+                    eprintln!("TL: id mismatch: inferred {:08x} for {}", inferred, d);
+                }
+                Some(d)
+            }
             Err(e) => {
                 eprintln!("TL: parse error: {:?}", e);
                 None
@@ -9,18 +9,53 @@
 //! This module gathers all the code generation submodules and coordinates
 //! them, feeding them the right data.
 mod enums;
+mod filter;
 mod grouper;
 mod loader;
 mod metadata;
 mod rustifier;
 mod structs;
 
-use grammers_tl_parser::tl::Category;
+use grammers_tl_parser::tl::{Category, Definition};
 use std::env;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
+/// Writes the list of every constructor identifier along with the name of
+/// its definition, sorted by identifier:
+///
+/// ```
+/// pub const CONSTRUCTORS: &[(u32, &str)] = &[
+///     (0x997275b5, "boolTrue"),
+/// ];
+/// ```
+fn write_constructors<W: Write>(file: &mut W, definitions: &[Definition]) -> io::Result<()> {
+    let mut constructors = definitions
+        .iter()
+        .map(|d| {
+            let mut name = d.namespace.join(".");
+            if !name.is_empty() {
+                name.push('.');
+            }
+            name.push_str(&d.name);
+            (d.id, name)
+        })
+        .collect::<Vec<_>>();
+    constructors.sort();
+
+    writeln!(
+        file,
+        "/// The identifier and name of every constructor, sorted by identifier."
+    )?;
+    writeln!(file, "pub const CONSTRUCTORS: &[(u32, &str)] = &[")?;
+    for (id, name) in constructors {
+        writeln!(file, "    (0x{:08x}, \"{}\"),", id, name)?;
+    }
+    writeln!(file, "];")?;
+    Ok(())
+}
+
 fn main() -> std::io::Result<()> {
     let layer = match loader::find_layer("tl/api.tl")? {
         Some(x) => x,
@@ -38,6 +73,23 @@ fn main() -> std::io::Result<()> {
         definitions
     };
 
+    // Printing any `rerun-if` disables the default of rerunning whenever
+    // any file in the package changes, so list everything that's used.
+    println!("cargo:rerun-if-changed=build");
+    println!("cargo:rerun-if-changed=tl");
+    println!("cargo:rerun-if-env-changed=GRAMMERS_TL_NAMESPACES");
+    let definitions = match env::var("GRAMMERS_TL_NAMESPACES") {
+        Ok(ref namespaces) if !namespaces.trim().is_empty() => {
+            let namespaces = namespaces
+                .split(',')
+                .map(str::trim)
+                .filter(|ns| !ns.is_empty())
+                .collect::<Vec<_>>();
+            filter::filter_namespaces(definitions, &namespaces)
+        }
+        _ => definitions,
+    };
+
     let mut file = BufWriter::new(File::create(
         Path::new(&env::var("OUT_DIR").unwrap()).join("generated.rs"),
     )?);
@@ -59,6 +111,8 @@ fn main() -> std::io::Result<()> {
         layer
     )?;
 
+    write_constructors(&mut file, &definitions)?;
+
     let metadata = metadata::Metadata::new(&definitions);
     structs::write_category_mod(&mut file, Category::Types, &definitions, &metadata)?;
     structs::write_category_mod(&mut file, Category::Functions, &definitions, &metadata)?;
@@ -7,12 +7,35 @@
 // except according to those terms.
 use std::collections::{HashMap, HashSet};
 
-use grammers_tl_parser::tl::{Category, Definition, ParameterType, Type};
+use grammers_tl_parser::tl::{Category, Definition, Parameter, ParameterType, Type};
 
 /// Additional metadata required by several parts of the generation.
 pub(crate) struct Metadata<'a> {
     recursing_defs: HashSet<u32>,
     defs_with_type: HashMap<(&'a Vec<String>, &'a String), Vec<&'a Definition>>,
+    default_defs: HashMap<(&'a Vec<String>, &'a String), &'a Definition>,
+}
+
+/// Returns `true` if the type is one of the built-in types, or a boxed
+/// vector, all of which have a `Default` value in Rust.
+fn is_builtin_with_default(ty: &Type) -> bool {
+    match ty.name.as_ref() {
+        "Bool" | "bytes" | "double" | "int" | "int128" | "int256" | "long" | "string" | "true" => {
+            true
+        }
+        "Vector" => !ty.bare,
+        _ => false,
+    }
+}
+
+/// Returns `true` if the parameter has a `Default` value without relying
+/// on any other definition (flags, optional parameters and built-ins).
+fn is_param_with_builtin_default(param: &Parameter) -> bool {
+    match &param.ty {
+        ParameterType::Flags => true,
+        ParameterType::Normal { flag: Some(_), .. } => true,
+        ParameterType::Normal { ty, flag: None } => !ty.generic_ref && is_builtin_with_default(ty),
+    }
 }
 
 impl<'a> Metadata<'a> {
@@ -20,6 +43,7 @@ impl<'a> Metadata<'a> {
         let mut metadata = Self {
             recursing_defs: HashSet::new(),
             defs_with_type: HashMap::new(),
+            default_defs: HashMap::new(),
         };
 
         definitions
@@ -42,6 +66,22 @@ impl<'a> Metadata<'a> {
                     .push(d);
             });
 
+        // Telegram uses the `...Empty` constructors to represent the lack of
+        // a value, so they're the natural default of their type.
+        definitions
+            .iter()
+            .filter(|d| {
+                d.category == Category::Types
+                    && d.name.ends_with("Empty")
+                    && d.params.iter().all(is_param_with_builtin_default)
+            })
+            .for_each(|d| {
+                metadata
+                    .default_defs
+                    .entry((&d.ty.namespace, &d.ty.name))
+                    .or_insert(d);
+            });
+
         metadata
     }
 
@@ -54,4 +94,23 @@ impl<'a> Metadata<'a> {
     pub fn defs_with_type(&self, ty: &'a Type) -> &Vec<&Definition> {
         &self.defs_with_type[&(&ty.namespace, &ty.name)]
     }
+
+    /// Returns the definition used as the `Default` value of the type, if
+    /// it has one.
+    pub fn default_def(&self, ty: &Type) -> Option<&Definition> {
+        self.default_defs.get(&(&ty.namespace, &ty.name)).copied()
+    }
+
+    /// Returns `true` if every parameter of the definition has a `Default`
+    /// value, so that one can be implemented for the definition itself.
+    pub fn has_default(&self, def: &Definition) -> bool {
+        def.params.iter().all(|param| match &param.ty {
+            ParameterType::Normal { ty, flag: None }
+                if !ty.generic_ref && !ty.bare && !is_builtin_with_default(ty) =>
+            {
+                self.default_def(ty).is_some()
+            }
+            _ => is_param_with_builtin_default(param),
+        })
+    }
 }
@@ -10,6 +10,30 @@
 
 use grammers_tl_parser::tl::{Definition, Parameter, ParameterType, Type};
 
+/// Strict and reserved keywords of Rust 2018, which cannot be used as-is
+/// for identifiers.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Escapes the identifier if it collides with a Rust keyword.
+///
+/// Most keywords can be used as raw identifiers, except for the few that
+/// refer to paths, which get an underscore appended instead.
+fn escape_keyword(name: String) -> String {
+    if !RUST_KEYWORDS.contains(&name.as_str()) {
+        name
+    } else if matches!(name.as_str(), "crate" | "self" | "Self" | "super") {
+        name + "_"
+    } else {
+        format!("r#{}", name)
+    }
+}
+
 /// Get the rusty class name for a certain definition, excluding namespace.
 ///
 /// # Examples
@@ -32,7 +56,7 @@ pub(crate) fn rusty_class_name(name: &str) -> String {
         }
     });
 
-    result
+    escape_keyword(result)
 }
 
 /// Get a rusty class name, including namespaces.
@@ -67,15 +91,12 @@ pub(crate) fn rusty_namespaced_type_name(def: &Definition) -> String {
 /// Get the rusty attribute name for a certain parameter.
 pub(crate) fn rusty_attr_name(param: &Parameter) -> String {
     match &param.name[..] {
-        "final" => "r#final".into(),
-        "loop" => "r#loop".into(),
+        // Kept for compatibility, since it's always a flag (`user.self`).
         "self" => "is_self".into(),
-        "static" => "r#static".into(),
-        "type" => "r#type".into(),
         _ => {
             let mut result = param.name.clone();
             result[..].make_ascii_lowercase();
-            result
+            escape_keyword(result)
         }
     }
 }
@@ -92,7 +113,10 @@ pub(crate) fn push_sanitized_name(result: &mut String, ty: &Type) {
         "long" => "i64",
         "string" => "String",
         "true" => "bool",
+        // `Vector` is boxed, and prefixed by its constructor identifier,
+        // while `vector` and `%Vector` are bare, and only have the length.
         "vector" => "crate::RawVec",
+        "Vector" if ty.bare => "crate::RawVec",
         "Vector" => "Vec",
         _ => "",
     };
@@ -175,7 +199,7 @@ pub(crate) fn rusty_type_path(param: &Parameter) -> String {
             if ty.generic_ref {
                 result.push_str("Vec::<u8>")
             } else {
-                push_sanitized_path(&mut result, &ty);
+                push_sanitized_path(&mut result, ty);
                 if let Some(arg) = &ty.generic_arg {
                     result.push_str("::<");
                     push_sanitized_path(&mut result, arg);
@@ -186,3 +210,39 @@ pub(crate) fn rusty_type_path(param: &Parameter) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_escape_keyword() {
+        assert_eq!(escape_keyword("name".into()), "name");
+        assert_eq!(escape_keyword("type".into()), "r#type");
+        assert_eq!(escape_keyword("move".into()), "r#move");
+        assert_eq!(escape_keyword("yield".into()), "r#yield");
+    }
+
+    #[test]
+    fn check_escape_path_keyword() {
+        // These can't be raw identifiers.
+        assert_eq!(escape_keyword("crate".into()), "crate_");
+        assert_eq!(escape_keyword("self".into()), "self_");
+        assert_eq!(escape_keyword("Self".into()), "Self_");
+        assert_eq!(escape_keyword("super".into()), "super_");
+    }
+
+    #[test]
+    fn check_rusty_attr_name() {
+        let def: Definition = "foo#01234567 type:int = Foo".parse().unwrap();
+        assert_eq!(rusty_attr_name(&def.params[0]), "r#type");
+        assert_eq!(rusty_type_name(&def.params[0]), "i32");
+
+        let def: Definition =
+            "bar#89abcdef flags:# self:flags.0?true move:flags.1?true crate:int Name:long = Bar"
+                .parse()
+                .unwrap();
+        let names = def.params.iter().map(rusty_attr_name).collect::<Vec<_>>();
+        assert_eq!(names, ["flags", "is_self", "r#move", "crate_", "name"]);
+    }
+}
@@ -14,7 +14,7 @@ use crate::rustifier::{
     rusty_attr_name, rusty_class_name, rusty_namespaced_class_name, rusty_namespaced_type_name,
     rusty_type, rusty_type_name, rusty_type_path,
 };
-use grammers_tl_parser::tl::{Category, Definition, ParameterType};
+use grammers_tl_parser::tl::{Category, Definition, Parameter, ParameterType};
 use std::io::{self, Write};
 
 /// Defines the `struct` corresponding to the definition:
@@ -28,12 +28,15 @@ fn write_struct<W: Write>(
     file: &mut W,
     indent: &str,
     def: &Definition,
-    _metadata: &Metadata,
+    metadata: &Metadata,
 ) -> io::Result<()> {
     // Define struct
     if cfg!(feature = "impl-debug") {
         writeln!(file, "{}#[derive(Debug)]", indent)?;
     }
+    if cfg!(feature = "impl-default") && metadata.has_default(def) {
+        writeln!(file, "{}#[derive(Default)]", indent)?;
+    }
 
     writeln!(
         file,
@@ -93,7 +96,7 @@ fn write_identifiable<W: Write>(
 ///
 /// ```
 /// impl crate::Serializable for Name {
-///     fn serialize<B: std::io::Write>(&self, buf: &mut B) -> std::io::Result<()> {
+///     fn serialize<B: crate::io::Write>(&self, buf: &mut B) -> crate::io::Result<()> {
 ///         self.field.serialize(buf)?;
 ///         Ok(())
 ///     }
@@ -113,7 +116,7 @@ fn write_serializable<W: Write>(
     )?;
     writeln!(
         file,
-        "{}    fn serialize<B: std::io::Write>(&self, {}buf: &mut B) -> std::io::Result<()> {{",
+        "{}    fn serialize<B: crate::io::Write>(&self, {}buf: &mut B) -> crate::io::Result<()> {{",
         indent,
         if def.category == Category::Types && def.params.is_empty() {
             "_"
@@ -200,7 +203,7 @@ fn write_serializable<W: Write>(
 ///
 /// ```
 /// impl crate::Deserializable for Name {
-///     fn deserialize<B: std::io::Read>(buf: &mut B) -> std::io::Result<Self> {
+///     fn deserialize<B: crate::io::Read>(buf: &mut B) -> crate::io::Result<Self> {
 ///         let field = FieldType::deserialize(buf)?;
 ///         Ok(Name { field })
 ///     }
@@ -220,7 +223,7 @@ fn write_deserializable<W: Write>(
     )?;
     writeln!(
         file,
-        "{}    fn deserialize<B: std::io::Read>({}buf: &mut B) -> std::io::Result<Self> {{",
+        "{}    fn deserialize<B: crate::io::Read>({}buf: &mut B) -> crate::io::Result<Self> {{",
         indent,
         if def.params.is_empty() { "_" } else { "" }
     )?;
@@ -317,6 +320,7 @@ fn write_deserializable<W: Write>(
 ///
 /// ```
 /// impl crate::RPC for Name {
+///     const CONSTRUCTOR_NAME: &'static str = "ns.name";
 ///     type Return = Name;
 /// }
 /// ```
@@ -332,6 +336,16 @@ fn write_rpc<W: Write>(
         indent,
         rusty_class_name(&def.name)
     )?;
+    let mut name = def.namespace.join(".");
+    if !name.is_empty() {
+        name.push('.');
+    }
+    name.push_str(&def.name);
+    writeln!(
+        file,
+        "{}    const CONSTRUCTOR_NAME: &'static str = \"{}\";",
+        indent, name
+    )?;
     writeln!(file, "{}    type Return = {};", indent, rusty_type(&def.ty))?;
     writeln!(file, "{}}}", indent)?;
     Ok(())
@@ -404,6 +418,110 @@ fn write_impl_from<W: Write>(
     Ok(())
 }
 
+/// Defines the builder corresponding to a function definition with optional
+/// parameters, which takes the required parameters up-front:
+///
+/// ```
+/// impl Name {
+///     pub fn builder(field: Type) -> NameBuilder { ... }
+/// }
+///
+/// pub struct NameBuilder(Name);
+///
+/// impl NameBuilder {
+///     pub fn optional(mut self, optional: Type) -> Self { ... }
+///     pub fn build(self) -> Name { ... }
+/// }
+/// ```
+///
+/// The flags are computed on serialization, so setting the optional
+/// parameters through the builder is enough to set their bits.
+fn write_builder<W: Write>(
+    file: &mut W,
+    indent: &str,
+    def: &Definition,
+    _metadata: &Metadata,
+) -> io::Result<()> {
+    let is_optional = |param: &&Parameter| match &param.ty {
+        ParameterType::Flags => false,
+        ParameterType::Normal { flag, .. } => flag.is_some(),
+    };
+    let is_required = |param: &&Parameter| match &param.ty {
+        ParameterType::Flags => false,
+        ParameterType::Normal { flag, .. } => flag.is_none(),
+    };
+    if !def.params.iter().any(|p| is_optional(&p)) {
+        return Ok(());
+    }
+
+    let name = rusty_class_name(&def.name);
+
+    writeln!(file, "{}impl {} {{", indent, name)?;
+    writeln!(file, "{}    #[allow(clippy::too_many_arguments)]", indent)?;
+    write!(file, "{}    pub fn builder(", indent)?;
+    for param in def.params.iter().filter(is_required) {
+        write!(
+            file,
+            "{}: {}, ",
+            rusty_attr_name(param),
+            rusty_type_name(param)
+        )?;
+    }
+    writeln!(file, ") -> {}Builder {{", name)?;
+    writeln!(file, "{}        {}Builder({} {{", indent, name, name)?;
+    for param in def.params.iter() {
+        match &param.ty {
+            ParameterType::Flags => {}
+            ParameterType::Normal { ty, flag } => {
+                let attr = rusty_attr_name(param);
+                if flag.is_none() {
+                    writeln!(file, "{}            {},", indent, attr)?;
+                } else if ty.name == "true" {
+                    writeln!(file, "{}            {}: false,", indent, attr)?;
+                } else {
+                    writeln!(file, "{}            {}: None,", indent, attr)?;
+                }
+            }
+        }
+    }
+    writeln!(file, "{}        }})", indent)?;
+    writeln!(file, "{}    }}", indent)?;
+    writeln!(file, "{}}}", indent)?;
+
+    writeln!(file, "{}pub struct {}Builder({});", indent, name, name)?;
+
+    writeln!(file, "{}#[allow(clippy::wrong_self_convention)]", indent)?;
+    writeln!(file, "{}impl {}Builder {{", indent, name)?;
+    for param in def.params.iter().filter(is_optional) {
+        if let ParameterType::Normal { ty, .. } = &param.ty {
+            let attr = rusty_attr_name(param);
+            if ty.name == "true" {
+                writeln!(
+                    file,
+                    "{}    pub fn {attr}(mut self, {attr}: bool) -> Self {{ self.0.{attr} = {attr}; self }}",
+                    indent,
+                    attr = attr
+                )?;
+            } else {
+                writeln!(
+                    file,
+                    "{}    pub fn {attr}(mut self, {attr}: {ty}) -> Self {{ self.0.{attr} = Some({attr}); self }}",
+                    indent,
+                    attr = attr,
+                    ty = rusty_type(ty)
+                )?;
+            }
+        }
+    }
+    writeln!(
+        file,
+        "{}    pub fn build(self) -> {} {{ self.0 }}",
+        indent, name
+    )?;
+    writeln!(file, "{}}}", indent)?;
+    Ok(())
+}
+
 /// Writes an entire definition as Rust code (`struct` and `impl`).
 fn write_definition<W: Write>(
     file: &mut W,
@@ -419,6 +537,9 @@ fn write_definition<W: Write>(
     }
     if def.category == Category::Functions {
         write_rpc(file, indent, def, metadata)?;
+        if cfg!(feature = "impl-builder") {
+            write_builder(file, indent, def, metadata)?;
+        }
     }
     if def.category == Category::Types && cfg!(feature = "impl-from-enum") {
         write_impl_from(file, indent, def, metadata)?;
@@ -485,9 +606,17 @@ pub(crate) fn write_category_mod<W: Write>(
             // If all of the conversions are infallible this will be unused.
             // Don't bother checking this beforehand, just allow warnings.
             writeln!(file, "{}#[allow(unused_imports)]", indent)?;
-            writeln!(file, "{}use std::convert::TryFrom;", indent)?;
+            writeln!(file, "{}use core::convert::TryFrom;", indent)?;
         }
 
+        // The crate may be `no_std`, so these are not always in the prelude.
+        writeln!(file, "{}#[allow(unused_imports)]", indent)?;
+        writeln!(
+            file,
+            "{}use alloc::{{boxed::Box, string::String, vec::Vec}};",
+            indent
+        )?;
+
         for definition in grouped[key].iter() {
             write_definition(&mut file, indent, definition, metadata)?;
         }
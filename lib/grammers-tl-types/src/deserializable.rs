@@ -6,7 +6,10 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 use crate::errors::UnexpectedConstructor;
-use std::io::{Cursor, Error, ErrorKind, Read, Result};
+use crate::io::{Error, ErrorKind, Read, Result};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 
 /// Read a single byte from the buffer.
 #[inline(always)]
@@ -25,15 +28,14 @@ pub trait Deserializable {
     /// # Examples
     ///
     /// ```
-    /// use std::io::Cursor;
     /// use grammers_tl_types::Deserializable;
     ///
-    /// let mut cursor = Cursor::new([0xb5, 0x75, 0x72, 0x99]);
-    /// assert_eq!(bool::deserialize(&mut cursor).unwrap(), true);
+    /// let mut buffer: &[u8] = &[0xb5, 0x75, 0x72, 0x99];
+    /// assert_eq!(bool::deserialize(&mut buffer).unwrap(), true);
     /// ```
     fn deserialize<B: Read>(buf: &mut B) -> Result<Self>
     where
-        Self: std::marker::Sized;
+        Self: core::marker::Sized;
 
     /// Convenience function to deserialize an instance from a given buffer.
     ///
@@ -46,9 +48,10 @@ pub trait Deserializable {
     /// ```
     fn from_bytes(buf: &[u8]) -> Result<Self>
     where
-        Self: std::marker::Sized,
+        Self: core::marker::Sized,
     {
-        Self::deserialize(&mut Cursor::new(buf))
+        let mut buf = buf;
+        Self::deserialize(&mut buf)
     }
 }
 
@@ -368,7 +371,7 @@ impl Deserializable for Vec<u8> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tl-api"))]
 mod tests {
     use super::*;
 
@@ -13,8 +13,9 @@
 //! [`Deserializable`]: trait.Serializable.html
 //! [`types`]: types/index.html
 //! [`enums`]: enums/index.html
+use core::fmt;
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::fmt;
 
 /// The error type indicating an unexpected constructor was found,
 /// for example, when reading data that doesn't represent the
@@ -35,11 +36,16 @@ pub struct UnexpectedConstructor {
     pub id: u32,
 }
 
+#[cfg(feature = "std")]
 impl Error for UnexpectedConstructor {}
 
 impl fmt::Display for UnexpectedConstructor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "unexpected constructor: {:08x}", self.id)
+        write!(f, "unexpected constructor: {:08x}", self.id)?;
+        if let Some(name) = crate::name_for_id(self.id) {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
     }
 }
 
@@ -49,7 +55,7 @@ impl fmt::Display for UnexpectedConstructor {
 #[cfg(feature = "impl-from-enum")]
 pub struct WrongVariant;
 
-#[cfg(feature = "impl-from-enum")]
+#[cfg(all(feature = "std", feature = "impl-from-enum"))]
 impl Error for WrongVariant {}
 
 #[cfg(feature = "impl-from-enum")]
@@ -0,0 +1,157 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The input and output abstractions used by [`Serializable`] and
+//! [`Deserializable`].
+//!
+//! With the `std` feature enabled (the default), these are re-exports of
+//! the types and traits found in `std::io`. Otherwise, minimal equivalents
+//! are provided, capable of working with in-memory buffers.
+//!
+//! [`Serializable`]: ../trait.Serializable.html
+//! [`Deserializable`]: ../trait.Deserializable.html
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// A list specifying general categories of I/O error.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// Data not valid for the operation were encountered.
+        InvalidData,
+        /// An operation could not be completed because an "end of file"
+        /// was reached prematurely.
+        UnexpectedEof,
+        /// Any I/O error not part of this list.
+        Other,
+    }
+
+    /// The error type for I/O operations of the `Read` and `Write` traits.
+    pub struct Error {
+        kind: ErrorKind,
+        error: Option<Box<dyn fmt::Display + Send + Sync>>,
+    }
+
+    /// A specialized `Result` type for I/O operations.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Error {
+        /// Creates a new I/O error from a known kind of error as well as an
+        /// arbitrary error payload.
+        pub fn new<E: fmt::Display + Send + Sync + 'static>(kind: ErrorKind, error: E) -> Self {
+            Self {
+                kind,
+                error: Some(Box::new(error)),
+            }
+        }
+
+        /// Returns the corresponding `ErrorKind` for this error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind, error: None }
+        }
+    }
+
+    impl fmt::Debug for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Error {{ kind: {:?}", self.kind)?;
+            if let Some(error) = &self.error {
+                write!(f, ", error: \"{}\"", error)?;
+            }
+            write!(f, " }}")
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.error {
+                Some(error) => error.fmt(f),
+                None => write!(f, "{:?}", self.kind),
+            }
+        }
+    }
+
+    /// Allows reading bytes from a source.
+    pub trait Read {
+        /// Pull some bytes from this source into the specified buffer,
+        /// returning how many bytes were read.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        /// Read the exact number of bytes required to fill `buf`.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => return Err(ErrorKind::UnexpectedEof.into()),
+                    n => buf = &mut buf[n..],
+                }
+            }
+            Ok(())
+        }
+
+        /// Read all bytes until the end of the source, placing them into
+        /// `buf`, and returning how many bytes were read.
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let start = buf.len();
+            let mut chunk = [0u8; 64];
+            loop {
+                match self.read(&mut chunk)? {
+                    0 => return Ok(buf.len() - start),
+                    n => buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = tail;
+            Ok(n)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    /// Allows writing bytes into a destination.
+    pub trait Write {
+        /// Attempts to write an entire buffer into this writer.
+        fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+    }
+
+    impl Write for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+}
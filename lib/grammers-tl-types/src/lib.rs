@@ -19,6 +19,7 @@
 //!
 //! The default feature set includes:
 //!
+//! * `std`.
 //! * `tl-api`.
 //! * `impl-debug`.
 //! * `impl-from-type`.
@@ -26,6 +27,10 @@
 //!
 //! The available features are:
 //!
+//! * `std`: uses the standard library's `io` traits for serialization.
+//!   Without it, the crate is `no_std` (but still requires `alloc`), and
+//!   the minimal traits in the [`io`] module are used instead.
+//!
 //! * `tl-api`: generates code for the `api.tl`.
 //!   This is what high-level libraries often need.
 //!
@@ -41,17 +46,53 @@
 //! * `impl-from-type`: implements `From<Type> for Enum`.
 //! * `impl-from-enum`: implements `TryFrom<Enum> for Type`.
 //!
+//! * `impl-default`: implements `Default` for the generated [`types`] and
+//!   [`functions`] whose parameters all have a default value, so requests
+//!   can be built with `..Default::default()`. Optional parameters default
+//!   to `None`, and boxed types use their `...Empty` constructor, if any.
+//!
+//! * `impl-builder`: generates a builder for the [`functions`] with optional
+//!   parameters, which takes the required ones in `Function::builder` and
+//!   has a method to set each optional one, such as
+//!   `SendMessage::builder(peer, message, random_id).silent(true).build()`.
+//!
+//! * `non-exhaustive-enums`: marks the generated [`enums`] as
+//!   `#[non_exhaustive]`, so that code matching on them needs a wildcard
+//!   arm and keeps compiling when a new layer adds constructors. Note that
+//!   this also forbids irrefutable patterns on single-constructor enums,
+//!   such as `let enums::Foo::Foo(x) = foo;`. Use the conversion from
+//!   `impl-from-enum` instead, as in `let x: types::Foo = foo.into();`.
+//!
+//! # Generating a subset
+//!
+//! If the `GRAMMERS_TL_NAMESPACES` environment variable is set during the
+//! build to a comma-separated list of namespaces (such as
+//! `messages,auth,help`), only the definitions in those namespaces will be
+//! generated, along with any other definition they depend on. Functions in
+//! the global namespace are always generated. This can considerably reduce
+//! build times for applications that only need a few namespaces, but other
+//! crates relying on the rest of definitions will fail to compile.
+//!
 //! [`types`]: types/index.html
+//! [`enums`]: enums/index.html
 //! [`functions`]: functions/index.html
 //! [`Serializable`]: trait.Serializable.html
 //! [`Deserializable`]: trait.Deserializable.html
+//! [`io`]: io/index.html
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod deserializable;
 pub mod errors;
 mod generated;
+pub mod io;
 mod serializable;
 
+use alloc::vec::Vec;
+
 pub use deserializable::Deserializable;
-pub use generated::{enums, functions, types, LAYER};
+pub use generated::{enums, functions, types, CONSTRUCTORS, LAYER};
 pub use serializable::Serializable;
 
 /// This struct represents the concrete type of a vector, that is,
@@ -74,6 +115,27 @@ impl From<Vec<u8>> for Blob {
     }
 }
 
+#[cfg(feature = "tl-api")]
+impl From<enums::Bool> for bool {
+    fn from(value: enums::Bool) -> Self {
+        match value {
+            enums::Bool::BoolTrue(_) => true,
+            enums::Bool::BoolFalse(_) => false,
+        }
+    }
+}
+
+#[cfg(feature = "tl-api")]
+impl From<bool> for enums::Bool {
+    fn from(value: bool) -> Self {
+        if value {
+            enums::Bool::BoolTrue(types::BoolTrue {})
+        } else {
+            enums::Bool::BoolFalse(types::BoolFalse {})
+        }
+    }
+}
+
 /// Anything implementing this trait is identifiable by both ends (client-server)
 /// when performing Remote Procedure Calls (RPC) and transmission of objects.
 pub trait Identifiable {
@@ -85,7 +147,74 @@ pub trait Identifiable {
 /// use to perform Remote Procedure Calls (RPC), and know what the type of
 /// the response will be.
 pub trait RPC: Serializable {
+    /// The name of the function, as written in the schema (for example,
+    /// `"messages.sendMessage"`).
+    const CONSTRUCTOR_NAME: &'static str;
+
     /// The type of the "return" value coming from the other end of the
     /// connection.
     type Return: Deserializable;
+
+    /// Returns the name of the function, which is useful to label the
+    /// request being made, such as when logging it.
+    fn constructor_name(&self) -> &'static str {
+        Self::CONSTRUCTOR_NAME
+    }
+}
+
+/// Returns the name of the definition with the given constructor identifier,
+/// as written in the schema (for example, `"messages.sendMessage"`), if it's
+/// a known one.
+///
+/// This is useful to label raw identifiers, such as when logging packets.
+pub fn name_for_id(id: u32) -> Option<&'static str> {
+    CONSTRUCTORS
+        .binary_search_by_key(&id, |&(id, _)| id)
+        .ok()
+        .map(|i| CONSTRUCTORS[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_constructors_sorted() {
+        assert!(CONSTRUCTORS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    #[cfg(feature = "tl-api")]
+    fn check_bool_conversions() {
+        assert!(bool::from(enums::Bool::from(true)));
+        assert!(!bool::from(enums::Bool::from(false)));
+        assert_eq!(enums::Bool::from(true).to_bytes(), true.to_bytes());
+        assert_eq!(enums::Bool::from(false).to_bytes(), false.to_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "tl-api")]
+    fn check_name_for_id() {
+        assert_eq!(name_for_id(0x997275b5), Some("boolTrue"));
+        assert_eq!(
+            name_for_id(functions::messages::SendMessage::CONSTRUCTOR_ID),
+            Some("messages.sendMessage")
+        );
+        assert_eq!(name_for_id(0), None);
+    }
+
+    #[test]
+    #[cfg(feature = "tl-api")]
+    fn check_constructor_name() {
+        assert_eq!(
+            functions::messages::SendMessage::CONSTRUCTOR_NAME,
+            "messages.sendMessage"
+        );
+        let request = functions::help::GetConfig {};
+        assert_eq!(request.constructor_name(), "help.getConfig");
+        assert_eq!(
+            request.constructor_name(),
+            name_for_id(functions::help::GetConfig::CONSTRUCTOR_ID).unwrap()
+        );
+    }
 }
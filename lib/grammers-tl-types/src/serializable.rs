@@ -5,7 +5,9 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use std::io::{Result, Write};
+use crate::io::{Result, Write};
+use alloc::string::String;
+use alloc::vec::Vec;
 
 /// This trait allows for concrete instances to be serialized into
 /// binary data as specified by the [Binary Data Serialization].
@@ -0,0 +1,13 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runs the tests of the code generator, which lives in the build script
+//! and is otherwise never compiled as a test.
+#[allow(dead_code)]
+#[path = "../build/rustifier.rs"]
+mod rustifier;
@@ -0,0 +1,421 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Known byte vectors for a selection of real constructors.
+//!
+//! Every value is checked in both directions: serializing it must produce
+//! the exact bytes, and deserializing the bytes must produce a value which
+//! serializes back to the same bytes (the types don't implement `PartialEq`).
+#![cfg(feature = "tl-api")]
+
+use grammers_tl_types::{self as tl, Deserializable, Serializable};
+
+/// 2020-01-01T00:00:00Z.
+const DATE: i32 = 1_577_836_800;
+
+fn check<T: Serializable + Deserializable>(value: T, expected: &[u8]) -> T {
+    assert_eq!(value.to_bytes(), expected);
+
+    let value = T::from_bytes(expected).unwrap();
+    assert_eq!(value.to_bytes(), expected);
+    value
+}
+
+#[test]
+fn input_peer_empty() {
+    check::<tl::enums::InputPeer>(
+        tl::types::InputPeerEmpty {}.into(),
+        &[0xea, 0x18, 0x3b, 0x7f],
+    );
+}
+
+#[test]
+fn input_peer_self() {
+    check::<tl::enums::InputPeer>(
+        tl::types::InputPeerSelf {}.into(),
+        &[0xc9, 0x7e, 0xa0, 0x7d],
+    );
+}
+
+#[test]
+fn input_peer_user() {
+    let peer = check::<tl::enums::InputPeer>(
+        tl::types::InputPeerUser {
+            user_id: 123_456_789,
+            access_hash: 0x0102_0304_0506_0708,
+        }
+        .into(),
+        &[
+            0xe6, 0x7d, 0x8e, 0x7b, // inputPeerUser
+            0x15, 0xcd, 0x5b, 0x07, // user_id
+            0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, // access_hash
+        ],
+    );
+
+    match peer {
+        tl::enums::InputPeer::InputPeerUser(user) => {
+            assert_eq!(user.user_id, 123_456_789);
+            assert_eq!(user.access_hash, 0x0102_0304_0506_0708);
+        }
+        _ => panic!("unexpected peer"),
+    }
+}
+
+#[test]
+fn input_peer_channel() {
+    check::<tl::enums::InputPeer>(
+        tl::types::InputPeerChannel {
+            channel_id: 1,
+            access_hash: -2,
+        }
+        .into(),
+        &[
+            0xf8, 0xae, 0xad, 0x20, // inputPeerChannel
+            0x01, 0x00, 0x00, 0x00, // channel_id
+            0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // access_hash
+        ],
+    );
+}
+
+#[test]
+fn user() {
+    let user = check::<tl::enums::User>(
+        tl::types::User {
+            is_self: true,
+            contact: false,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            id: 1000,
+            access_hash: Some(0x0102_0304_0506_0708),
+            first_name: Some("Jo".into()),
+            last_name: None,
+            username: Some("jo_bot".into()),
+            phone: None,
+            photo: None,
+            status: Some(tl::types::UserStatusRecently {}.into()),
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: None,
+        }
+        .into(),
+        &[
+            0xc1, 0x58, 0x84, 0x93, // user
+            0x4b, 0x04, 0x00, 0x00, // flags: self, access_hash, first_name, username, status
+            0xe8, 0x03, 0x00, 0x00, // id
+            0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, // access_hash
+            0x02, b'J', b'o', 0x00, // first_name
+            0x06, b'j', b'o', b'_', b'b', b'o', b't', 0x00, // username
+            0xf1, 0x42, 0x6f, 0xe2, // userStatusRecently
+        ],
+    );
+
+    match user {
+        tl::enums::User::User(user) => {
+            assert!(user.is_self);
+            assert!(!user.bot);
+            assert_eq!(user.first_name.as_deref(), Some("Jo"));
+            assert_eq!(user.last_name, None);
+            assert_eq!(user.username.as_deref(), Some("jo_bot"));
+        }
+        _ => panic!("unexpected user"),
+    }
+}
+
+#[test]
+fn message() {
+    let message = check::<tl::enums::Message>(
+        tl::types::Message {
+            out: true,
+            mentioned: false,
+            media_unread: false,
+            silent: false,
+            post: false,
+            from_scheduled: false,
+            legacy: false,
+            edit_hide: false,
+            id: 42,
+            from_id: Some(1000),
+            to_id: tl::types::PeerUser { user_id: 2000 }.into(),
+            fwd_from: None,
+            via_bot_id: None,
+            reply_to_msg_id: Some(41),
+            date: DATE,
+            message: "Hello".into(),
+            media: None,
+            reply_markup: None,
+            entities: None,
+            views: None,
+            edit_date: None,
+            post_author: None,
+            grouped_id: None,
+            restriction_reason: None,
+        }
+        .into(),
+        &[
+            0x65, 0x0e, 0x2c, 0x45, // message
+            0x0a, 0x01, 0x00, 0x00, // flags: out, reply_to_msg_id, from_id
+            0x2a, 0x00, 0x00, 0x00, // id
+            0xe8, 0x03, 0x00, 0x00, // from_id
+            0x6d, 0xbc, 0xb1, 0x9d, // peerUser
+            0xd0, 0x07, 0x00, 0x00, // user_id
+            0x29, 0x00, 0x00, 0x00, // reply_to_msg_id
+            0x00, 0xe1, 0x0b, 0x5e, // date
+            0x05, b'H', b'e', b'l', b'l', b'o', 0x00, 0x00, // message
+        ],
+    );
+
+    match message {
+        tl::enums::Message::Message(message) => {
+            assert!(message.out);
+            assert_eq!(message.from_id, Some(1000));
+            assert_eq!(message.reply_to_msg_id, Some(41));
+            assert_eq!(message.via_bot_id, None);
+            assert_eq!(message.message, "Hello");
+        }
+        _ => panic!("unexpected message"),
+    }
+}
+
+#[test]
+fn update_short_message_long_text() {
+    // Strings of 254 bytes or more use the long length prefix.
+    let text = "a".repeat(254);
+    let mut expected = vec![
+        0x11, 0xbf, 0x4f, 0x91, // updateShortMessage
+        0x00, 0x00, 0x00, 0x00, // flags
+        0x07, 0x00, 0x00, 0x00, // id
+        0xe8, 0x03, 0x00, 0x00, // user_id
+        0xfe, 0xfe, 0x00, 0x00, // message length
+    ];
+    expected.extend(text.as_bytes());
+    expected.extend(&[0x00, 0x00]); // message padding
+    expected.extend(&[
+        0x0a, 0x00, 0x00, 0x00, // pts
+        0x01, 0x00, 0x00, 0x00, // pts_count
+        0x00, 0xe1, 0x0b, 0x5e, // date
+    ]);
+
+    let updates = check::<tl::enums::Updates>(
+        tl::types::UpdateShortMessage {
+            out: false,
+            mentioned: false,
+            media_unread: false,
+            silent: false,
+            id: 7,
+            user_id: 1000,
+            message: text.clone(),
+            pts: 10,
+            pts_count: 1,
+            date: DATE,
+            fwd_from: None,
+            via_bot_id: None,
+            reply_to_msg_id: None,
+            entities: None,
+        }
+        .into(),
+        &expected,
+    );
+
+    match updates {
+        tl::enums::Updates::UpdateShortMessage(update) => assert_eq!(update.message, text),
+        _ => panic!("unexpected updates"),
+    }
+}
+
+#[test]
+fn updates() {
+    let updates = check::<tl::enums::Updates>(
+        tl::types::Updates {
+            updates: vec![
+                tl::types::UpdateUserTyping {
+                    user_id: 1000,
+                    action: tl::types::SendMessageTypingAction {}.into(),
+                }
+                .into(),
+                tl::types::UpdateDeleteMessages {
+                    messages: vec![1, 2],
+                    pts: 10,
+                    pts_count: 2,
+                }
+                .into(),
+            ],
+            users: vec![tl::types::UserEmpty { id: 5 }.into()],
+            chats: Vec::new(),
+            date: DATE,
+            seq: 0,
+        }
+        .into(),
+        &[
+            0x40, 0x42, 0xae, 0x74, // updates
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x02, 0x00, 0x00, 0x00, // len
+            0x27, 0x69, 0x48, 0x5c, // updateUserTyping
+            0xe8, 0x03, 0x00, 0x00, // user_id
+            0x4e, 0x74, 0xbf, 0x16, // sendMessageTypingAction
+            0xe5, 0xb0, 0x0d, 0xa2, // updateDeleteMessages
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x02, 0x00, 0x00, 0x00, // len
+            0x01, 0x00, 0x00, 0x00, // messages[0]
+            0x02, 0x00, 0x00, 0x00, // messages[1]
+            0x0a, 0x00, 0x00, 0x00, // pts
+            0x02, 0x00, 0x00, 0x00, // pts_count
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x01, 0x00, 0x00, 0x00, // len
+            0xba, 0x50, 0x02, 0x20, // userEmpty
+            0x05, 0x00, 0x00, 0x00, // id
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x00, 0x00, 0x00, 0x00, // len
+            0x00, 0xe1, 0x0b, 0x5e, // date
+            0x00, 0x00, 0x00, 0x00, // seq
+        ],
+    );
+
+    match updates {
+        tl::enums::Updates::Updates(updates) => {
+            assert_eq!(updates.updates.len(), 2);
+            assert_eq!(updates.users.len(), 1);
+            assert!(updates.chats.is_empty());
+        }
+        _ => panic!("unexpected updates"),
+    }
+}
+
+#[test]
+fn vector_boxed() {
+    let vec = check::<Vec<i32>>(
+        vec![1, 2],
+        &[
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x02, 0x00, 0x00, 0x00, // len
+            0x01, 0x00, 0x00, 0x00, // [0]
+            0x02, 0x00, 0x00, 0x00, // [1]
+        ],
+    );
+    assert_eq!(vec, [1, 2]);
+
+    // The bare form must be rejected where the boxed one is expected.
+    assert!(Vec::<i32>::from_bytes(&[0x00, 0x00, 0x00, 0x00]).is_err());
+}
+
+#[test]
+fn vector_bare() {
+    let vec = check::<tl::RawVec<i32>>(
+        tl::RawVec(vec![1, 2]),
+        &[
+            0x02, 0x00, 0x00, 0x00, // len
+            0x01, 0x00, 0x00, 0x00, // [0]
+            0x02, 0x00, 0x00, 0x00, // [1]
+        ],
+    );
+    assert_eq!(vec.0, [1, 2]);
+}
+
+#[test]
+fn vector_boxed_nested() {
+    // `messages.getHistory` returns vectors of boxed types inside a boxed
+    // type, and each vector must have its own prefix.
+    let messages = check::<tl::enums::messages::Messages>(
+        tl::types::messages::Messages {
+            messages: vec![tl::types::MessageEmpty { id: 1 }.into()],
+            chats: Vec::new(),
+            users: Vec::new(),
+        }
+        .into(),
+        &[
+            0x87, 0x8e, 0x71, 0x8c, // messages.messages
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x01, 0x00, 0x00, 0x00, // len
+            0x54, 0xde, 0xe5, 0x83, // messageEmpty
+            0x01, 0x00, 0x00, 0x00, // id
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x00, 0x00, 0x00, 0x00, // len
+            0x15, 0xc4, 0xb5, 0x1c, // vector
+            0x00, 0x00, 0x00, 0x00, // len
+        ],
+    );
+
+    match messages {
+        tl::enums::messages::Messages::Messages(messages) => {
+            assert_eq!(messages.messages.len(), 1)
+        }
+        _ => panic!("unexpected messages"),
+    }
+}
+
+#[test]
+fn photo_size_keyword_field() {
+    // `type:string` is generated as the raw identifier `r#type`.
+    let size = check::<tl::enums::PhotoSize>(
+        tl::types::PhotoSizeEmpty { r#type: "s".into() }.into(),
+        &[
+            0x3c, 0xe2, 0x17, 0x0e, // photoSizeEmpty
+            0x01, 0x73, 0x00, 0x00, // type
+        ],
+    );
+
+    match size {
+        tl::enums::PhotoSize::PhotoSizeEmpty(size) => assert_eq!(size.r#type, "s"),
+        _ => panic!("unexpected photo size"),
+    }
+}
+
+#[cfg(feature = "impl-default")]
+#[test]
+fn send_message_default() {
+    let request = tl::functions::messages::SendMessage {
+        peer: tl::types::InputPeerSelf {}.into(),
+        message: "Hi".to_string(),
+        random_id: 1,
+        ..Default::default()
+    };
+
+    assert!(request.reply_to_msg_id.is_none());
+    match tl::enums::InputPeer::default() {
+        tl::enums::InputPeer::InputPeerEmpty(_) => {}
+        _ => panic!("unexpected default peer"),
+    }
+}
+
+#[cfg(feature = "impl-builder")]
+#[test]
+fn send_message_builder() {
+    let request = tl::functions::messages::SendMessage::builder(
+        tl::types::InputPeerSelf {}.into(),
+        "Hi".to_string(),
+        1,
+    )
+    .silent(true)
+    .reply_to_msg_id(2)
+    .build();
+
+    assert!(request.silent);
+    assert!(!request.background);
+    assert_eq!(request.reply_to_msg_id, Some(2));
+    assert!(request.entities.is_none());
+    assert_eq!(
+        request.to_bytes(),
+        [
+            0x70, 0x38, 0x0c, 0x52, // messages.sendMessage
+            0x21, 0x00, 0x00, 0x00, // flags (silent, reply_to_msg_id)
+            0xc9, 0x7e, 0xa0, 0x7d, // inputPeerSelf
+            0x02, 0x00, 0x00, 0x00, // reply_to_msg_id
+            0x02, 0x48, 0x69, 0x00, // message
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // random_id
+        ]
+    );
+}